@@ -0,0 +1,66 @@
+//! Construction benchmarks for large state machines.
+//!
+//! Run with `cargo bench --bench construction`.
+
+use std::error::Error;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State, StepDefinition}, data::DeserializeStateData};
+
+const NODES: usize = 10_000;
+const ITERATIONS: u32 = 20;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+    counter: i64,
+}
+
+impl DeserializeStateData for SharedData {
+    fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+        let data: Self = serde_json::from_str(json)?;
+        Ok(data)
+    }
+}
+
+fn increment(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.counter += 1;
+    Ok(())
+}
+
+fn node_ids() -> Vec<String> {
+    (0..NODES).map(|i| format!("Node{}", i)).collect()
+}
+
+fn bench<F: FnMut()>(name: &str, mut f: F) {
+    let mut total = Duration::ZERO;
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        f();
+        total += start.elapsed();
+    }
+    println!("{:<32} {:>12?} / iteration", name, total / ITERATIONS);
+}
+
+fn main() {
+    let ids = node_ids();
+
+    bench("step (10k nodes)", || {
+        let mut data = SharedData { counter: 0 };
+        let mut machine = StateMachine::new("Bench".to_string(), &mut data, 3);
+        for id in &ids {
+            machine.step(id, State::Task, increment, None, None, None, None);
+        }
+        black_box(&machine);
+    });
+
+    bench("steps (10k nodes)", || {
+        let mut data = SharedData { counter: 0 };
+        let mut machine = StateMachine::new("Bench".to_string(), &mut data, 3);
+        machine
+            .steps(ids.iter().map(|id| StepDefinition::new(id, State::Task, increment)))
+            .expect("unique ids");
+        black_box(&machine);
+    });
+}
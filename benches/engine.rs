@@ -0,0 +1,33 @@
+//! Engine benchmarks over representative machines.
+//!
+//! Run with `cargo bench --features bench --bench engine`. Set `BENCH_BASELINE` to a json file
+//! written by a previous run with `BENCH_SAVE` to report the regressions against it.
+
+use std::{env, fs};
+use sfn_machine::bench::{self, BenchResult};
+
+const ITERATIONS: u32 = 10;
+// slowdown tolerated before reporting a regression, in percent
+const TOLERANCE: f64 = 10.0;
+
+fn main() {
+    let results = bench::run_all(ITERATIONS);
+    for result in &results {
+        println!("{}", result);
+    }
+
+    if let Ok(path) = env::var("BENCH_SAVE") {
+        fs::write(&path, serde_json::to_string_pretty(&results).expect("serializable results")).expect("writable baseline");
+    }
+    if let Ok(path) = env::var("BENCH_BASELINE") {
+        let baseline: Vec<BenchResult> = serde_json::from_str(&fs::read_to_string(&path).expect("readable baseline"))
+            .expect("valid baseline");
+        let regressions = bench::regressions(&baseline, &results, TOLERANCE);
+        for regression in &regressions {
+            println!("regression: {} {:?} -> {:?} (+{:.1}%)", regression.name, regression.baseline, regression.current, regression.slowdown);
+        }
+        if !regressions.is_empty() {
+            std::process::exit(1);
+        }
+    }
+}
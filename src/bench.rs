@@ -0,0 +1,191 @@
+//! Representative state machines and a small timing harness, to validate the performance of
+//! the engine on a given hardware and to catch regressions between two versions.
+//!
+//! Enabled by the `bench` feature, run them with `cargo bench --features bench --bench engine`
+
+use std::error::Error;
+use std::fmt;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use crate::machine::backoff::BackoffConfig;
+use crate::machine::data::DeserializeStateData;
+use crate::machine::error::StateMachineError;
+use crate::machine::state::{State, StateMachine, StepDefinition};
+
+
+/// The shared data of the benchmark machines
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BenchData {
+    /// incremented by every successful step
+    pub counter: u64,
+    /// number of failures left before the failing steps succeed
+    pub failures_left: u32,
+    /// a payload carried through the execution
+    pub payload: Vec<u64>,
+}
+
+impl DeserializeStateData for BenchData {
+    fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+        let data: Self = serde_json::from_str(json)?;
+        Ok(data)
+    }
+}
+
+fn increment(data: &mut BenchData) -> Result<(), Box<dyn Error>> {
+    data.counter += 1;
+    Ok(())
+}
+
+fn flaky(data: &mut BenchData) -> Result<(), Box<dyn Error>> {
+    if data.failures_left > 0 {
+        data.failures_left -= 1;
+        return Err(Box::new(StateMachineError { message: String::from("Bench.Flaky") }));
+    }
+    data.counter += 1;
+    Ok(())
+}
+
+fn transform(data: &mut BenchData) -> Result<(), Box<dyn Error>> {
+    for value in data.payload.iter_mut() {
+        *value = value.wrapping_mul(31).wrapping_add(7);
+    }
+    data.counter += 1;
+    Ok(())
+}
+
+fn ids(nodes: usize) -> Vec<String> {
+    (0..nodes).map(|i| format!("Node{}", i)).collect()
+}
+
+/// A machine of `nodes` task steps executed one after the other
+pub fn linear<'a>(ids: &[String], data: &'a mut BenchData) -> StateMachine<'a, BenchData> {
+    let mut machine = StateMachine::new("BenchLinear".to_string(), data, 3);
+    machine.steps(ids.iter().map(|id| StepDefinition::new(id, State::Task, increment))).expect("unique ids");
+    machine
+}
+
+/// A machine whose single step fails `data.failures_left` times before succeeding,
+/// retried without delay
+pub fn deep_retries(data: &mut BenchData) -> StateMachine<'_, BenchData> {
+    let retries = data.failures_left as i32;
+    let mut machine = StateMachine::new("BenchRetries".to_string(), data, retries);
+    machine.set_backoff_config(BackoffConfig { max_retries: None, initial_delay: Duration::ZERO, ..Default::default() });
+    let mut step = StepDefinition::new("Flaky", State::Task, flaky);
+    step.retry = Some(vec!["Bench.Flaky"]);
+    machine.steps([step]).expect("unique ids");
+    machine
+}
+
+/// A machine of `ids.len()` steps transforming a large payload, with snapshots enabled
+pub fn large_data<'a>(ids: &[String], data: &'a mut BenchData) -> StateMachine<'a, BenchData> {
+    let mut machine = StateMachine::new("BenchLargeData".to_string(), data, 3);
+    machine.enable_snapshots();
+    machine.steps(ids.iter().map(|id| StepDefinition::new(id, State::Task, transform))).expect("unique ids");
+    machine
+}
+
+/// The timing of a benchmark
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BenchResult {
+    /// the name of the benchmark
+    pub name: String,
+    /// the number of measured iterations
+    pub iterations: u32,
+    /// the mean duration of an iteration
+    pub mean: Duration,
+    /// the fastest iteration
+    pub min: Duration,
+    /// the slowest iteration
+    pub max: Duration,
+}
+
+impl fmt::Display for BenchResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:<32} mean {:>12?}  min {:>12?}  max {:>12?}", self.name, self.mean, self.min, self.max)
+    }
+}
+
+/// Time `iterations` runs of a function, after a warm-up run
+pub fn measure<F: FnMut()>(name: &str, iterations: u32, mut f: F) -> BenchResult {
+    let iterations = iterations.max(1);
+    f();
+    let (mut total, mut min, mut max) = (Duration::ZERO, Duration::MAX, Duration::ZERO);
+    for _ in 0..iterations {
+        let started = Instant::now();
+        f();
+        let elapsed = started.elapsed();
+        total += elapsed;
+        min = min.min(elapsed);
+        max = max.max(elapsed);
+    }
+    BenchResult { name: name.to_string(), iterations, mean: total / iterations, min, max }
+}
+
+/// Run the representative benchmarks: building, executing and running compiled a linear machine of 1k steps,
+/// retrying a step 1k times, and executing 100 steps over a payload of 10k values
+pub fn run_all(iterations: u32) -> Vec<BenchResult> {
+    let linear_ids = ids(1_000);
+    let data_ids = ids(100);
+    vec![
+        measure("linear: build 1k steps", iterations, || {
+            let mut data = BenchData::default();
+            black_box(linear(&linear_ids, &mut data));
+        }),
+        measure("linear: execute 1k steps", iterations, || {
+            let mut data = BenchData::default();
+            linear(&linear_ids, &mut data).execute().expect("linear execution");
+            black_box(data.counter);
+        }),
+        measure("linear: run 1k steps compiled", iterations, {
+            let mut data = BenchData::default();
+            let compiled = linear(&linear_ids, &mut data).compile().expect("linear machine");
+            move || {
+                let mut data = BenchData::default();
+                compiled.run(&mut data).expect("compiled run");
+                black_box(data.counter);
+            }
+        }),
+        measure("deep retries: 1k retries", iterations, || {
+            let mut data = BenchData { failures_left: 1_000, ..Default::default() };
+            deep_retries(&mut data).execute().expect("retried execution");
+            black_box(data.counter);
+        }),
+        measure("large data: 100 steps, 10k values", iterations, || {
+            let mut data = BenchData { payload: (0..10_000).collect(), ..Default::default() };
+            large_data(&data_ids, &mut data).execute().expect("large data execution");
+            black_box(data.counter);
+        }),
+    ]
+}
+
+/// A benchmark slower than its baseline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Regression {
+    /// the name of the benchmark
+    pub name: String,
+    /// the mean duration of the baseline
+    pub baseline: Duration,
+    /// the mean duration of the current run
+    pub current: Duration,
+    /// the slowdown, in percent
+    pub slowdown: f64,
+}
+
+/// The benchmarks whose mean duration grew by more than `tolerance` percent over the baseline,
+/// benchmarks missing from the baseline are ignored
+pub fn regressions(baseline: &[BenchResult], current: &[BenchResult], tolerance: f64) -> Vec<Regression> {
+    current.iter().filter_map(|result| {
+        let base = baseline.iter().find(|base| base.name == result.name)?;
+        if base.mean.is_zero() {
+            return None;
+        }
+        let slowdown = (result.mean.as_secs_f64() / base.mean.as_secs_f64() - 1.0) * 100.0;
+        (slowdown > tolerance).then(|| Regression {
+            name: result.name.clone(),
+            baseline: base.mean,
+            current: result.mean,
+            slowdown,
+        })
+    }).collect()
+}
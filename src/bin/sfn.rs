@@ -0,0 +1,77 @@
+//! Command line tools for machine definitions.
+//!
+//! `sfn validate <definition.json>` lints a definition serialized from a
+//! [`MachineDefinition`](sfn_machine::machine::definition::MachineDefinition), e.g. with
+//! `serde_json::to_string(&machine.definition())`, see [`diagnose_json`](diagnostics::diagnose_json).
+//! It exits with 1 when a diagnostic has the error severity, and 2 when the definition cannot be
+//! read. The options are
+//!
+//! - `--format text|json`: the diagnostics as lines, the default, or as a json document
+//! - `--max-sleep-secs <secs>`: the maximum duration of a sleep step
+//! - `--strict`: the warnings fail the validation too
+
+use std::{env, fs, process};
+use serde::Serialize;
+use sfn_machine::machine::diagnostics::{self, SpannedDiagnostic};
+use sfn_machine::machine::lint::{LintConfig, Severity};
+
+const USAGE: &str = "usage: sfn validate <definition.json> [--format text|json] [--max-sleep-secs <secs>] [--strict]";
+
+#[derive(Debug, Serialize)]
+struct Output<'p> {
+    file: &'p str,
+    valid: bool,
+    diagnostics: Vec<SpannedDiagnostic>,
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(2);
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    if args.next().as_deref() != Some("validate") {
+        fail(USAGE);
+    }
+    let (mut path, mut format, mut strict) = (None, String::from("text"), false);
+    let mut config = LintConfig::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => format = args.next().unwrap_or_else(|| fail(USAGE)),
+            "--max-sleep-secs" => {
+                let secs = args.next().and_then(|secs| secs.parse().ok()).unwrap_or_else(|| fail(USAGE));
+                config.max_sleep_secs = Some(secs);
+            },
+            "--strict" => strict = true,
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(arg),
+            _ => fail(USAGE),
+        }
+    }
+    let path = path.unwrap_or_else(|| fail(USAGE));
+    if format != "text" && format != "json" {
+        fail(USAGE);
+    }
+    let source = fs::read_to_string(&path).unwrap_or_else(|err| fail(&format!("cannot read {}: {}", path, err)));
+
+    let diagnostics = diagnostics::diagnose_json(&source, &config);
+    let threshold = if strict { Severity::Warning } else { Severity::Error };
+    let valid = !diagnostics.iter().any(|entry| entry.diagnostic.severity >= threshold);
+    match format.as_str() {
+        "json" => {
+            let output = Output { file: &path, valid, diagnostics };
+            println!("{}", serde_json::to_string_pretty(&output).unwrap_or_default());
+        },
+        _ => {
+            for entry in &diagnostics {
+                match entry.span {
+                    Some(span) => println!("{}:{}:{}: {}", path, span.line, span.column, entry.diagnostic),
+                    None => println!("{}: {}", path, entry.diagnostic),
+                }
+            }
+        },
+    }
+    if !valid {
+        process::exit(1);
+    }
+}
@@ -10,6 +10,7 @@ A state machine is comprised of steps which can be one of the following states
 pub enum State {
     Task,
     Choice(fn() -> bool),
+    Route(u8, u8),
     Sleep(u64),
     Pass,
     Parallel,
@@ -143,4 +144,8 @@ sfn_machine.step("Node0", State::Task, StateMachine::error, None, None, Some(vec
 /// 
 /// It is a minimalistic implementation that utilizes a linked-list such that the tasks already
 /// execute is a given fashion with little work needed to defined the steps
-pub mod machine;
\ No newline at end of file
+pub mod machine;
+
+/// Representative state machines and a timing harness for performance validation
+#[cfg(feature = "bench")]
+pub mod bench;
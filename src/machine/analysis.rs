@@ -0,0 +1,188 @@
+use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use crate::machine::definition::{MachineDefinition, NodeDefinition, StateKind};
+use crate::machine::error;
+
+
+/// A step of an abstract execution path
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PathStep {
+    /// the step was executed successfully
+    Executed(String),
+    /// the condition of a choice step was false, or the execution did not take a route step
+    Skipped(String),
+    /// the step failed and the error was caught by the catch block at the given index
+    Caught {
+        /// the id of the step
+        node: String,
+        /// the index of the catch block
+        block: usize,
+    },
+    /// a step of the transaction failed, or its commit handler, and the group was rolled back,
+    /// see [`TransactionDefinition`](crate::machine::definition::TransactionDefinition)
+    RolledBack(String),
+}
+
+/// The terminal outcome of an abstract execution path
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PathOutcome {
+    /// the execution completed
+    Succeeded,
+    /// the execution failed at the given step, or at the given transaction when its
+    /// `on_failure` handler failed
+    Failed(String),
+}
+
+/// An abstract execution path through a machine definition
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ExecutionPath {
+    /// the visited steps, in order
+    pub steps: Vec<PathStep>,
+    /// how the execution ended
+    pub outcome: PathOutcome,
+}
+
+/// The paths found while exploring a definition, see [`MachineDefinition::explore`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Exploration {
+    /// the explored paths
+    pub paths: Vec<ExecutionPath>,
+    /// whether the exploration stopped at the bound before covering every path
+    pub truncated: bool,
+}
+
+/// The result of [`MachineDefinition::equivalent`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Equivalence {
+    /// paths only reachable in the first definition
+    pub only_in_left: Vec<ExecutionPath>,
+    /// paths only reachable in the second definition
+    pub only_in_right: Vec<ExecutionPath>,
+    /// whether one of the explorations hit the bound, the result then only holds up to the bound
+    pub truncated: bool,
+}
+
+impl Equivalence {
+    /// Whether both definitions have the same paths and outcomes
+    pub fn is_equivalent(&self) -> bool {
+        self.only_in_left.is_empty() && self.only_in_right.is_empty()
+    }
+}
+
+impl MachineDefinition {
+    /// Explore the abstract execution paths of the definition, up to `max_paths` paths.
+    ///
+    /// The functions of the steps are opaque, so every outcome is considered possible:
+    /// choice conditions can be true or false, route steps can be taken or not, and the
+    /// functions can succeed or fail. A failure is either caught by one of the catch blocks of
+    /// the step, whose handler can succeed or fail in turn, or ends the execution. Within a
+    /// transaction the failures, and those of the commit handler, roll the group back instead
+    /// and the execution resumes after it, unless the `on_failure` handler fails. Retries do
+    /// not create new paths as they eventually end in a success or a failure.
+    pub fn explore(&self, max_paths: usize) -> Exploration {
+        let mut exploration = Exploration { paths: Vec::new(), truncated: false };
+        explore_from(self, 0, &mut Vec::new(), max_paths, &mut exploration);
+        exploration
+    }
+
+    /// Check whether two definitions are behaviorally equivalent, that is they reach the same
+    /// steps in the same order with the same terminal outcomes, exploring up to `max_paths`
+    /// paths in each of them. Steps are identified by their ids.
+    pub fn equivalent(&self, other: &MachineDefinition, max_paths: usize) -> Equivalence {
+        let (left, right) = (self.explore(max_paths), other.explore(max_paths));
+        let left_paths: HashSet<&ExecutionPath> = left.paths.iter().collect();
+        let right_paths: HashSet<&ExecutionPath> = right.paths.iter().collect();
+        Equivalence {
+            only_in_left: left.paths.iter().filter(|p| !right_paths.contains(p)).cloned().collect(),
+            only_in_right: right.paths.iter().filter(|p| !left_paths.contains(p)).cloned().collect(),
+            truncated: left.truncated || right.truncated,
+        }
+    }
+}
+
+// Whether the step runs a function, which may fail
+pub(crate) fn has_function(node: &NodeDefinition) -> bool {
+    matches!(node.state, StateKind::Task | StateKind::Choice | StateKind::Route(..))
+}
+
+fn catches_everything(node: &NodeDefinition) -> bool {
+    node.catch.iter().any(|patterns| error::matches_everything(patterns))
+}
+
+fn finish(steps: &[PathStep], outcome: PathOutcome, max_paths: usize, exploration: &mut Exploration) {
+    if exploration.paths.len() >= max_paths {
+        exploration.truncated = true;
+        return;
+    }
+    exploration.paths.push(ExecutionPath { steps: steps.to_vec(), outcome });
+}
+
+fn explore_from(definition: &MachineDefinition, index: usize, steps: &mut Vec<PathStep>, max_paths: usize, exploration: &mut Exploration) {
+    if exploration.truncated {
+        return;
+    }
+    // the execution stops when reaching the last step or a step marked as the end
+    let node = match definition.nodes.get(index) {
+        Some(node) if !node.end => node,
+        _ => return finish(steps, PathOutcome::Succeeded, max_paths, exploration),
+    };
+    let failed = PathOutcome::Failed(node.id.clone());
+
+    // the next function is executed first, its failures are never caught. The resulting path
+    // is identical to an uncaught failure of the step function, which is explored below
+    let uncaught = has_function(node) && !catches_everything(node);
+    if node.next && !uncaught {
+        fail(definition, index, steps, failed.clone(), max_paths, exploration);
+    }
+
+    // a step guarded by a feature flag is skipped when the flag is off, its fallback function
+    // is as opaque as the function of the step
+    if matches!(node.state, StateKind::Choice | StateKind::Route(..)) || node.flag.is_some() {
+        steps.push(PathStep::Skipped(node.id.clone()));
+        advance(definition, index, steps, max_paths, exploration);
+        steps.pop();
+    }
+
+    steps.push(PathStep::Executed(node.id.clone()));
+    advance(definition, index, steps, max_paths, exploration);
+    steps.pop();
+
+    if has_function(node) {
+        for block in 0..node.catch.len() {
+            steps.push(PathStep::Caught { node: node.id.clone(), block });
+            advance(definition, index, steps, max_paths, exploration);
+            // the handler of the catch block failed
+            fail(definition, index, steps, failed.clone(), max_paths, exploration);
+            steps.pop();
+        }
+        if uncaught {
+            fail(definition, index, steps, failed, max_paths, exploration);
+        }
+    }
+}
+
+// The step at `index` succeeded, the commit handler of the transaction it closes can still fail
+fn advance(definition: &MachineDefinition, index: usize, steps: &mut Vec<PathStep>, max_paths: usize, exploration: &mut Exploration) {
+    let closing = definition.nodes[index].transaction.as_ref()
+        .and_then(|name| definition.transactions.iter().find(|transaction| &transaction.name == name))
+        .filter(|transaction| transaction.steps.last() == Some(&definition.nodes[index].id));
+    if let Some(transaction) = closing.filter(|transaction| transaction.on_commit) {
+        fail(definition, index, steps, PathOutcome::Failed(transaction.name.clone()), max_paths, exploration);
+    }
+    explore_from(definition, index + 1, steps, max_paths, exploration);
+}
+
+// The step at `index` failed: the execution ends, or the transaction of the step is rolled back
+// and the execution resumes after its last step
+fn fail(definition: &MachineDefinition, index: usize, steps: &mut Vec<PathStep>, failed: PathOutcome, max_paths: usize, exploration: &mut Exploration) {
+    let name = match &definition.nodes[index].transaction {
+        Some(name) => name,
+        None => return finish(steps, failed, max_paths, exploration),
+    };
+    let last = definition.nodes.iter().rposition(|node| node.transaction.as_ref() == Some(name)).unwrap_or(index);
+    steps.push(PathStep::RolledBack(name.clone()));
+    explore_from(definition, last + 1, steps, max_paths, exploration);
+    // the on_failure handler failed
+    finish(steps, PathOutcome::Failed(name.clone()), max_paths, exploration);
+    steps.pop();
+}
@@ -0,0 +1,92 @@
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::machine::data;
+use crate::machine::definition::MachineDefinition;
+use crate::machine::error::StateMachineError;
+use crate::machine::history::ExecutionHistory;
+use crate::machine::state::StateMachine;
+
+
+/// The version of the archive format written by [`StateMachine::export_execution`]
+pub const ARCHIVE_VERSION: u32 = 1;
+
+/// An execution bundled with the definition of the machine which ran it, to debug or
+/// archive it in another environment.
+///
+/// The history carries the tags of the execution, and its snapshots when they were enabled
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionArchive {
+    /// the version of the archive format
+    pub version: u32,
+    /// the definition of the machine at the time of the execution
+    pub definition: MachineDefinition,
+    /// the history of the execution
+    pub history: ExecutionHistory,
+}
+
+fn archive_error(message: String) -> StateMachineError {
+    StateMachineError { message }
+}
+
+impl ExecutionArchive {
+    /// Render the archive as json
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Read an archive from json, rejecting an unsupported format version
+    pub fn from_json(json: &str) -> Result<Self, StateMachineError> {
+        ExecutionArchive::decode::<data::Json>(json.as_bytes())
+    }
+
+    /// Encode the archive with the given codec
+    pub fn encode<C: data::DataCodec>(&self) -> Result<Vec<u8>, StateMachineError> {
+        C::encode(self).map_err(|err| archive_error(format!("Cannot encode the execution archive as {}: {}", C::NAME, err)))
+    }
+
+    /// Decode an archive encoded with the given codec, rejecting an unsupported format version
+    pub fn decode<C: data::DataCodec>(bytes: &[u8]) -> Result<Self, StateMachineError> {
+        let archive: ExecutionArchive = C::decode(bytes)
+            .map_err(|err| archive_error(format!("Invalid execution archive: {}", err)))?;
+        if archive.version != ARCHIVE_VERSION {
+            return Err(archive_error(format!("Unsupported execution archive version: {}", archive.version)));
+        }
+        Ok(archive)
+    }
+
+    /// Write the archive to a file
+    pub fn write_to<P: AsRef<Path>>(&self, path: P) -> Result<(), StateMachineError> {
+        fs::write(path, self.to_json()).map_err(|err| archive_error(err.to_string()))
+    }
+
+    /// Read an archive from a file
+    pub fn read_from<P: AsRef<Path>>(path: P) -> Result<Self, StateMachineError> {
+        let json = fs::read_to_string(path).map_err(|err| archive_error(err.to_string()))?;
+        ExecutionArchive::from_json(&json)
+    }
+}
+
+impl<'a, T: data::DeserializeStateData> StateMachine<'a, T> {
+    /// Bundle the last execution with the definition of the machine
+    pub fn export_execution(&self) -> ExecutionArchive {
+        ExecutionArchive {
+            version: ARCHIVE_VERSION,
+            definition: self.definition(),
+            history: self.history.clone(),
+        }
+    }
+
+    /// Load an archived execution as the last execution of the machine, for instance to
+    /// inspect it or to replay it with [`StateMachine::replay_with_live_handlers`].
+    ///
+    /// The archive is rejected when the definition of the machine differs from the archived one
+    pub fn import_execution(&mut self, archive: ExecutionArchive) -> Result<(), StateMachineError> {
+        let diff = archive.definition.diff(&self.definition());
+        if !diff.is_empty() {
+            return Err(archive_error(format!("The archived execution was run by a different definition:\n{}", diff)));
+        }
+        self.history = archive.history;
+        Ok(())
+    }
+}
@@ -1,44 +1,402 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::task::{Context, Poll, Waker};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 
-macro_rules! ifelse {
-    ($test:expr => $true_expr:expr; $false_expr:expr) => {
-        if $test {
-            $true_expr
+/// Configuration of the exponential backoff
+#[derive(Debug, Clone, Copy)]
+pub struct BackoffConfig {
+    /// upper bound of the number of retries, `None` removes the cap
+    pub max_retries: Option<u32>,
+    /// delay before the first retry
+    pub initial_delay: Duration,
+    /// factor applied to the delay after every retry
+    pub multiplier: u32,
+    /// hook receiving the events emitted while retrying
+    pub on_event: Option<fn(&BackoffEvent)>,
+}
+
+impl Default for BackoffConfig {
+    fn default() -> Self {
+        BackoffConfig {
+            max_retries: Some(5),
+            initial_delay: Duration::from_secs(1),
+            multiplier: 2,
+            on_event: None,
+        }
+    }
+}
+
+impl BackoffConfig {
+    fn emit(&self, event: BackoffEvent) {
+        if let Some(on_event) = self.on_event {
+            on_event(&event);
         }
-        else {
-            $false_expr
+    }
+
+    /// Resolve the number of retries to perform, clamped to the configured cap
+    fn retries(&self, retries: Option<i32>) -> (u32, Option<BackoffWarning>) {
+        let requested = retries.map(|retries| retries.max(0) as u32);
+        match (requested, self.max_retries) {
+            (Some(requested), Some(max_retries)) if requested > max_retries => {
+                let warning = BackoffWarning::RetriesClamped { requested, max_retries };
+                self.emit(BackoffEvent::Warning(warning));
+                (max_retries, Some(warning))
+            }
+            (Some(requested), _) => (requested, None),
+            (None, Some(max_retries)) => (max_retries, None),
+            (None, None) => (BackoffConfig::default().max_retries.unwrap_or_default(), None),
         }
     }
 }
 
+/// Events emitted by the exponential backoff, see [`BackoffConfig::on_event`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackoffEvent {
+    /// an attempt failed and the operation is retried after the delay
+    Retrying {
+        /// the attempt which failed
+        attempt: u32,
+        /// the delay before the next attempt
+        delay: Duration,
+    },
+    /// a warning was raised
+    Warning(BackoffWarning),
+}
+
+/// Warnings raised while resolving the backoff configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackoffWarning {
+    /// the requested number of retries exceeded the configured cap
+    RetriesClamped {
+        /// number of retries requested
+        requested: u32,
+        /// number of retries performed
+        max_retries: u32,
+    },
+}
+
+/// Summary of an operation which eventually succeeded
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BackoffReport {
+    /// number of times the operation was executed
+    pub attempts: u32,
+    /// total time spent waiting between the attempts
+    pub total_delay: Duration,
+    /// set when the requested number of retries was clamped
+    pub warning: Option<BackoffWarning>,
+}
+
+/// The last failure of an operation which could not be completed within the retries
+#[derive(Debug)]
+pub struct BackoffFailure<E> {
+    /// the error returned by the last attempt
+    pub error: E,
+    /// number of times the operation was executed
+    pub attempts: u32,
+    /// total time spent waiting between the attempts
+    pub total_delay: Duration,
+    /// set when the requested number of retries was clamped
+    pub warning: Option<BackoffWarning>,
+}
+
+impl<E> BackoffFailure<E> {
+    fn new(error: E, report: BackoffReport) -> Self {
+        BackoffFailure { error, attempts: report.attempts, total_delay: report.total_delay, warning: report.warning }
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for BackoffFailure<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
 /// Exponential backoff which defines the a simple backoff logic for handling certain processes
 /// which might have failed with a recoverable error.
 /// 
 /// It accepts an operation (a method) which is of the form
 /// 
 /// fn(&mut T) -> Result<(), Box<dyn Error>>;
-pub fn exponential_backoff<F, E, T>(mut operation: F, data: &mut T, retries: Option<i32>) -> Result<(), E>
+///
+/// The operation is executed at most `retries + 1` times, the delay between two attempts
+/// doubling every time. When every attempt fails, the error of the last attempt is returned
+/// along with the number of attempts and the total delay.
+///
+/// The default [`BackoffConfig`] is used, see [`exponential_backoff_with`] to customize it.
+pub fn exponential_backoff<F, E, T>(operation: F, data: &mut T, retries: Option<i32>) -> Result<BackoffReport, BackoffFailure<E>>
+where
+    F: FnMut(&mut T) -> Result<(), E>,
+{
+    exponential_backoff_with(operation, data, retries, &BackoffConfig::default())
+}
+
+/// Exponential backoff using the provided configuration, see [`exponential_backoff`]
+pub fn exponential_backoff_with<F, E, T>(operation: F, data: &mut T, retries: Option<i32>, config: &BackoffConfig) -> Result<BackoffReport, BackoffFailure<E>>
+where
+    F: FnMut(&mut T) -> Result<(), E>,
+{
+    backoff_with_hints(operation, data, retries, config, |_| (true, None))
+}
+
+/// Exponential backoff where every error tells whether it can be retried, and may impose the
+/// delay before the next attempt, e.g. a retry-after delay given by a server
+pub(crate) fn backoff_with_hints<F, E, T, H>(mut operation: F, data: &mut T, retries: Option<i32>, config: &BackoffConfig, mut hint: H) -> Result<BackoffReport, BackoffFailure<E>>
 where
     F: FnMut(&mut T) -> Result<(), E>,
+    H: FnMut(&E) -> (bool, Option<Duration>),
 {
-    let mut _retries = 0;
-    let mut max_retries = 5;
-    let mut delay = Duration::from_secs(1);
-    ifelse!(retries.unwrap() > max_retries => println!("Provided number of retries can not be more than 5"); max_retries = retries.unwrap());
-
-    while _retries < max_retries {
-        match operation(data) {
-            Ok(_) => return Ok(()), // Operation successful, exit early
-            Err(_) => {
-                println!("Operation failed, retrying ...");
-                thread::sleep(delay);
-                _retries += 1;
-                delay *= 2; // Exponential backoff
+    let (max_retries, warning) = config.retries(retries);
+    let mut report = BackoffReport { warning, ..Default::default() };
+    let mut delay = config.initial_delay;
+
+    loop {
+        report.attempts += 1;
+        let error = match operation(data) {
+            Ok(_) => return Ok(report), // Operation successful, exit early
+            Err(error) => error,
+        };
+        let (retryable, retry_after) = hint(&error);
+        if report.attempts > max_retries || !retryable {
+            return Err(BackoffFailure::new(error, report));
+        }
+
+        let wait = retry_after.unwrap_or(delay);
+        config.emit(BackoffEvent::Retrying { attempt: report.attempts, delay: wait });
+        thread::sleep(wait);
+        report.total_delay = report.total_delay.saturating_add(wait);
+        // Exponential backoff, saturating rather than overflowing on long policies
+        delay = delay.checked_mul(config.multiplier).unwrap_or(Duration::MAX);
+    }
+}
+
+/// The error returned by [`exponential_backoff_async`]
+#[derive(Debug)]
+pub enum BackoffError<E> {
+    /// the operation kept failing, holds the last failure
+    Failed(BackoffFailure<E>),
+    /// the retries were interrupted through the cancellation token
+    Cancelled,
+}
+
+/// A timer used by [`exponential_backoff_async`] to wait between two attempts.
+///
+/// It can be implemented on top of the timer of any async runtime
+pub trait Timer {
+    /// Returns a future which completes once the duration has elapsed
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>>;
+}
+
+/// A runtime agnostic timer, the sleeps are waited by a single background thread shared by
+/// every timer, started with the first sleep
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ThreadTimer;
+
+impl Timer for ThreadTimer {
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        Box::pin(ThreadSleep { duration, shared: None })
+    }
+}
+
+// Whether the sleep elapsed, and the waker of the task awaiting it
+type SleepState = Arc<Mutex<(bool, Option<Waker>)>>;
+
+// The channel of the background thread of the thread timers, receiving the sleeps to wake up
+fn timer_thread() -> &'static Sender<(Instant, SleepState)> {
+    static SLEEPS: OnceLock<Sender<(Instant, SleepState)>> = OnceLock::new();
+    SLEEPS.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel::<(Instant, SleepState)>();
+        thread::spawn(move || {
+            let mut pending: Vec<(Instant, SleepState)> = Vec::new();
+            loop {
+                let now = Instant::now();
+                pending.retain(|(deadline, state)| {
+                    if *deadline > now {
+                        return true;
+                    }
+                    let mut state = state.lock().unwrap();
+                    state.0 = true;
+                    if let Some(waker) = state.1.take() {
+                        waker.wake();
+                    }
+                    false
+                });
+                // the sender lives as long as the process, the channel is never disconnected
+                let received = match pending.iter().map(|(deadline, _)| *deadline).min() {
+                    Some(next) => receiver.recv_timeout(next.saturating_duration_since(now)).ok(),
+                    None => receiver.recv().ok(),
+                };
+                pending.extend(received);
             }
+        });
+        sender
+    })
+}
+
+struct ThreadSleep {
+    duration: Duration,
+    shared: Option<SleepState>,
+}
+
+impl Future for ThreadSleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        match &self.shared {
+            Some(shared) => {
+                let mut state = shared.lock().unwrap();
+                if state.0 {
+                    return Poll::Ready(());
+                }
+                state.1 = Some(cx.waker().clone());
+                Poll::Pending
+            }
+            None => {
+                let shared = Arc::new(Mutex::new((false, Some(cx.waker().clone()))));
+                // a sleep too long to be represented never elapses
+                if let Some(deadline) = Instant::now().checked_add(self.duration) {
+                    let _ = timer_thread().send((deadline, shared.clone()));
+                }
+                self.shared = Some(shared);
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// A token used to cancel pending retries.
+///
+/// Clones share the same state, cancelling one of them cancels all of them
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<CancellationState>,
+}
+
+#[derive(Debug, Default)]
+struct CancellationState {
+    cancelled: AtomicBool,
+    // the wakers of the pending sleeps, by registration
+    wakers: Mutex<BTreeMap<u64, Waker>>,
+    registrations: AtomicU64,
+}
+
+impl CancellationToken {
+    /// Create a new token which is not cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the token, waking up every task waiting on it
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        let wakers = std::mem::take(&mut *self.inner.wakers.lock().unwrap());
+        for waker in wakers.into_values() {
+            waker.wake();
+        }
+    }
+
+    /// Whether the token has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    // Register the waker of a pending sleep, replacing the one of its previous poll
+    fn register(&self, registration: &mut Option<u64>, waker: &Waker) {
+        let key = *registration.get_or_insert_with(|| self.inner.registrations.fetch_add(1, Ordering::SeqCst));
+        let mut wakers = self.inner.wakers.lock().unwrap();
+        match wakers.get_mut(&key) {
+            Some(registered) if registered.will_wake(waker) => (),
+            Some(registered) => *registered = waker.clone(),
+            None => { wakers.insert(key, waker.clone()); },
+        }
+    }
+
+    fn unregister(&self, registration: Option<u64>) {
+        if let Some(key) = registration {
+            self.inner.wakers.lock().unwrap().remove(&key);
+        }
+    }
+}
+
+/// Waits for the sleep to complete, returns false if the token got cancelled in the meantime
+struct CancellableSleep<'t> {
+    sleep: Pin<Box<dyn Future<Output = ()> + Send>>,
+    token: &'t CancellationToken,
+    registration: Option<u64>,
+}
+
+impl Drop for CancellableSleep<'_> {
+    fn drop(&mut self) {
+        self.token.unregister(self.registration);
+    }
+}
+
+impl Future for CancellableSleep<'_> {
+    type Output = bool;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<bool> {
+        if self.token.is_cancelled() {
+            return Poll::Ready(false);
+        }
+        if self.sleep.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(true);
         }
+        let this = &mut *self;
+        this.token.register(&mut this.registration, cx.waker());
+        // the token could have been cancelled before the waker got registered
+        if self.token.is_cancelled() {
+            return Poll::Ready(false);
+        }
+        Poll::Pending
     }
+}
+
+/// Asynchronous variant of [`exponential_backoff_with`].
+///
+/// The delays between attempts are awaited on the provided timer instead of blocking the
+/// thread, and the retries stop as soon as the cancellation token is cancelled, including
+/// while waiting for the next attempt.
+pub async fn exponential_backoff_async<F, E, T>(
+    mut operation: F,
+    data: &mut T,
+    retries: Option<i32>,
+    config: &BackoffConfig,
+    timer: &dyn Timer,
+    cancellation: &CancellationToken,
+) -> Result<BackoffReport, BackoffError<E>>
+where
+    F: FnMut(&mut T) -> Result<(), E>,
+{
+    let (max_retries, warning) = config.retries(retries);
+    let mut report = BackoffReport { warning, ..Default::default() };
+    let mut delay = config.initial_delay;
 
-    Err(operation(data).err().unwrap_or_else(|| panic!("the operation could not be completed due to an unrecoverable error")))
-}
\ No newline at end of file
+    loop {
+        if cancellation.is_cancelled() {
+            return Err(BackoffError::Cancelled);
+        }
+        report.attempts += 1;
+        let error = match operation(data) {
+            Ok(_) => return Ok(report),
+            Err(error) => error,
+        };
+        if report.attempts > max_retries {
+            return Err(BackoffError::Failed(BackoffFailure::new(error, report)));
+        }
+
+        config.emit(BackoffEvent::Retrying { attempt: report.attempts, delay });
+        let sleep = CancellableSleep { sleep: timer.sleep(delay), token: cancellation, registration: None };
+        if !sleep.await {
+            return Err(BackoffError::Cancelled);
+        }
+        report.total_delay = report.total_delay.saturating_add(delay);
+        delay = delay.checked_mul(config.multiplier).unwrap_or(Duration::MAX);
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use crate::machine::backoff::CancellationToken;
+use crate::machine::data;
+use crate::machine::history::ExecutionHistory;
+use crate::machine::state::StateMachine;
+use crate::machine::stats::ExecutorStats;
+
+
+/// The progress of a batch run, reported after every execution
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchProgress {
+    /// the number of inputs executed, including the ones completed by a previous run
+    pub completed: usize,
+    /// the number of inputs
+    pub total: usize,
+    /// the time elapsed since the start of the run
+    pub elapsed: Duration,
+    /// the estimated time left, from the average duration of the executions of this run
+    pub eta: Duration,
+}
+
+/// The options of a batch run, see [`run_batch`]
+#[derive(Debug, Clone)]
+pub struct BatchOptions {
+    /// the number of inputs executed at the same time, at least one
+    pub concurrency: usize,
+    /// stop starting new executions once one of them failed, the remaining inputs are skipped
+    pub stop_on_failure: bool,
+    /// a file recording the indexes of the inputs executed successfully. A run resumed with
+    /// the same file does not execute them again, failed inputs are executed again
+    pub progress_file: Option<PathBuf>,
+    /// called after every execution
+    pub on_progress: Option<fn(&BatchProgress)>,
+    /// stops starting new executions once cancelled, the remaining inputs are skipped
+    pub cancellation: Option<CancellationToken>,
+    /// gauges reporting the queue and workers of the run, and the executions of its machines
+    pub stats: Option<Arc<ExecutorStats>>,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        BatchOptions { concurrency: 1, stop_on_failure: false, progress_file: None, on_progress: None, cancellation: None, stats: None }
+    }
+}
+
+/// The status of an input of a batch run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BatchStatus {
+    /// the execution succeeded
+    Succeeded,
+    /// the execution failed with the given error
+    Failed(String),
+    /// the input was not executed
+    Skipped,
+    /// the input was executed successfully by a previous run recorded in the progress file,
+    /// it was not executed again
+    Completed,
+}
+
+/// The result of the execution of an input of a batch run
+#[derive(Debug)]
+pub struct BatchItem<T> {
+    /// the index of the input
+    pub index: usize,
+    /// the status of the execution
+    pub status: BatchStatus,
+    /// the shared data after the execution, or the untouched input when it was skipped
+    pub data: T,
+    /// the history of the execution, `None` when it was skipped
+    pub history: Option<ExecutionHistory>,
+}
+
+/// The results of a batch run, in the order of the inputs
+#[derive(Debug)]
+pub struct BatchReport<T> {
+    /// the result of every input
+    pub items: Vec<BatchItem<T>>,
+    /// the error which stopped the run while recording an input in the progress file, the
+    /// input is reported as succeeded but a resumed run executes it again
+    pub progress_error: Option<io::Error>,
+}
+
+impl<T> BatchReport<T> {
+    fn count(&self, status: fn(&BatchStatus) -> bool) -> usize {
+        self.items.iter().filter(|item| status(&item.status)).count()
+    }
+
+    /// Number of successful executions
+    pub fn succeeded(&self) -> usize {
+        self.count(|status| *status == BatchStatus::Succeeded)
+    }
+
+    /// Number of failed executions
+    pub fn failed(&self) -> usize {
+        self.count(|status| matches!(status, BatchStatus::Failed(_)))
+    }
+
+    /// Number of skipped inputs
+    pub fn skipped(&self) -> usize {
+        self.count(|status| *status == BatchStatus::Skipped)
+    }
+
+    /// Number of inputs completed by a previous run
+    pub fn resumed(&self) -> usize {
+        self.count(|status| *status == BatchStatus::Completed)
+    }
+}
+
+/// The indexes recorded in a progress file, a missing file records none.
+/// Lines left incomplete by a crash are ignored
+fn read_progress(path: &Path) -> io::Result<HashSet<usize>> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(content.lines().filter_map(|line| line.trim().parse().ok()).collect()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(HashSet::new()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Run a state machine over many inputs, typically for backfills and migrations.
+///
+/// `build` creates the state machine executing an input, which it receives as the shared data.
+/// The inputs are executed by `options.concurrency` threads, in the order of the inputs.
+///
+/// It fails when the progress file cannot be read or opened. A failure to record an input in
+/// it stops the run, the results are returned with the error, see [`BatchReport::progress_error`]
+pub fn run_batch<T, F>(inputs: Vec<T>, options: &BatchOptions, build: F) -> io::Result<BatchReport<T>>
+where
+    T: data::DeserializeStateData + Send,
+    F: for<'a> Fn(&'a mut T) -> StateMachine<'a, T> + Sync,
+{
+    let total = inputs.len();
+    let mut done = match &options.progress_file {
+        Some(path) => read_progress(path)?,
+        None => HashSet::new(),
+    };
+    // a progress file recorded with more inputs does not complete inputs that do not exist
+    done.retain(|index| *index < total);
+    let progress_file: Option<Mutex<File>> = match &options.progress_file {
+        Some(path) => Some(Mutex::new(OpenOptions::new().create(true).append(true).open(path)?)),
+        None => None,
+    };
+    let mut items = Vec::with_capacity(total);
+    let mut pending = Vec::with_capacity(total);
+    for (index, data) in inputs.into_iter().enumerate() {
+        if done.contains(&index) {
+            items.push(BatchItem { index, status: BatchStatus::Completed, data, history: None });
+        } else {
+            pending.push((index, Mutex::new(Some(data))));
+        }
+    }
+
+    let results: Mutex<Vec<BatchItem<T>>> = Mutex::new(Vec::with_capacity(pending.len()));
+    let write_error: Mutex<Option<io::Error>> = Mutex::new(None);
+    let next = AtomicUsize::new(0);
+    let executed = AtomicUsize::new(0);
+    let stopped = AtomicBool::new(false);
+    let started = Instant::now();
+    let cancelled = || options.cancellation.as_ref().is_some_and(CancellationToken::is_cancelled);
+
+    let workers = options.concurrency.clamp(1, pending.len().max(1));
+    let stats = options.stats.as_deref();
+    if let Some(stats) = stats {
+        stats.update(|gauges| {
+            gauges.queued = gauges.queued.saturating_add(pending.len());
+            gauges.workers = gauges.workers.saturating_add(workers);
+        });
+    }
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                if stopped.load(Ordering::SeqCst) || cancelled() {
+                    break;
+                }
+                let (index, mut data) = match pending.get(next.fetch_add(1, Ordering::SeqCst)) {
+                    Some((index, input)) => (*index, input.lock().unwrap().take().unwrap()),
+                    None => break,
+                };
+                let busy = stats.map(|stats| {
+                    stats.update(|gauges| gauges.queued = gauges.queued.saturating_sub(1));
+                    stats.busy_worker()
+                });
+                let (result, history) = {
+                    let mut machine = build(&mut data);
+                    if let Some(stats) = &options.stats {
+                        machine.set_executor_stats(stats.clone());
+                    }
+                    let result = machine.execute();
+                    (result, machine.history().clone())
+                };
+                let status = match result {
+                    Ok(()) => BatchStatus::Succeeded,
+                    Err(err) => {
+                        if options.stop_on_failure {
+                            stopped.store(true, Ordering::SeqCst);
+                        }
+                        BatchStatus::Failed(err.to_string())
+                    },
+                };
+                if let (BatchStatus::Succeeded, Some(file)) = (&status, &progress_file) {
+                    let mut file = file.lock().unwrap();
+                    if let Err(err) = writeln!(file, "{}", index).and_then(|_| file.flush()) {
+                        stopped.store(true, Ordering::SeqCst);
+                        *write_error.lock().unwrap() = Some(err);
+                    }
+                }
+                results.lock().unwrap().push(BatchItem { index, status, data, history: Some(history) });
+                drop(busy);
+
+                let executed = executed.fetch_add(1, Ordering::SeqCst) + 1;
+                if let Some(on_progress) = options.on_progress {
+                    let elapsed = started.elapsed();
+                    let remaining = (pending.len() - executed) as u32;
+                    on_progress(&BatchProgress {
+                        completed: done.len() + executed,
+                        total,
+                        elapsed,
+                        eta: elapsed / executed as u32 * remaining,
+                    });
+                }
+            });
+        }
+    });
+
+    if let Some(stats) = stats {
+        // the inputs skipped after a failure or a cancellation leave the queue
+        let skipped = pending.iter().filter(|(_, input)| input.lock().unwrap().is_some()).count();
+        stats.update(|gauges| {
+            gauges.queued = gauges.queued.saturating_sub(skipped);
+            gauges.workers = gauges.workers.saturating_sub(workers);
+        });
+    }
+    items.extend(results.into_inner().unwrap());
+    for (index, input) in pending {
+        if let Some(data) = input.into_inner().unwrap() {
+            items.push(BatchItem { index, status: BatchStatus::Skipped, data, history: None });
+        }
+    }
+    items.sort_by_key(|item| item.index);
+    Ok(BatchReport { items, progress_error: write_error.into_inner().unwrap() })
+}
@@ -0,0 +1,144 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crate::machine::analysis::{ExecutionPath, PathOutcome, PathStep};
+use crate::machine::definition::MachineDefinition;
+
+
+/// A property checked on every explored path of a definition, see [`MachineDefinition::check`].
+///
+/// A step counts as executed when its function ran, successfully or with an error caught by
+/// one of its catch blocks
+#[derive(Debug, Clone)]
+pub enum Invariant {
+    /// whenever `trigger` is executed, `response` is executed after it,
+    /// e.g. the compensation always runs after the payment
+    Response {
+        /// the step requiring a response
+        trigger: String,
+        /// the step which must follow
+        response: String,
+    },
+    /// `node` is only executed after `required` was executed
+    Precedence {
+        /// the step which must come first
+        required: String,
+        /// the guarded step
+        node: String,
+    },
+    /// the step is never executed
+    Absence(String),
+    /// the execution never fails
+    NeverFails,
+    /// a custom property
+    Custom {
+        /// the name of the property, used in the report
+        name: String,
+        /// whether the property holds for a path
+        holds: fn(&ExecutionPath) -> bool,
+    },
+}
+
+fn executed(step: &PathStep) -> Option<&str> {
+    match step {
+        PathStep::Executed(node) | PathStep::Caught { node, .. } => Some(node),
+        PathStep::Skipped(_) | PathStep::RolledBack(_) => None,
+    }
+}
+
+impl Invariant {
+    /// Whether the invariant holds for a path
+    pub fn holds(&self, path: &ExecutionPath) -> bool {
+        let nodes: Vec<&str> = path.steps.iter().filter_map(executed).collect();
+        match self {
+            Invariant::Response { trigger, response } => match nodes.iter().rposition(|node| node == trigger) {
+                Some(index) => nodes[index + 1..].contains(&response.as_str()),
+                None => true,
+            },
+            Invariant::Precedence { required, node } => match nodes.iter().position(|n| n == node) {
+                Some(index) => nodes[..index].contains(&required.as_str()),
+                None => true,
+            },
+            Invariant::Absence(node) => !nodes.contains(&node.as_str()),
+            Invariant::NeverFails => path.outcome == PathOutcome::Succeeded,
+            Invariant::Custom { holds, .. } => holds(path),
+        }
+    }
+}
+
+impl fmt::Display for Invariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Invariant::Response { trigger, response } => write!(f, "{} is always followed by {}", trigger, response),
+            Invariant::Precedence { required, node } => write!(f, "{} is always preceded by {}", node, required),
+            Invariant::Absence(node) => write!(f, "{} is never executed", node),
+            Invariant::NeverFails => write!(f, "the execution never fails"),
+            Invariant::Custom { name, .. } => write!(f, "{}", name),
+        }
+    }
+}
+
+/// A path violating an invariant
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Violation {
+    /// the violated invariant, as displayed
+    pub invariant: String,
+    /// the counterexample
+    pub path: ExecutionPath,
+}
+
+/// The result of [`MachineDefinition::check`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelCheck {
+    /// the counterexamples, shortest first for every invariant
+    pub violations: Vec<Violation>,
+    /// the number of explored paths
+    pub paths: usize,
+    /// whether the exploration hit the bound, the invariants then only hold up to the bound
+    pub truncated: bool,
+}
+
+impl ModelCheck {
+    /// Whether every invariant holds on the explored paths
+    pub fn holds(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+impl fmt::Display for ModelCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bound = if self.truncated { " (bound reached)" } else { "" };
+        writeln!(f, "{} paths checked{}, {} violations", self.paths, bound, self.violations.len())?;
+        for violation in &self.violations {
+            let steps: Vec<String> = violation.path.steps.iter().map(|step| match step {
+                PathStep::Executed(node) => node.clone(),
+                PathStep::Skipped(node) => format!("!{}", node),
+                PathStep::Caught { node, block } => format!("{}.Catch{}", node, block),
+                PathStep::RolledBack(transaction) => format!("{}.Rollback", transaction),
+            }).collect();
+            let outcome = match &violation.path.outcome {
+                PathOutcome::Succeeded => String::from("succeeded"),
+                PathOutcome::Failed(node) => format!("failed at {}", node),
+            };
+            writeln!(f, "  {}: [{}] {}", violation.invariant, steps.join(", "), outcome)?;
+        }
+        Ok(())
+    }
+}
+
+impl MachineDefinition {
+    /// Check invariants on the abstract execution paths of the definition, exploring up to
+    /// `max_paths` paths, see [`MachineDefinition::explore`]
+    pub fn check(&self, invariants: &[Invariant], max_paths: usize) -> ModelCheck {
+        let exploration = self.explore(max_paths);
+        let mut violations = Vec::new();
+        for invariant in invariants {
+            let mut counterexamples: Vec<&ExecutionPath> = exploration.paths.iter().filter(|path| !invariant.holds(path)).collect();
+            counterexamples.sort_by_key(|path| path.steps.len());
+            violations.extend(counterexamples.into_iter().map(|path| Violation {
+                invariant: invariant.to_string(),
+                path: path.clone(),
+            }));
+        }
+        ModelCheck { violations, paths: exploration.paths.len(), truncated: exploration.truncated }
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::machine::history::{EventOutcome, ExecutionHistory, HistoryEvent};
+use crate::machine::replay::{diff_values, DataDifference};
+
+
+/// A step or catch block which behaved differently in two executions
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StepDifference {
+    /// the label of the step, see [`HistoryEvent::label`]
+    pub label: String,
+    /// the outcome in the first execution, `None` when it did not visit the step
+    pub left: Option<EventOutcome>,
+    /// the outcome in the second execution, `None` when it did not visit the step
+    pub right: Option<EventOutcome>,
+    /// the values of the shared data which differ after the step, `recorded` holding the value
+    /// of the first execution and `live` the one of the second
+    pub differences: Vec<DataDifference>,
+}
+
+/// The differences between two executions of the same definition, see [`ExecutionHistory::compare`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionDiff {
+    /// the id of the state machine
+    pub machine_id: String,
+    /// the index of the first event where the executions diverge, visiting another step or
+    /// with another outcome, `None` when their paths are identical
+    pub divergence: Option<usize>,
+    /// the steps which behaved differently, in the order of the first execution, then the
+    /// steps only visited by the second execution
+    pub steps: Vec<StepDifference>,
+    /// the error of the first execution
+    pub left_error: Option<String>,
+    /// the error of the second execution
+    pub right_error: Option<String>,
+}
+
+impl ExecutionDiff {
+    /// Whether both executions visited the same steps, with the same outcomes and data
+    pub fn is_identical(&self) -> bool {
+        self.divergence.is_none() && self.steps.is_empty() && self.left_error == self.right_error
+    }
+}
+
+fn render(outcome: Option<&EventOutcome>) -> String {
+    outcome.map_or(String::from("<not visited>"), |outcome| format!("{:?}", outcome))
+}
+
+impl fmt::Display for ExecutionDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_identical() {
+            return writeln!(f, "executions of {}: identical", self.machine_id);
+        }
+        writeln!(f, "executions of {}: differences", self.machine_id)?;
+        if let Some(index) = self.divergence {
+            writeln!(f, "  executions diverge at event {}", index)?;
+        }
+        if self.left_error != self.right_error {
+            writeln!(f, "  error: {:?} -> {:?}", self.left_error, self.right_error)?;
+        }
+        for step in &self.steps {
+            if step.left != step.right {
+                writeln!(f, "  {}: {} -> {}", step.label, render(step.left.as_ref()), render(step.right.as_ref()))?;
+            }
+            for difference in &step.differences {
+                writeln!(f, "  {} {}: {} -> {}", step.label, difference.path,
+                    difference.recorded.as_deref().unwrap_or("<absent>"), difference.live.as_deref().unwrap_or("<absent>"))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// The events keyed by their label and occurrence, a step visited twice is compared with the
+// second visit of the other execution. The commits and rollbacks of the transactions are left
+// out, the steps of a rolled back group differ by their outcome
+fn keyed(history: &ExecutionHistory) -> Vec<((String, usize), &HistoryEvent)> {
+    let mut occurrences: HashMap<String, usize> = HashMap::new();
+    history.events.iter().filter(|event| event.transaction.is_none()).map(|event| {
+        let label = event.label();
+        let occurrence = occurrences.entry(label.clone()).or_default();
+        *occurrence += 1;
+        ((label, *occurrence), event)
+    }).collect()
+}
+
+fn snapshot(event: &HistoryEvent) -> Option<Value> {
+    event.data_after.as_ref().and_then(|json| serde_json::from_str(json).ok())
+}
+
+impl ExecutionHistory {
+    /// Compare the execution with another execution of the same definition, e.g. to find why
+    /// the second one failed when the first one succeeded.
+    ///
+    /// The steps are matched by their label and the number of times they were visited, and
+    /// differ when their outcome or the data after them differ. The data is only compared
+    /// when both executions recorded snapshots, see [`StateMachine::enable_snapshots`](crate::machine::state::StateMachine::enable_snapshots)
+    pub fn compare(&self, other: &ExecutionHistory) -> ExecutionDiff {
+        let (left, right) = (keyed(self), keyed(other));
+        let divergence = left.iter().zip(&right)
+            .position(|((label, event), (other_label, other))| label != other_label || event.outcome != other.outcome)
+            .or_else(|| (left.len() != right.len()).then(|| left.len().min(right.len())));
+
+        let right_events: HashMap<&(String, usize), &HistoryEvent> = right.iter().map(|(key, event)| (key, *event)).collect();
+        let mut steps = Vec::new();
+        for (key, event) in &left {
+            let other = right_events.get(key);
+            let mut differences = Vec::new();
+            if let Some(other) = other {
+                if let (Some(before), Some(after)) = (snapshot(event), snapshot(other)) {
+                    diff_values("$", Some(&before), Some(&after), &mut differences);
+                }
+            }
+            let right_outcome = other.map(|other| other.outcome.clone());
+            if right_outcome.as_ref() != Some(&event.outcome) || !differences.is_empty() {
+                steps.push(StepDifference { label: key.0.clone(), left: Some(event.outcome.clone()), right: right_outcome, differences });
+            }
+        }
+        let left_keys: Vec<&(String, usize)> = left.iter().map(|(key, _)| key).collect();
+        for (key, event) in right.iter().filter(|(key, _)| !left_keys.contains(&key)) {
+            steps.push(StepDifference { label: key.0.clone(), left: None, right: Some(event.outcome.clone()), differences: Vec::new() });
+        }
+
+        ExecutionDiff {
+            machine_id: self.machine_id.clone(),
+            divergence,
+            steps,
+            left_error: self.error.clone(),
+            right_error: other.error.clone(),
+        }
+    }
+}
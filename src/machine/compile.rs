@@ -0,0 +1,88 @@
+use std::error::Error;
+use crate::machine::data;
+use crate::machine::error::StateMachineError;
+use crate::machine::state::{State, StateMachine};
+
+// Define the function signature of the steps
+type StateFunction<T> = fn(&mut T) -> Result<(), Box<dyn Error>>;
+
+/// A linear machine flattened into the chain of its step functions, see [`StateMachine::compile`].
+///
+/// Running it calls the functions one after the other on the given data, with no state
+/// dispatch, history, snapshots, retries or panic isolation
+#[derive(Debug, Clone)]
+pub struct CompiledMachine<T> {
+    id: String,
+    chain: Vec<StateFunction<T>>,
+}
+
+impl<T> CompiledMachine<T> {
+    /// The id of the compiled state machine
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The number of functions in the chain
+    pub fn len(&self) -> usize {
+        self.chain.len()
+    }
+
+    /// Whether the chain has no function
+    pub fn is_empty(&self) -> bool {
+        self.chain.is_empty()
+    }
+
+    /// Run the chain on the data, stopping at the first error
+    pub fn run(&self, data: &mut T) -> Result<(), Box<dyn Error>> {
+        for function in &self.chain {
+            function(data)?;
+        }
+        Ok(())
+    }
+}
+
+fn compile_error(node: &str, reason: &str) -> StateMachineError {
+    StateMachineError {
+        message: format!("Step {} cannot be compiled: {}", node, reason),
+    }
+}
+
+impl<'a, T: data::DeserializeStateData> StateMachine<'a, T> {
+    /// Flatten the machine into a chain of functions, for tiny machines on latency-critical paths.
+    ///
+    /// Only linear machines are compiled: task and pass steps, without catch or retry
+    /// blocks, feature flags, data limit or invariants. The next function of a step runs before
+    /// its own function, and the chain stops before the first step marked as the end, like an
+    /// execution
+    pub fn compile(&self) -> Result<CompiledMachine<T>, StateMachineError> {
+        if self.data_limit.is_some() || !self.invariants.is_empty() {
+            return Err(StateMachineError {
+                message: format!("State machine {} cannot be compiled: it checks its data after the steps", self.id),
+            });
+        }
+        let mut chain = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            if node.end == Some(true) {
+                break;
+            }
+            if node.catch.is_some() {
+                return Err(compile_error(&node.id, "it catches errors"));
+            }
+            if node.retry.is_some() || !node.retry_blocks.is_empty() {
+                return Err(compile_error(&node.id, "it retries errors"));
+            }
+            if node.flag.is_some() {
+                return Err(compile_error(&node.id, "it is guarded by a feature flag"));
+            }
+            match node.state {
+                State::Task => {
+                    chain.extend(node.next);
+                    chain.push(node.state_function);
+                },
+                State::Pass => chain.extend(node.next),
+                _ => return Err(compile_error(&node.id, "only task and pass steps are linear")),
+            }
+        }
+        Ok(CompiledMachine { id: self.id.clone(), chain })
+    }
+}
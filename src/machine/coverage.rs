@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
+use crate::machine::data;
+use crate::machine::definition::{MachineDefinition, StateKind};
+use crate::machine::history::{EventOutcome, ExecutionHistory};
+use crate::machine::state::StateMachine;
+
+
+/// Collects the steps, transitions, catch blocks and choice branches exercised by the
+/// executions of a machine, typically across a whole test suite.
+///
+/// Histories are recorded explicitly with [`Coverage::record`], or automatically after every
+/// execution of an instrumented machine, see [`StateMachine::instrument`]
+#[derive(Debug, Clone)]
+pub struct Coverage {
+    definition: MachineDefinition,
+    hits: HashMap<String, u32>,
+}
+
+fn transition_name(from: &str, to: &str) -> String {
+    format!("{} -> {}", from, to)
+}
+
+fn catch_name(node: &str, block: usize) -> String {
+    format!("{}.Catch{}", node, block)
+}
+
+// choice and route steps have a taken and a skipped branch
+fn branches(state: StateKind) -> bool {
+    matches!(state, StateKind::Choice | StateKind::Route(..))
+}
+
+fn branch_name(node: &str, taken: bool) -> String {
+    format!("{}:{}", node, if taken { "taken" } else { "skipped" })
+}
+
+impl Coverage {
+    /// Create a coverage collector for a machine definition
+    pub fn new(definition: MachineDefinition) -> Self {
+        Coverage { definition, hits: HashMap::new() }
+    }
+
+    /// Create a coverage collector which can be shared between executions
+    pub fn shared(definition: MachineDefinition) -> Arc<Mutex<Coverage>> {
+        Arc::new(Mutex::new(Coverage::new(definition)))
+    }
+
+    fn hit(&mut self, name: String) {
+        *self.hits.entry(name).or_default() += 1;
+    }
+
+    /// Record the history of an execution
+    pub fn record(&mut self, history: &ExecutionHistory) {
+        let mut previous: Option<&str> = None;
+        // the commits and rollbacks of the transactions are not steps of the definition
+        for event in history.events.iter().filter(|event| event.transaction.is_none()) {
+            if let EventOutcome::Caught { block, .. } = event.outcome {
+                self.hit(catch_name(&event.node, block));
+                continue;
+            }
+            self.hit(event.node.clone());
+            if let Some(previous) = previous {
+                self.hit(transition_name(previous, &event.node));
+            }
+            previous = Some(&event.node);
+
+            let choice = self.definition.node(&event.node).is_some_and(|node| branches(node.state));
+            if choice {
+                self.hit(branch_name(&event.node, event.outcome != EventOutcome::Skipped));
+            }
+        }
+    }
+
+    /// Build the coverage report
+    pub fn report(&self) -> CoverageReport {
+        let item = |name: String| CoverageItem { hits: self.hits.get(&name).copied().unwrap_or(0), name };
+        let nodes = &self.definition.nodes;
+        CoverageReport {
+            machine_id: self.definition.id.clone(),
+            nodes: nodes.iter().map(|node| item(node.id.clone())).collect(),
+            transitions: self.definition.transitions().iter().map(|(from, to)| item(transition_name(from, to))).collect(),
+            catch_blocks: nodes.iter()
+                .flat_map(|node| (0..node.catch.len()).map(move |block| catch_name(&node.id, block)))
+                .map(item)
+                .collect(),
+            choice_branches: nodes.iter()
+                .filter(|node| branches(node.state))
+                .flat_map(|node| [branch_name(&node.id, true), branch_name(&node.id, false)])
+                .map(item)
+                .collect(),
+        }
+    }
+}
+
+impl<'a, T: data::DeserializeStateData> StateMachine<'a, T> {
+    /// Record the history of every execution of the state machine in the coverage collector
+    pub fn instrument(&mut self, coverage: Arc<Mutex<Coverage>>) {
+        self.coverage = Some(coverage);
+    }
+}
+
+/// An element of the machine and the number of executions which exercised it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoverageItem {
+    /// the step id, `<from> -> <to>` for a transition, `<step>.Catch<index>` for a catch
+    /// block and `<step>:taken` or `<step>:skipped` for a branch of a choice or route step
+    pub name: String,
+    /// the number of times it was exercised
+    pub hits: u32,
+}
+
+/// The coverage of a machine, see [`Coverage::report`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoverageReport {
+    /// the id of the state machine
+    pub machine_id: String,
+    /// the steps
+    pub nodes: Vec<CoverageItem>,
+    /// the transitions between the steps
+    pub transitions: Vec<CoverageItem>,
+    /// the catch blocks
+    pub catch_blocks: Vec<CoverageItem>,
+    /// the branches of the choice and route steps
+    pub choice_branches: Vec<CoverageItem>,
+}
+
+fn covered(items: &[CoverageItem]) -> usize {
+    items.iter().filter(|item| item.hits > 0).count()
+}
+
+impl CoverageReport {
+    fn sections(&self) -> [(&str, &[CoverageItem]); 4] {
+        [
+            ("nodes", &self.nodes),
+            ("transitions", &self.transitions),
+            ("catch blocks", &self.catch_blocks),
+            ("choice branches", &self.choice_branches),
+        ]
+    }
+
+    /// The percentage of exercised elements, all kinds together
+    pub fn percent(&self) -> f64 {
+        let (covered, total) = self.sections().iter()
+            .fold((0, 0), |(c, t), (_, items)| (c + covered(items), t + items.len()));
+        if total == 0 { 100.0 } else { covered as f64 * 100.0 / total as f64 }
+    }
+
+    /// The elements which were never exercised
+    pub fn uncovered(&self) -> Vec<&str> {
+        self.sections().iter()
+            .flat_map(|(_, items)| items.iter())
+            .filter(|item| item.hits == 0)
+            .map(|item| item.name.as_str())
+            .collect()
+    }
+
+    /// Render the report as json
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+impl fmt::Display for CoverageReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "coverage of {}: {:.1}%", self.machine_id, self.percent())?;
+        for (name, items) in self.sections() {
+            writeln!(f, "  {:<16} {}/{}", name, covered(items), items.len())?;
+        }
+        let uncovered = self.uncovered();
+        if !uncovered.is_empty() {
+            writeln!(f, "uncovered:")?;
+            for name in uncovered {
+                writeln!(f, "  {}", name)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,4 +1,6 @@
 use std::error::Error;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
 
 
 /// The shared data between the steps of the state machine implements this trait.
@@ -7,4 +9,41 @@ use std::error::Error;
 pub trait DeserializeStateData: Sized {
     /// A method within the trait to deserialize json from a string
     fn from_json(json: &str) -> Result<Self, Box<dyn Error>>;
-}
\ No newline at end of file
+}
+
+/// A serialization format for the shared data and the execution records.
+///
+/// It is used to measure the data against [`StateMachine::set_max_data_size_with`](crate::machine::state::StateMachine::set_max_data_size_with),
+/// and to encode [`ExecutionArchive`](crate::machine::archive::ExecutionArchive)s and
+/// [`ExecutionHistory`](crate::machine::history::ExecutionHistory)s, e.g. to send them to
+/// another process. Binary formats such as CBOR or MessagePack are plugged in by implementing
+/// it over their serde crate.
+///
+/// The snapshots of the shared data held by a history, and by a transaction, stay json text
+/// whatever the codec: the history is encoded as a whole, and replays, comparisons, lineage and
+/// [`ExecutionHistory::data_at`](crate::machine::history::ExecutionHistory::data_at) read the
+/// snapshots as json values
+pub trait DataCodec {
+    /// The name of the format
+    const NAME: &'static str;
+    /// Encode a value
+    fn encode<V: Serialize>(value: &V) -> Result<Vec<u8>, Box<dyn Error>>;
+    /// Decode a value
+    fn decode<V: DeserializeOwned>(bytes: &[u8]) -> Result<V, Box<dyn Error>>;
+}
+
+/// The json [`DataCodec`], the default format of the crate
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json;
+
+impl DataCodec for Json {
+    const NAME: &'static str = "json";
+
+    fn encode<V: Serialize>(value: &V) -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(serde_json::to_vec(value)?)
+    }
+
+    fn decode<V: DeserializeOwned>(bytes: &[u8]) -> Result<V, Box<dyn Error>> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
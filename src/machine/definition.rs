@@ -0,0 +1,367 @@
+use std::collections::HashMap;
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crate::machine::{backoff, data, transaction};
+use crate::machine::estimate::StepBudget;
+use crate::machine::state::{State, StateMachine, RetryBlock};
+
+
+/// The kind of a state, without the functions attached to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StateKind {
+    /// see [`State::Task`]
+    Task,
+    /// see [`State::Choice`]
+    Choice,
+    /// see [`State::Route`]
+    Route(u8, u8),
+    /// see [`State::Sleep`]
+    Sleep(u64),
+    /// see [`State::Pass`]
+    Pass,
+    /// see [`State::Parallel`]
+    Parallel,
+    /// see [`State::Succeed`]
+    Succeed,
+    /// see [`State::Fail`]
+    Fail,
+    /// see [`State::Map`]
+    Map,
+    /// see [`State::CustomState`]
+    CustomState,
+}
+
+impl From<&State> for StateKind {
+    fn from(state: &State) -> Self {
+        match state {
+            State::Task => StateKind::Task,
+            State::Choice(_) => StateKind::Choice,
+            State::Route(from, to) => StateKind::Route(*from, *to),
+            State::Sleep(v) => StateKind::Sleep(*v),
+            State::Pass => StateKind::Pass,
+            State::Parallel => StateKind::Parallel,
+            State::Succeed => StateKind::Succeed,
+            State::Fail => StateKind::Fail,
+            State::Map => StateKind::Map,
+            State::CustomState => StateKind::CustomState,
+        }
+    }
+}
+
+impl fmt::Display for StateKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateKind::Route(from, to) => write!(f, "Route({}..{})", from, to),
+            StateKind::Sleep(v) => write!(f, "Sleep({})", v),
+            kind => write!(f, "{:?}", kind),
+        }
+    }
+}
+
+/// The definition of a retry block
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetryDefinition {
+    /// error strings
+    pub error_equals: Vec<String>,
+    /// maximum number of retries
+    pub max_retries: u32,
+    /// upper bound of the number of retries enforced by the backoff
+    pub max_retries_cap: Option<u32>,
+    /// delay before the first retry, in milliseconds
+    pub initial_delay_ms: u128,
+    /// factor applied to the delay after every retry
+    pub multiplier: u32,
+}
+
+impl From<&RetryBlock> for RetryDefinition {
+    fn from(block: &RetryBlock) -> Self {
+        RetryDefinition {
+            error_equals: block.error_equals.clone(),
+            max_retries: block.max_retries,
+            max_retries_cap: block.backoff.max_retries,
+            initial_delay_ms: block.backoff.initial_delay.as_millis(),
+            multiplier: block.backoff.multiplier,
+        }
+    }
+}
+
+/// The definition of the backoff of the plain retry lists of the steps, see
+/// [`StateMachine::set_backoff_config`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackoffDefinition {
+    /// upper bound of the number of retries, `None` removes the cap
+    pub max_retries: Option<u32>,
+    /// delay before the first retry, in milliseconds
+    pub initial_delay_ms: u128,
+    /// factor applied to the delay after every retry
+    pub multiplier: u32,
+}
+
+impl From<&backoff::BackoffConfig> for BackoffDefinition {
+    fn from(config: &backoff::BackoffConfig) -> Self {
+        BackoffDefinition {
+            max_retries: config.max_retries,
+            initial_delay_ms: config.initial_delay.as_millis(),
+            multiplier: config.multiplier,
+        }
+    }
+}
+
+impl Default for BackoffDefinition {
+    fn default() -> Self {
+        BackoffDefinition::from(&backoff::BackoffConfig::default())
+    }
+}
+
+/// The definition of a step of the state machine
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeDefinition {
+    /// the id of the step
+    pub id: String,
+    /// the state of the step
+    pub state: StateKind,
+    /// whether a next function is executed before the step function
+    pub next: bool,
+    /// the errors retried by the step
+    pub retry: Vec<String>,
+    /// the retry blocks of the step
+    pub retry_blocks: Vec<RetryDefinition>,
+    /// the errors caught by each catch block of the step
+    pub catch: Vec<Vec<String>>,
+    /// the feature flag guarding the step
+    #[serde(default)]
+    pub flag: Option<String>,
+    /// the expected latency and cost of the step
+    #[serde(default)]
+    pub budget: Option<StepBudget>,
+    /// the transaction the step belongs to, see [`StateMachine::transaction`]
+    #[serde(default)]
+    pub transaction: Option<String>,
+    /// whether the step is the last one of the state machine
+    pub end: bool,
+}
+
+/// The definition of a transaction, a group of contiguous steps rolled back together,
+/// see [`StateMachine::transaction`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionDefinition {
+    /// the name of the transaction
+    pub name: String,
+    /// the ids of the steps of the group, in their order of execution
+    pub steps: Vec<String>,
+    /// whether an `on_commit` handler runs once the last step succeeds, it can fail and
+    /// roll the group back. The `on_failure` handler is always present
+    pub on_commit: bool,
+}
+
+/// The definition of a state machine: its steps, transitions and policies,
+/// without the functions and the shared data.
+///
+/// It can be serialized, compared and diffed between two versions of a machine
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MachineDefinition {
+    /// the id of the state machine
+    pub id: String,
+    /// the number of retries of the steps retrying errors
+    pub retries: i32,
+    /// the backoff of the steps retrying errors
+    #[serde(default)]
+    pub backoff: BackoffDefinition,
+    /// the retry budget of an execution
+    pub retry_budget: Option<u32>,
+    /// the steps, in their order of execution
+    pub nodes: Vec<NodeDefinition>,
+    /// the transactions, in their order of execution
+    #[serde(default)]
+    pub transactions: Vec<TransactionDefinition>,
+}
+
+impl<'a, T: data::DeserializeStateData> StateMachine<'a, T> {
+    /// Extract the definition of the state machine
+    pub fn definition(&self) -> MachineDefinition {
+        let groups = transaction::ranges(&self.transactions, &self.nodes);
+        let group_of = |index: usize| groups.iter().position(|(first, last)| (*first..=*last).contains(&index));
+        let nodes = self.nodes.iter().enumerate().map(|(index, node)| NodeDefinition {
+            id: node.id.clone(),
+            state: StateKind::from(&node.state),
+            next: node.next.is_some(),
+            retry: node.retry.iter().flatten().map(|v| v.to_string()).collect(),
+            retry_blocks: node.retry_blocks.iter().map(RetryDefinition::from).collect(),
+            catch: node.catch.iter().flatten().map(|block| block.error_equals.clone()).collect(),
+            flag: node.flag.as_ref().map(|flag| flag.name.clone()),
+            budget: node.budget,
+            transaction: group_of(index).map(|group| self.transactions[group].name.clone()),
+            end: node.end.unwrap_or(false),
+        }).collect();
+        let transactions = self.transactions.iter().zip(&groups).map(|(transaction, (first, last))| TransactionDefinition {
+            name: transaction.name.clone(),
+            steps: self.nodes[*first..=*last].iter().map(|node| node.id.clone()).collect(),
+            on_commit: transaction.on_commit.is_some(),
+        }).collect();
+
+        MachineDefinition {
+            id: self.id.clone(),
+            retries: self.retries,
+            backoff: BackoffDefinition::from(&self.backoff),
+            retry_budget: self.retry_budget,
+            nodes,
+            transactions,
+        }
+    }
+}
+
+impl MachineDefinition {
+    /// Get a step by its id
+    pub fn node(&self, id: &str) -> Option<&NodeDefinition> {
+        self.nodes.iter().find(|node| node.id == id)
+    }
+
+    /// The number of retries of the steps retrying errors, clamped to the cap of the backoff
+    pub(crate) fn plain_retries(&self) -> u32 {
+        let retries = self.retries.max(0) as u32;
+        self.backoff.max_retries.map_or(retries, |cap| retries.min(cap))
+    }
+
+    /// The transitions between the steps, a step marked as the end has no outgoing transition
+    pub fn transitions(&self) -> Vec<(String, String)> {
+        self.nodes.windows(2)
+            .filter(|pair| !pair[0].end)
+            .map(|pair| (pair[0].id.clone(), pair[1].id.clone()))
+            .collect()
+    }
+
+    /// Compare the definition with a newer version of it
+    pub fn diff(&self, other: &MachineDefinition) -> DefinitionDiff {
+        let mut diff = DefinitionDiff::default();
+
+        push_change(&mut diff.machine_changes, "id", &self.id, &other.id);
+        push_change(&mut diff.machine_changes, "retries", &self.retries, &other.retries);
+        push_change(&mut diff.machine_changes, "backoff", &self.backoff, &other.backoff);
+        push_change(&mut diff.machine_changes, "retry_budget", &self.retry_budget, &other.retry_budget);
+        push_change(&mut diff.machine_changes, "transactions", &self.transactions, &other.transactions);
+
+        let before: HashMap<&str, &NodeDefinition> = self.nodes.iter().map(|n| (n.id.as_str(), n)).collect();
+        let after: HashMap<&str, &NodeDefinition> = other.nodes.iter().map(|n| (n.id.as_str(), n)).collect();
+        for node in &self.nodes {
+            if !after.contains_key(node.id.as_str()) {
+                diff.removed_nodes.push(node.id.clone());
+            }
+        }
+        for node in &other.nodes {
+            match before.get(node.id.as_str()) {
+                None => diff.added_nodes.push(node.id.clone()),
+                Some(previous) => {
+                    let mut changes = Vec::new();
+                    push_change(&mut changes, "state", &previous.state, &node.state);
+                    push_change(&mut changes, "next", &previous.next, &node.next);
+                    push_change(&mut changes, "retry", &previous.retry, &node.retry);
+                    push_change(&mut changes, "retry_blocks", &previous.retry_blocks, &node.retry_blocks);
+                    push_change(&mut changes, "catch", &previous.catch, &node.catch);
+                    push_change(&mut changes, "flag", &previous.flag, &node.flag);
+                    push_change(&mut changes, "budget", &previous.budget, &node.budget);
+                    push_change(&mut changes, "transaction", &previous.transaction, &node.transaction);
+                    push_change(&mut changes, "end", &previous.end, &node.end);
+                    if !changes.is_empty() {
+                        diff.changed_nodes.push(NodeChange { id: node.id.clone(), changes });
+                    }
+                }
+            }
+        }
+
+        let (before, after) = (self.transitions(), other.transitions());
+        diff.removed_transitions = before.iter().filter(|t| !after.contains(t)).cloned().collect();
+        diff.added_transitions = after.iter().filter(|t| !before.contains(t)).cloned().collect();
+        diff
+    }
+}
+
+fn push_change<V: Serialize + PartialEq>(changes: &mut Vec<Change>, field: &str, before: &V, after: &V) {
+    if before != after {
+        changes.push(Change {
+            field: field.to_string(),
+            before: serde_json::to_string(before).unwrap_or_default(),
+            after: serde_json::to_string(after).unwrap_or_default(),
+        });
+    }
+}
+
+/// A changed attribute, the values are rendered as json
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Change {
+    /// the name of the attribute
+    pub field: String,
+    /// the previous value
+    pub before: String,
+    /// the new value
+    pub after: String,
+}
+
+/// The changes of a step present in both definitions
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeChange {
+    /// the id of the step
+    pub id: String,
+    /// the changed attributes
+    pub changes: Vec<Change>,
+}
+
+/// The differences between two versions of a machine definition,
+/// see [`MachineDefinition::diff`].
+///
+/// Its `Display` implementation renders a human readable summary
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DefinitionDiff {
+    /// changed attributes of the machine itself
+    pub machine_changes: Vec<Change>,
+    /// steps only present in the new version
+    pub added_nodes: Vec<String>,
+    /// steps only present in the old version
+    pub removed_nodes: Vec<String>,
+    /// steps present in both versions with different attributes
+    pub changed_nodes: Vec<NodeChange>,
+    /// transitions only present in the new version
+    pub added_transitions: Vec<(String, String)>,
+    /// transitions only present in the old version
+    pub removed_transitions: Vec<(String, String)>,
+}
+
+impl DefinitionDiff {
+    /// Whether both definitions are identical
+    pub fn is_empty(&self) -> bool {
+        self.machine_changes.is_empty()
+            && self.added_nodes.is_empty()
+            && self.removed_nodes.is_empty()
+            && self.changed_nodes.is_empty()
+            && self.added_transitions.is_empty()
+            && self.removed_transitions.is_empty()
+    }
+}
+
+impl fmt::Display for DefinitionDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "no changes");
+        }
+        for change in &self.machine_changes {
+            writeln!(f, "~ machine {}: {} -> {}", change.field, change.before, change.after)?;
+        }
+        for id in &self.added_nodes {
+            writeln!(f, "+ node {}", id)?;
+        }
+        for id in &self.removed_nodes {
+            writeln!(f, "- node {}", id)?;
+        }
+        for node in &self.changed_nodes {
+            for change in &node.changes {
+                writeln!(f, "~ node {} {}: {} -> {}", node.id, change.field, change.before, change.after)?;
+            }
+        }
+        for (from, to) in &self.added_transitions {
+            writeln!(f, "+ transition {} -> {}", from, to)?;
+        }
+        for (from, to) in &self.removed_transitions {
+            writeln!(f, "- transition {} -> {}", from, to)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,220 @@
+use serde::{Deserialize, Serialize};
+use crate::machine::definition::MachineDefinition;
+use crate::machine::lint::{self, Diagnostic, LintConfig, Severity};
+
+
+/// A range of the source text of a definition
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceSpan {
+    /// offset of the first byte
+    pub start: usize,
+    /// offset after the last byte
+    pub end: usize,
+    /// line of the first byte, starting at 1
+    pub line: usize,
+    /// column of the first byte, in characters, starting at 1
+    pub column: usize,
+}
+
+impl SourceSpan {
+    fn new(source: &str, start: usize, end: usize) -> Self {
+        let before = &source[..start];
+        let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+        SourceSpan {
+            start,
+            end,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+
+    // The span of a position given by its line and column, e.g. by a parse error
+    fn at(source: &str, line: usize, column: usize) -> Self {
+        let line_start: usize = source.split_inclusive('\n').take(line.saturating_sub(1)).map(str::len).sum();
+        let start = source[line_start..].char_indices().nth(column.saturating_sub(1))
+            .map_or(source.len(), |(offset, _)| line_start + offset);
+        SourceSpan { start, end: start, line, column }
+    }
+}
+
+/// A lint diagnostic located in the source text of the definition, see [`diagnose_json`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpannedDiagnostic {
+    /// the diagnostic
+    #[serde(flatten)]
+    pub diagnostic: Diagnostic,
+    /// json pointer of the offending key, e.g. `/nodes/2/retry`, or of the step when the key is absent
+    pub pointer: Option<String>,
+    /// the offending key in the source text, or the position of a parse error
+    pub span: Option<SourceSpan>,
+}
+
+// The key of a step at fault for a diagnostic of the given rule
+fn offending_key(rule: &str, retry_blocks: bool) -> &'static str {
+    match rule {
+        lint::TASK_WITHOUT_RETRY => "retry",
+        lint::UNREACHABLE_STEP => "id",
+        lint::END_STEP_NOT_EXECUTED => "end",
+        lint::UNREACHABLE_CATCH => "catch",
+        lint::UNREACHABLE_RETRY if retry_blocks => "retry_blocks",
+        lint::UNREACHABLE_RETRY => "retry",
+        _ => "state",
+    }
+}
+
+/// Parse a definition from its json text and lint it, locating every diagnostic in the text so
+/// that an editor can underline the offending key.
+///
+/// A text which is not a valid definition yields a single [`lint::INVALID_DEFINITION`] diagnostic,
+/// located at the parse error
+pub fn diagnose_json(source: &str, config: &LintConfig) -> Vec<SpannedDiagnostic> {
+    let definition: MachineDefinition = match serde_json::from_str(source) {
+        Ok(definition) => definition,
+        Err(err) => return vec![SpannedDiagnostic {
+            diagnostic: Diagnostic {
+                rule: lint::INVALID_DEFINITION.to_string(),
+                severity: Severity::Error,
+                node: None,
+                message: err.to_string(),
+            },
+            pointer: None,
+            span: Some(SourceSpan::at(source, err.line(), err.column())),
+        }],
+    };
+    definition.lint(config).diagnostics.into_iter().map(|diagnostic| {
+        let index = diagnostic.node.as_ref().and_then(|id| definition.nodes.iter().position(|node| &node.id == id));
+        let (pointer, span) = match index {
+            Some(index) => {
+                let key = offending_key(&diagnostic.rule, !definition.nodes[index].retry_blocks.is_empty());
+                let (key, node) = (format!("/nodes/{}/{}", index, key), format!("/nodes/{}", index));
+                match locate(source, &key) {
+                    Some(span) => (Some(key), Some(span)),
+                    None => (Some(node.clone()), locate(source, &node)),
+                }
+            },
+            None => (None, None),
+        };
+        SpannedDiagnostic { diagnostic, pointer, span }
+    }).collect()
+}
+
+/// Locate the value at a json pointer in a json text. The span of an object member is the one
+/// of its key, the span of an array element the one of the element
+pub fn locate(source: &str, pointer: &str) -> Option<SourceSpan> {
+    let tokens: Vec<&str> = pointer.split('/').skip(1).collect();
+    let mut scanner = Scanner { bytes: source.as_bytes(), pos: 0 };
+    let (start, end) = scanner.find(&tokens)?;
+    Some(SourceSpan::new(source, start, end))
+}
+
+// A minimal json scanner, it only skips over the values which are not on the path
+struct Scanner<'s> {
+    bytes: &'s [u8],
+    pos: usize,
+}
+
+impl Scanner<'_> {
+    fn peek(&mut self) -> Option<u8> {
+        while self.bytes.get(self.pos).is_some_and(u8::is_ascii_whitespace) {
+            self.pos += 1;
+        }
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        (self.peek()? == byte).then(|| self.pos += 1)
+    }
+
+    // Skip a string, returns its raw contents
+    fn string(&mut self) -> Option<&[u8]> {
+        self.expect(b'"')?;
+        let start = self.pos;
+        loop {
+            match self.bytes.get(self.pos)? {
+                b'"' => break,
+                b'\\' => self.pos += 2,
+                _ => self.pos += 1,
+            }
+        }
+        self.pos += 1;
+        Some(&self.bytes[start..self.pos - 1])
+    }
+
+    fn skip_value(&mut self) -> Option<()> {
+        match self.peek()? {
+            b'"' => self.string().map(|_| ()),
+            open @ (b'{' | b'[') => {
+                let close = if open == b'{' { b'}' } else { b']' };
+                self.pos += 1;
+                if self.peek()? == close {
+                    self.pos += 1;
+                    return Some(());
+                }
+                loop {
+                    if open == b'{' {
+                        self.string()?;
+                        self.expect(b':')?;
+                    }
+                    self.skip_value()?;
+                    match self.peek()? {
+                        b',' => self.pos += 1,
+                        byte if byte == close => {
+                            self.pos += 1;
+                            return Some(());
+                        },
+                        _ => return None,
+                    }
+                }
+            },
+            _ => {
+                // numbers and literals
+                let start = self.pos;
+                while self.bytes.get(self.pos).is_some_and(|byte| !b",}] \t\r\n".contains(byte)) {
+                    self.pos += 1;
+                }
+                (self.pos > start).then_some(())
+            },
+        }
+    }
+
+    fn find(&mut self, tokens: &[&str]) -> Option<(usize, usize)> {
+        let (token, rest) = match tokens.split_first() {
+            Some(split) => split,
+            None => {
+                let start = self.pos;
+                self.skip_value()?;
+                return Some((start, self.pos));
+            },
+        };
+        match self.peek()? {
+            b'{' => {
+                self.pos += 1;
+                while self.peek()? == b'"' {
+                    let start = self.pos;
+                    let matched = self.string()? == token.as_bytes();
+                    let end = self.pos;
+                    self.expect(b':')?;
+                    if matched {
+                        return if rest.is_empty() { Some((start, end)) } else { self.find(rest) };
+                    }
+                    self.skip_value()?;
+                    if self.peek()? == b',' {
+                        self.pos += 1;
+                    }
+                }
+                None
+            },
+            b'[' => {
+                let index: usize = token.parse().ok()?;
+                self.pos += 1;
+                for _ in 0..index {
+                    self.skip_value()?;
+                    self.expect(b',')?;
+                }
+                self.peek()?;
+                self.find(rest)
+            },
+            _ => None,
+        }
+    }
+}
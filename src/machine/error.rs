@@ -1,5 +1,22 @@
 use std::fmt;
 use std::error::Error;
+use std::time::Duration;
+use crate::machine::isolation;
+
+/// Wildcard matching every error in retry and catch lists
+pub const ALL: &str = "States.ALL";
+
+/// Error raised when the retry budget of the state machine is exhausted
+pub const RETRY_BUDGET_EXHAUSTED: &str = "States.RetryBudgetExhausted";
+
+/// Error raised when the serialized shared data exceeds the size limit of the state machine
+pub const DATA_LIMIT_EXCEEDED: &str = "States.DataLimitExceeded";
+
+/// Error recorded in the history for a step breaking an invariant of the state machine
+pub const INVARIANT_VIOLATED: &str = "States.InvariantViolated";
+
+/// Error raised in place of a step function which panicked
+pub const RUNTIME: &str = "States.Runtime";
 
 /// Custom error that can be thrown at any point in the execution
 #[derive(Debug)]
@@ -14,4 +31,222 @@ impl fmt::Display for StateMachineError {
   }
 }
 
-impl Error for StateMachineError {}
\ No newline at end of file
+impl Error for StateMachineError {}
+
+/// The error of a step function which panicked, its code is [`RUNTIME`] so that it can be
+/// retried and caught like any other error. The panic message is its source, and the history
+/// records both, see [`HistoryEvent`](crate::machine::history::HistoryEvent)
+#[derive(Debug)]
+pub struct PanicError {
+    message: PanicMessage,
+    backtrace: Option<String>,
+}
+
+#[derive(Debug)]
+struct PanicMessage(String);
+
+impl fmt::Display for PanicMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for PanicMessage {}
+
+impl PanicError {
+    pub(crate) fn new(message: String, backtrace: Option<String>) -> Self {
+        PanicError { message: PanicMessage(message), backtrace }
+    }
+
+    /// The message of the panic
+    pub fn message(&self) -> &str {
+        &self.message.0
+    }
+
+    /// The backtrace of the panic, captured when enabled with `RUST_BACKTRACE` and
+    /// [`capture_panic_backtraces`]
+    pub fn backtrace(&self) -> Option<&str> {
+        self.backtrace.as_deref()
+    }
+}
+
+/// Capture the backtraces of the panics of the steps into their [`PanicError`], in place of
+/// letting the panic hook print them.
+///
+/// It chains a hook to the panic hook of the process, once. The panics raised outside of the
+/// machines are left to the previous hook. Without it, the panics of the steps are still
+/// isolated, and printed by the panic hook of the host
+pub fn capture_panic_backtraces() {
+    isolation::install_hook();
+}
+
+impl fmt::Display for PanicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", RUNTIME)
+    }
+}
+
+impl Error for PanicError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.message)
+    }
+}
+
+/// Implemented by handler errors which know whether they are worth retrying, e.g. a throttling
+/// error carrying the retry-after delay sent by a server.
+///
+/// The error types are registered with [`StateMachine::retry_errors_of`](crate::machine::state::StateMachine::retry_errors_of)
+pub trait Retryable {
+    /// Whether the failed operation can succeed when retried
+    fn is_retryable(&self) -> bool;
+
+    /// The delay to wait before the next attempt, in place of the backoff delay
+    fn retry_after(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Implemented by handler errors whose code, matched by the retry and catch lists and recorded in
+/// the history, differs from their message, e.g. the variants of a `thiserror` enum.
+///
+/// The error types are registered with [`StateMachine::error_codes_of`](crate::machine::state::StateMachine::error_codes_of)
+pub trait ErrorCode {
+    /// The code of the error, e.g. `Http.Throttled`
+    fn code(&self) -> String;
+}
+
+/// The reason an execution failed, see [`StateMachine::execute`](crate::machine::state::StateMachine::execute).
+///
+/// It displays as the error of the failure, e.g. the error raised by the failing step, which
+/// is also recorded in the history. New kinds of failures may be added in minor releases
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ExecutionError {
+    /// the machine cannot be executed as defined, e.g. a step was invoked too many times
+    Validation {
+        /// the description of the problem
+        message: String,
+    },
+    /// a step failed with an error it neither retried nor caught
+    NodeFailed {
+        /// the failing step
+        node: String,
+        /// the error raised by the step
+        error: String,
+    },
+    /// a step still failed after all its retries, and did not catch the error
+    RetryExhausted {
+        /// the failing step
+        node: String,
+        /// the error raised by the last attempt
+        error: String,
+        /// the number of attempts, the first one included
+        attempts: u32,
+    },
+    /// the retry budget of the execution ran out while retrying a step
+    RetryBudgetExhausted {
+        /// the step being retried
+        node: String,
+    },
+    /// a step left shared data larger than the data limit of the machine
+    DataLimitExceeded {
+        /// the step which grew the data
+        node: String,
+    },
+    /// the shared data broke an invariant of the machine after a step or catch block
+    InvariantViolated {
+        /// the name of the invariant
+        invariant: String,
+        /// the label of the step or catch block which broke it
+        node: String,
+        /// the description of the violation returned by the invariant
+        message: String,
+    },
+    /// the function of a matching catch block failed
+    CatchFailed {
+        /// the step whose error was caught
+        node: String,
+        /// the error raised by the catch block
+        error: String,
+    },
+}
+
+impl ExecutionError {
+    /// The step at which the execution failed, if any
+    pub fn node(&self) -> Option<&str> {
+        match self {
+            ExecutionError::Validation { .. } => None,
+            ExecutionError::NodeFailed { node, .. }
+            | ExecutionError::RetryExhausted { node, .. }
+            | ExecutionError::RetryBudgetExhausted { node }
+            | ExecutionError::DataLimitExceeded { node }
+            | ExecutionError::InvariantViolated { node, .. }
+            | ExecutionError::CatchFailed { node, .. } => Some(node),
+        }
+    }
+}
+
+impl fmt::Display for ExecutionError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ExecutionError::Validation { message } => write!(f, "{}", message),
+      ExecutionError::NodeFailed { error, .. }
+      | ExecutionError::RetryExhausted { error, .. }
+      | ExecutionError::CatchFailed { error, .. } => write!(f, "{}", error),
+      ExecutionError::RetryBudgetExhausted { .. } => write!(f, "{}", RETRY_BUDGET_EXHAUSTED),
+      ExecutionError::DataLimitExceeded { .. } => write!(f, "{}", DATA_LIMIT_EXCEEDED),
+      ExecutionError::InvariantViolated { invariant, node, message } => {
+        write!(f, "{}: invariant {} violated by {}: {}", INVARIANT_VIOLATED, invariant, node, message)
+      },
+    }
+  }
+}
+
+impl Error for ExecutionError {}
+
+impl From<ExecutionError> for StateMachineError {
+    fn from(err: ExecutionError) -> Self {
+        StateMachineError { message: err.to_string() }
+    }
+}
+
+/// Whether an error string matches a pattern of a retry or catch list.
+///
+/// `States.ALL` matches every error, and `*` in a pattern matches any sequence of
+/// characters, so `Http.5*` matches `Http.500` and `Http.503`
+pub fn matches(pattern: &str, error: &str) -> bool {
+    if pattern == ALL {
+        return true;
+    }
+    let (pattern, error) = (pattern.as_bytes(), error.as_bytes());
+    let (mut p, mut e) = (0, 0);
+    // position of the last `*` in the pattern and of the error when it was met
+    let mut backtrack: Option<(usize, usize)> = None;
+    while e < error.len() {
+        if p < pattern.len() && pattern[p] == b'*' {
+            backtrack = Some((p, e));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == error[e] {
+            p += 1;
+            e += 1;
+        } else if let Some((star, matched)) = backtrack {
+            // let the last `*` absorb one more character
+            p = star + 1;
+            e = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == b'*')
+}
+
+/// Whether an error string matches any of the patterns, see [`matches()`]
+pub fn matches_any<S: AsRef<str>>(patterns: &[S], error: &str) -> bool {
+    patterns.iter().any(|pattern| matches(pattern.as_ref(), error))
+}
+
+/// Whether the patterns match every possible error
+pub fn matches_everything<S: AsRef<str>>(patterns: &[S]) -> bool {
+    patterns.iter().any(|pattern| pattern.as_ref() == ALL || pattern.as_ref().chars().all(|c| c == '*'))
+}
\ No newline at end of file
@@ -0,0 +1,212 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::machine::definition::{MachineDefinition, NodeDefinition, StateKind};
+use crate::machine::error::StateMachineError;
+use crate::machine::data;
+use crate::machine::state::StateMachine;
+
+
+// The branches enumerated by an estimate, beyond which it is truncated
+const MAX_BRANCHES: usize = 1024;
+
+/// The expected latency and cost of a step, see [`StateMachine::set_budget`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StepBudget {
+    /// the expected duration of an attempt of the step
+    pub latency: Duration,
+    /// the expected cost of an attempt of the step, in any integer unit, e.g. micro-dollars
+    pub cost: u64,
+}
+
+/// The probability of every step to run its function, by step id, see [`MachineDefinition::estimate`].
+///
+/// Without a probability, a choice step runs with a probability of 0.5, a route step with the
+/// share of the routing buckets it covers, and any other step always runs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PathProbabilities {
+    /// the probabilities, from 0 to 1, by step id
+    pub taken: BTreeMap<String, f64>,
+}
+
+impl PathProbabilities {
+    /// Set the probability of a step to run its function
+    pub fn step(mut self, node: &str, probability: f64) -> Self {
+        self.taken.insert(node.to_string(), probability.clamp(0.0, 1.0));
+        self
+    }
+
+    fn of(&self, node: &NodeDefinition) -> f64 {
+        match (self.taken.get(&node.id), node.state) {
+            (Some(probability), _) => *probability,
+            (None, StateKind::Choice) => 0.5,
+            (None, StateKind::Route(from, to)) => f64::from(to.min(100).saturating_sub(from)) / 100.0,
+            (None, _) => 1.0,
+        }
+    }
+}
+
+/// A combination of the steps taken by an execution, see [`Estimate::branches`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BranchEstimate {
+    /// the steps running their function, in their order of execution
+    pub steps: Vec<String>,
+    /// the probability of the branch
+    pub probability: f64,
+    /// the latency of the branch when every step succeeds at its first attempt
+    pub latency: Duration,
+    /// the latency of the branch when every step exhausts its retries
+    pub worst_latency: Duration,
+    /// the cost of the branch when every step succeeds at its first attempt
+    pub cost: u64,
+    /// the cost of the branch when every step exhausts its retries
+    pub worst_cost: u64,
+}
+
+/// The expected latency and cost of the executions of a definition, see [`MachineDefinition::estimate`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Estimate {
+    /// the expected end-to-end latency, when every step succeeds at its first attempt
+    pub expected_latency: Duration,
+    /// the expected cost, when every step succeeds at its first attempt
+    pub expected_cost: f64,
+    /// the branches, by decreasing worst latency
+    pub branches: Vec<BranchEstimate>,
+    /// whether there were too many branches to enumerate them all, the expected values are
+    /// exact nonetheless
+    pub truncated: bool,
+    /// the steps which may run without a budget, counted as free and instant, sleep steps
+    /// excepted
+    pub unannotated: Vec<String>,
+}
+
+impl Estimate {
+    /// The branch with the highest worst latency
+    pub fn worst(&self) -> Option<&BranchEstimate> {
+        self.branches.first()
+    }
+}
+
+// The latency of the attempts of a step, saturating at the longest duration
+fn attempts_latency(latency: Duration, attempts: u32, sleep: Duration) -> Duration {
+    latency.checked_mul(attempts).unwrap_or(Duration::MAX).saturating_add(sleep)
+}
+
+// The latency and cost of a step, at its first attempt and when it exhausts its retries. The
+// estimates saturate rather than overflow, e.g. for the exponential delays of a long policy
+fn step_estimate(definition: &MachineDefinition, node: &NodeDefinition) -> (Duration, u64, Duration, u64) {
+    let budget = node.budget.unwrap_or_default();
+    let sleep = match node.state {
+        StateKind::Sleep(secs) => Duration::from_secs(secs),
+        _ => Duration::ZERO,
+    };
+    let (latency, cost) = (budget.latency.saturating_add(sleep), budget.cost);
+
+    // the longest retry policy of the step, the plain retry list follows the backoff of the machine
+    let mut policies: Vec<(u32, u128, u32)> = node.retry_blocks.iter()
+        .map(|block| (block.max_retries_cap.map_or(block.max_retries, |cap| block.max_retries.min(cap)), block.initial_delay_ms, block.multiplier))
+        .collect();
+    if !node.retry.is_empty() {
+        policies.push((definition.plain_retries(), definition.backoff.initial_delay_ms, definition.backoff.multiplier));
+    }
+    let mut worst = (latency, cost);
+    for (retries, initial_delay_ms, multiplier) in policies {
+        let attempts = retries.saturating_add(1);
+        let mut delay = Duration::from_millis(initial_delay_ms.min(u128::from(u64::MAX)) as u64);
+        let mut latency = attempts_latency(budget.latency, attempts, sleep);
+        for _ in 0..retries {
+            if latency == Duration::MAX {
+                break;
+            }
+            latency = latency.saturating_add(delay);
+            delay = delay.checked_mul(multiplier).unwrap_or(Duration::MAX);
+        }
+        if latency > worst.0 {
+            worst = (latency, cost.saturating_mul(u64::from(attempts)));
+        }
+    }
+    (latency, cost, worst.0, worst.1)
+}
+
+impl MachineDefinition {
+    /// Estimate the latency and cost of the executions from the budgets of the steps, and the
+    /// probability of the steps to run, e.g. to reason about an SLA before running anything.
+    ///
+    /// The branches are the combinations of the steps which may be skipped, their worst case
+    /// counts the retries of every step with the delays of their retry blocks. The catch blocks
+    /// are not part of the estimate
+    pub fn estimate(&self, probabilities: &PathProbabilities) -> Estimate {
+        let last = self.nodes.iter().position(|node| node.end).unwrap_or(self.nodes.len());
+        let nodes = &self.nodes[..last];
+
+        let mut expected_latency = 0.0;
+        let mut expected_cost = 0.0;
+        for node in nodes {
+            let probability = probabilities.of(node);
+            let (latency, cost, _, _) = step_estimate(self, node);
+            expected_latency += probability * latency.as_secs_f64();
+            expected_cost += probability * cost as f64;
+        }
+
+        let mut branches = vec![BranchEstimate {
+            steps: Vec::new(),
+            probability: 1.0,
+            latency: Duration::ZERO,
+            worst_latency: Duration::ZERO,
+            cost: 0,
+            worst_cost: 0,
+        }];
+        let mut truncated = false;
+        for node in nodes {
+            let probability = probabilities.of(node);
+            let (latency, cost, worst_latency, worst_cost) = step_estimate(self, node);
+            let mut next = Vec::with_capacity(branches.len() * 2);
+            for branch in branches {
+                if probability < 1.0 && next.len() < MAX_BRANCHES {
+                    next.push(BranchEstimate { probability: branch.probability * (1.0 - probability), ..branch.clone() });
+                } else if probability < 1.0 {
+                    truncated = true;
+                }
+                if probability > 0.0 {
+                    let mut taken = branch;
+                    taken.steps.push(node.id.clone());
+                    taken.probability *= probability;
+                    taken.latency = taken.latency.saturating_add(latency);
+                    taken.worst_latency = taken.worst_latency.saturating_add(worst_latency);
+                    taken.cost = taken.cost.saturating_add(cost);
+                    taken.worst_cost = taken.worst_cost.saturating_add(worst_cost);
+                    next.push(taken);
+                }
+            }
+            branches = next;
+        }
+        branches.sort_by_key(|branch| Reverse(branch.worst_latency));
+
+        Estimate {
+            expected_latency: Duration::try_from_secs_f64(expected_latency).unwrap_or(Duration::MAX),
+            expected_cost,
+            branches,
+            truncated,
+            unannotated: nodes.iter()
+                .filter(|node| node.budget.is_none() && !matches!(node.state, StateKind::Sleep(_)) && probabilities.of(node) > 0.0)
+                .map(|node| node.id.clone())
+                .collect(),
+        }
+    }
+}
+
+impl<'a, T: data::DeserializeStateData> StateMachine<'a, T> {
+    /// Annotate a step with its expected latency and cost, see [`MachineDefinition::estimate`]
+    pub fn set_budget(&mut self, node_id: &str, budget: StepBudget) -> Result<(), StateMachineError> {
+        match self.nodes.iter_mut().find(|node| node.id == node_id) {
+            Some(node) => {
+                node.budget = Some(budget);
+                Ok(())
+            },
+            None => Err(StateMachineError {
+                message: format!("Node ID not found: {}", node_id),
+            }),
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::machine::data;
+use crate::machine::error::StateMachineError;
+use crate::machine::history::ExecutionHistory;
+use crate::machine::state::{State, StateMachine};
+
+
+/// A variant of an experiment and the percentage of the executions assigned to it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Variant {
+    /// the name of the variant
+    pub name: String,
+    /// the percentage of the executions assigned to the variant
+    pub weight: u8,
+}
+
+/// An A/B experiment splitting the executions of a machine between variants.
+///
+/// Variants are assigned from the routing bucket of the execution, the assigned variant is
+/// recorded in the history as a tag named after the experiment. Steps run for a single
+/// variant with the [`State::Route`] returned by [`Experiment::route`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "ExperimentDefinition")]
+pub struct Experiment {
+    // the weights of the variants add up to 100, checked by Experiment::new
+    pub(crate) name: String,
+    variants: Vec<Variant>,
+}
+
+// The serialized experiment, whose weights are checked when it is deserialized
+#[derive(Deserialize)]
+struct ExperimentDefinition {
+    name: String,
+    variants: Vec<Variant>,
+}
+
+impl TryFrom<ExperimentDefinition> for Experiment {
+    type Error = StateMachineError;
+
+    fn try_from(definition: ExperimentDefinition) -> Result<Self, Self::Error> {
+        let variants: Vec<(&str, u8)> = definition.variants.iter().map(|variant| (variant.name.as_str(), variant.weight)).collect();
+        Experiment::new(&definition.name, &variants)
+    }
+}
+
+impl Experiment {
+    /// Create an experiment from its variants and their weights, which must add up to 100
+    pub fn new(name: &str, variants: &[(&str, u8)]) -> Result<Self, StateMachineError> {
+        let total: u32 = variants.iter().map(|(_, weight)| u32::from(*weight)).sum();
+        if total != 100 {
+            return Err(StateMachineError {
+                message: format!("The weights of experiment {} add up to {} instead of 100", name, total),
+            });
+        }
+        Ok(Experiment {
+            name: name.to_string(),
+            variants: variants.iter().map(|(name, weight)| Variant { name: name.to_string(), weight: *weight }).collect(),
+        })
+    }
+
+    /// The name of the experiment, the tag recording the variant of an execution
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The variants, their weights add up to 100
+    pub fn variants(&self) -> &[Variant] {
+        &self.variants
+    }
+
+    fn ranges(&self) -> impl Iterator<Item = (&str, u8, u8)> {
+        self.variants.iter().scan(0u8, |from, variant| {
+            let start = *from;
+            *from = from.saturating_add(variant.weight);
+            Some((variant.name.as_str(), start, *from))
+        })
+    }
+
+    /// The variant assigned to a routing bucket
+    pub fn variant(&self, bucket: u8) -> Option<&str> {
+        self.ranges().find(|(_, from, to)| (*from..*to).contains(&bucket)).map(|(name, _, _)| name)
+    }
+
+    /// The route state of the steps only executed for a variant
+    pub fn route(&self, variant: &str) -> Option<State> {
+        self.ranges().find(|(name, _, _)| *name == variant).map(|(_, from, to)| State::Route(from, to))
+    }
+
+    /// Summarize the outcomes of the executions per variant
+    pub fn summarize<'h, I>(&self, histories: I) -> Vec<VariantSummary>
+    where
+        I: IntoIterator<Item = &'h ExecutionHistory>,
+    {
+        let mut summaries: Vec<VariantSummary> = self.variants.iter()
+            .map(|variant| VariantSummary { variant: variant.name.clone(), executions: 0, succeeded: 0, total_duration: Duration::ZERO })
+            .collect();
+        for history in histories {
+            let summary = history.tag(&self.name)
+                .and_then(|variant| summaries.iter_mut().find(|summary| summary.variant == variant));
+            if let Some(summary) = summary {
+                summary.executions += 1;
+                summary.succeeded += u32::from(history.succeeded());
+                summary.total_duration += history.events.iter().map(|event| event.duration).sum::<Duration>();
+            }
+        }
+        summaries
+    }
+}
+
+/// The outcomes of the executions assigned to a variant, see [`Experiment::summarize`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VariantSummary {
+    /// the name of the variant
+    pub variant: String,
+    /// the number of executions assigned to the variant
+    pub executions: u32,
+    /// the number of successful executions
+    pub succeeded: u32,
+    /// the time spent in the steps of all the executions
+    pub total_duration: Duration,
+}
+
+impl VariantSummary {
+    /// The percentage of successful executions
+    pub fn success_rate(&self) -> f64 {
+        if self.executions == 0 { 0.0 } else { self.succeeded as f64 * 100.0 / self.executions as f64 }
+    }
+
+    /// The average time spent in the steps of an execution
+    pub fn mean_duration(&self) -> Duration {
+        if self.executions == 0 { Duration::ZERO } else { self.total_duration / self.executions }
+    }
+}
+
+impl<'a, T: data::DeserializeStateData> StateMachine<'a, T> {
+    /// Assign a variant of the experiment to every execution, recorded in its history
+    pub fn set_experiment(&mut self, experiment: Experiment) {
+        self.experiment = Some(experiment);
+    }
+}
@@ -0,0 +1,181 @@
+use serde_json::{json, Map, Value};
+use crate::machine::definition::{MachineDefinition, NodeDefinition, StateKind};
+use crate::machine::error::StateMachineError;
+
+
+// The name of the state running a catch block of a step, like its label in the history
+fn catch_state(node: &str, block: usize) -> String {
+    format!("{}.Catch{}", node, block)
+}
+
+// A name usable as a CloudFormation substitution or a Terraform map key
+fn placeholder(name: &str) -> String {
+    name.chars().filter(|c| c.is_ascii_alphanumeric()).collect()
+}
+
+fn retriers(definition: &MachineDefinition, node: &NodeDefinition) -> Vec<Value> {
+    let mut retriers: Vec<Value> = node.retry_blocks.iter().map(|block| {
+        let attempts = block.max_retries_cap.map_or(block.max_retries, |cap| block.max_retries.min(cap));
+        json!({
+            "ErrorEquals": block.error_equals,
+            "MaxAttempts": attempts,
+            "IntervalSeconds": block.initial_delay_ms.div_ceil(1000).max(1),
+            "BackoffRate": block.multiplier.max(1),
+        })
+    }).collect();
+    if !node.retry.is_empty() {
+        retriers.push(json!({
+            "ErrorEquals": node.retry,
+            "MaxAttempts": definition.plain_retries(),
+        }));
+    }
+    retriers
+}
+
+// The comment of a step whose local behaviour has no direct equivalent in Step Functions
+fn comment(node: &NodeDefinition) -> Option<String> {
+    let comment = match node.state {
+        StateKind::Choice => Some(String::from("runs only when its choice function returns true, port it to a Choice state")),
+        StateKind::Route(from, to) => Some(format!("runs only for the routing buckets {} to {}", from, to.saturating_sub(1))),
+        StateKind::Parallel | StateKind::Map | StateKind::Succeed | StateKind::Fail | StateKind::CustomState => {
+            Some(format!("a {} step is a no-op in the local engine", node.state))
+        },
+        _ => None,
+    };
+    let flag = node.flag.as_ref().map(|flag| format!("guarded by the feature flag {}", flag));
+    // the local engine restores the data of a failed group, nothing undoes the states in AWS
+    let transaction = node.transaction.as_ref()
+        .map(|name| format!("part of the transaction {}, which is not rolled back", name));
+    let parts: Vec<String> = [comment, flag, transaction].into_iter().flatten().collect();
+    (!parts.is_empty()).then(|| parts.join(", "))
+}
+
+impl MachineDefinition {
+    /// Render the definition as an Amazon States Language document.
+    ///
+    /// Every step running a function becomes a Task state whose `Resource` is given by the
+    /// `resource` function, from the name of the state. Catch blocks become Task states named
+    /// like their label in the history, e.g. `NodeA.Catch0`, continuing with the next step.
+    /// Sleep steps become Wait states, and the steps which are no-ops in the local engine become
+    /// Pass states. Behaviours without an equivalent, such as choice functions, feature flags
+    /// and transactions, are described in the `Comment` of the state.
+    ///
+    /// The states follow the transitions of the definition, up to the first step marked as the
+    /// end. That step is not exported, like the local engine does not run it, and the step before
+    /// it ends the exported machine
+    pub fn to_asl<F: Fn(&str) -> String>(&self, resource: F) -> Result<Value, StateMachineError> {
+        let end = self.nodes.iter().position(|node| node.end).unwrap_or(self.nodes.len());
+        let nodes = match self.nodes.get(..end) {
+            Some(nodes) if !nodes.is_empty() => nodes,
+            _ => return Err(StateMachineError {
+                message: format!("State machine {} has no steps to export", self.id),
+            }),
+        };
+
+        let mut states = Map::new();
+        for (index, node) in nodes.iter().enumerate() {
+            let next = nodes.get(index + 1).map(|next| next.id.as_str());
+            let task = matches!(node.state, StateKind::Task | StateKind::Choice | StateKind::Route(_, _));
+            let mut state = Map::new();
+            match node.state {
+                StateKind::Task | StateKind::Choice | StateKind::Route(_, _) => {
+                    state.insert(String::from("Type"), json!("Task"));
+                    state.insert(String::from("Resource"), json!(resource(&node.id)));
+                },
+                StateKind::Sleep(seconds) => {
+                    state.insert(String::from("Type"), json!("Wait"));
+                    state.insert(String::from("Seconds"), json!(seconds));
+                },
+                _ => {
+                    state.insert(String::from("Type"), json!("Pass"));
+                },
+            }
+            if let Some(comment) = comment(node) {
+                state.insert(String::from("Comment"), json!(comment));
+            }
+            let retriers = retriers(self, node);
+            if !retriers.is_empty() && task {
+                state.insert(String::from("Retry"), Value::Array(retriers));
+            }
+            if !node.catch.is_empty() && task {
+                let catchers: Vec<Value> = node.catch.iter().enumerate()
+                    .map(|(block, errors)| json!({ "ErrorEquals": errors, "Next": catch_state(&node.id, block) }))
+                    .collect();
+                state.insert(String::from("Catch"), Value::Array(catchers));
+            }
+            match next {
+                Some(next) => state.insert(String::from("Next"), json!(next)),
+                None => state.insert(String::from("End"), json!(true)),
+            };
+            states.insert(node.id.clone(), Value::Object(state));
+
+            if !task {
+                continue;
+            }
+            for block in 0..node.catch.len() {
+                let name = catch_state(&node.id, block);
+                let mut state = json!({ "Type": "Task", "Resource": resource(&name) });
+                match next {
+                    Some(next) => state["Next"] = json!(next),
+                    None => state["End"] = json!(true),
+                }
+                states.insert(name, state);
+            }
+        }
+
+        Ok(json!({
+            "Comment": format!("Exported from the sfn-machine state machine {}", self.id),
+            "StartAt": nodes[0].id,
+            "States": states,
+        }))
+    }
+
+    /// Render a CloudFormation template defining an equivalent `AWS::StepFunctions::StateMachine`,
+    /// see [`MachineDefinition::to_asl`].
+    ///
+    /// The role of the state machine is a parameter of the template, and the Lambda functions are
+    /// resolved through `DefinitionSubstitutions` to `<machine>-<state>` in the current account
+    /// and region, placeholders to adjust to the deployed functions
+    pub fn to_cloudformation(&self) -> Result<String, StateMachineError> {
+        let asl = self.to_asl(|state| format!("${{{}}}", placeholder(state)))?;
+        let mut substitutions = Map::new();
+        if let Some(states) = asl["States"].as_object() {
+            for (name, _) in states.iter().filter(|(_, state)| state["Type"] == "Task") {
+                let arn = format!("arn:aws:lambda:${{AWS::Region}}:${{AWS::AccountId}}:function:{}-{}", placeholder(&self.id), placeholder(name));
+                substitutions.insert(placeholder(name), json!({ "Fn::Sub": arn }));
+            }
+        }
+        let template = json!({
+            "AWSTemplateFormatVersion": "2010-09-09",
+            "Parameters": {
+                "StateMachineRoleArn": { "Type": "String", "Description": "The role assumed by the state machine" },
+            },
+            "Resources": {
+                placeholder(&self.id): {
+                    "Type": "AWS::StepFunctions::StateMachine",
+                    "Properties": {
+                        "StateMachineName": self.id,
+                        "RoleArn": { "Ref": "StateMachineRoleArn" },
+                        "Definition": asl,
+                        "DefinitionSubstitutions": substitutions,
+                    },
+                },
+            },
+        });
+        Ok(serde_json::to_string_pretty(&template).unwrap_or_default())
+    }
+
+    /// Render a Terraform `aws_sfn_state_machine` resource defining an equivalent state machine,
+    /// see [`MachineDefinition::to_asl`].
+    ///
+    /// The role is read from the `state_machine_role_arn` variable and the Lambda functions from
+    /// the `lambda_arns` map variable, keyed by the alphanumeric characters of the state names
+    pub fn to_terraform(&self) -> Result<String, StateMachineError> {
+        let asl = self.to_asl(|state| format!("${{var.lambda_arns.{}}}", placeholder(state)))?;
+        let definition = serde_json::to_string_pretty(&asl).unwrap_or_default().replace('\n', "\n  ");
+        Ok(format!(
+            "resource \"aws_sfn_state_machine\" \"{}\" {{\n  name       = \"{}\"\n  role_arn   = var.state_machine_role_arn\n  definition = jsonencode({})\n}}\n",
+            placeholder(&self.id), self.id, definition,
+        ))
+    }
+}
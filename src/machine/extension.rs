@@ -0,0 +1,41 @@
+use crate::machine::data;
+use crate::machine::error::StateMachineError;
+use crate::machine::state::{RetryBlock, StateMachine, StepDefinition};
+
+
+/// A set of reusable steps published by a separate crate, e.g. steps reading and writing a
+/// database, installed with [`StateMachine::extend`].
+///
+/// The steps are regular steps: their attempts, errors and retries are recorded in the history
+/// like those of the machine, and they are retried with the retry blocks of the extension
+pub trait StateExtension<'a, T: data::DeserializeStateData> {
+    /// The name of the extension, the prefix of the ids of its steps, e.g. `Postgres`
+    fn name(&self) -> &str;
+
+    /// The steps of the extension, appended to the machine in their order
+    fn steps(&self) -> Vec<StepDefinition<'a, T>>;
+
+    /// The retry blocks of the steps, by step id without the prefix
+    fn retry_blocks(&self) -> Vec<(String, Vec<RetryBlock>)> {
+        Vec::new()
+    }
+
+    /// Configure the machine once the steps are added, e.g. to register the error types of the
+    /// extension with [`StateMachine::error_codes_of`] or [`StateMachine::retry_errors_of`]
+    fn configure(&self, _machine: &mut StateMachine<'a, T>) {}
+}
+
+impl<'a, T: data::DeserializeStateData> StateMachine<'a, T> {
+    /// Install the steps of an extension under its name, see [`StateMachine::merge`].
+    ///
+    /// The machine is left untouched when the ids of the steps collide with existing ones
+    pub fn extend<E: StateExtension<'a, T> + ?Sized>(&mut self, extension: &E) -> Result<(), StateMachineError> {
+        let prefix = extension.name();
+        self.merge(prefix, extension.steps())?;
+        for (node, retry_blocks) in extension.retry_blocks() {
+            self.set_retry_blocks(&format!("{}.{}", prefix, node), retry_blocks)?;
+        }
+        extension.configure(self);
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::RwLock;
+
+
+/// A source of feature flags, checked before executing the steps guarded by a flag,
+/// see [`StateMachine::set_feature_flag`](crate::machine::state::StateMachine::set_feature_flag)
+pub trait FeatureFlagProvider: fmt::Debug + Send + Sync {
+    /// Whether the flag is on
+    fn is_enabled(&self, flag: &str) -> bool;
+}
+
+/// A [`FeatureFlagProvider`] holding the flags in memory, they can be switched while
+/// executions are running. Unknown flags are on
+#[derive(Debug, Default)]
+pub struct InMemoryFlags {
+    flags: RwLock<HashMap<String, bool>>,
+}
+
+impl InMemoryFlags {
+    /// Create a provider without any flag
+    pub fn new() -> Self {
+        InMemoryFlags::default()
+    }
+
+    /// Switch a flag on or off
+    pub fn set(&self, flag: &str, enabled: bool) {
+        self.flags.write().unwrap().insert(flag.to_string(), enabled);
+    }
+}
+
+impl FeatureFlagProvider for InMemoryFlags {
+    fn is_enabled(&self, flag: &str) -> bool {
+        self.flags.read().unwrap().get(flag).copied().unwrap_or(true)
+    }
+}
@@ -0,0 +1,43 @@
+use std::error::Error;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use crate::machine::data;
+use crate::machine::error::StateMachineError;
+use crate::machine::state::StateMachine;
+
+
+/// A handler taking a json event and returning a json result, like the handlers of
+/// `lambda_runtime`, so that the code of a Lambda function can run as a step, see
+/// [`StateMachine::handler`]
+pub trait Handler<Input, Output> {
+    /// Handle an event
+    fn call(&self, event: Input) -> Result<Output, Box<dyn Error>>;
+}
+
+impl<'a, T: data::DeserializeStateData + Serialize> StateMachine<'a, T> {
+    /// Run a [`Handler`] as the function of a step, e.g.
+    /// `StateMachine::handler::<Greeter, _, _>`.
+    ///
+    /// The handler is created with its `Default` implementation on every call. Its event is
+    /// deserialized from the json of the shared data, and the fields of its result, a json
+    /// object, overwrite those of the shared data, like a `ResultPath` of `$` merging the output.
+    /// The shared data is left untouched when the handler fails
+    pub fn handler<H, Input, Output>(data: &mut T) -> Result<(), Box<dyn Error>>
+    where
+        H: Handler<Input, Output> + Default,
+        Input: DeserializeOwned,
+        Output: Serialize,
+    {
+        let mut json = serde_json::to_value(&*data)?;
+        let output = serde_json::to_value(H::default().call(serde_json::from_value(json.clone())?)?)?;
+        match (&mut json, output) {
+            (Value::Object(fields), Value::Object(output)) => fields.extend(output),
+            (_, output) => return Err(Box::new(StateMachineError {
+                message: format!("The result of the handler must be a json object, got {}", output),
+            })),
+        }
+        *data = T::from_json(&json.to_string())?;
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::machine::data;
+use crate::machine::error::StateMachineError;
+use crate::machine::storm::StormAlert;
+use crate::machine::watchdog::StallEvent;
+
+
+/// The outcome of a step recorded in the history
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EventOutcome {
+    /// the step was executed successfully
+    Succeeded,
+    /// the condition of a choice step was false, or the execution did not take a route step,
+    /// its function was not executed
+    Skipped,
+    /// the step failed with the given error
+    Failed(String),
+    /// the error of the step was caught by the catch block at the given index
+    Caught {
+        /// the index of the catch block
+        block: usize,
+        /// the caught error
+        error: String,
+    },
+}
+
+/// An event of the history, recorded for every visited step and catch block
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEvent {
+    /// the id of the step
+    pub node: String,
+    /// the outcome of the step
+    pub outcome: EventOutcome,
+    /// number of times the function of the step was executed
+    pub attempts: u32,
+    /// time spent waiting between the retries
+    pub retry_delay: Duration,
+    /// time spent in the step, retries included
+    pub duration: Duration,
+    /// time spent in the sleep of a sleep step
+    #[serde(default)]
+    pub wait: Duration,
+    /// time spent serializing the shared data, for the snapshots and the size limit
+    #[serde(default)]
+    pub serialization: Duration,
+    /// the messages of the sources of the error failing the step, outermost first
+    #[serde(default)]
+    pub causes: Vec<String>,
+    /// the backtrace of the panic failing the step, when captured, see [`PanicError`](crate::machine::error::PanicError)
+    #[serde(default)]
+    pub backtrace: Option<String>,
+    /// json snapshot of the shared data before the step, when snapshots are enabled
+    pub data_before: Option<String>,
+    /// json snapshot of the shared data after the step, when snapshots are enabled
+    pub data_after: Option<String>,
+    /// the transaction committed or rolled back by the event, which is not a step of the
+    /// machine, see [`StateMachine::transaction`](crate::machine::state::StateMachine::transaction)
+    #[serde(default)]
+    pub transaction: Option<String>,
+}
+
+impl HistoryEvent {
+    pub(crate) fn new(node: &str, outcome: EventOutcome) -> Self {
+        HistoryEvent {
+            node: node.to_string(),
+            outcome,
+            attempts: 0,
+            retry_delay: Duration::ZERO,
+            duration: Duration::ZERO,
+            wait: Duration::ZERO,
+            serialization: Duration::ZERO,
+            causes: Vec::new(),
+            backtrace: None,
+            data_before: None,
+            data_after: None,
+            transaction: None,
+        }
+    }
+
+    /// number of retries of the step
+    pub fn retries(&self) -> u32 {
+        self.attempts.saturating_sub(1)
+    }
+
+    /// The label of the event in the execution path, the id of the step, or
+    /// `<step>.Catch<index>` for a catch block
+    pub fn label(&self) -> String {
+        match &self.outcome {
+            EventOutcome::Caught { block, .. } => format!("{}.Catch{}", self.node, block),
+            _ => self.node.clone(),
+        }
+    }
+}
+
+/// The point of a step at which the shared data is reconstructed, see [`ExecutionHistory::data_at`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Snapshot {
+    /// before the step, and its next function, were executed
+    Before,
+    /// after the step, and the catch block handling its error if any, were executed
+    After,
+}
+
+/// The history of the last execution of a state machine
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionHistory {
+    /// the id of the state machine
+    pub machine_id: String,
+    /// the tags attached to the execution, see [`ExecutionOptions`](crate::machine::state::ExecutionOptions)
+    #[serde(default)]
+    pub tags: BTreeMap<String, String>,
+    /// the routing bucket of the execution, from 0 to 99, see [`State::Route`](crate::machine::state::State::Route)
+    #[serde(default)]
+    pub routing_bucket: u8,
+    /// the routing key of the execution, see [`ExecutionOptions::routing_key`](crate::machine::state::ExecutionOptions::routing_key)
+    #[serde(default)]
+    pub routing_key: Option<String>,
+    /// the seed of the execution, see [`ExecutionOptions::seed`](crate::machine::state::ExecutionOptions::seed)
+    #[serde(default)]
+    pub seed: u64,
+    /// the events, in their order of occurrence
+    pub events: Vec<HistoryEvent>,
+    /// the stalls detected during the execution, see [`StallEvent`]
+    #[serde(default)]
+    pub stalls: Vec<StallEvent>,
+    /// the pauses of the retries of the steps raised during the execution, see [`StormAlert`]
+    #[serde(default)]
+    pub storm_alerts: Vec<StormAlert>,
+    /// the error which ended the execution, if it failed
+    pub error: Option<String>,
+}
+
+impl ExecutionHistory {
+    pub(crate) fn new(machine_id: &str) -> Self {
+        ExecutionHistory { machine_id: machine_id.to_string(), ..Default::default() }
+    }
+
+    /// The labels of the visited steps and catch blocks, see [`HistoryEvent::label`]
+    pub fn path(&self) -> Vec<String> {
+        self.events.iter().map(HistoryEvent::label).collect()
+    }
+
+    /// Number of retries of a step
+    pub fn retries(&self, node: &str) -> u32 {
+        self.events.iter().filter(|event| event.node == node).map(HistoryEvent::retries).sum()
+    }
+
+    /// Number of retries of all the steps
+    pub fn total_retries(&self) -> u32 {
+        self.events.iter().map(HistoryEvent::retries).sum()
+    }
+
+    /// Reconstruct the shared data as it was before or after a step.
+    ///
+    /// It requires the snapshots to be enabled, see [`StateMachine::enable_snapshots`](crate::machine::state::StateMachine::enable_snapshots)
+    pub fn data_at<T: data::DeserializeStateData>(&self, node: &str, snapshot: Snapshot) -> Result<T, Box<dyn Error>> {
+        let mut events = self.events.iter().filter(|event| event.node == node);
+        let json = match snapshot {
+            Snapshot::Before => events.next().and_then(|event| event.data_before.as_ref()),
+            Snapshot::After => events.next_back().and_then(|event| event.data_after.as_ref()),
+        };
+        match json {
+            Some(json) => T::from_json(json),
+            None => Err(Box::new(StateMachineError {
+                message: format!("no snapshot recorded for step {}", node),
+            })),
+        }
+    }
+
+    /// The value of a tag attached to the execution
+    pub fn tag(&self, key: &str) -> Option<&str> {
+        self.tags.get(key).map(String::as_str)
+    }
+
+    /// Whether the execution succeeded
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Encode the history with the given codec, see [`data::DataCodec`]
+    pub fn encode<C: data::DataCodec>(&self) -> Result<Vec<u8>, StateMachineError> {
+        C::encode(self).map_err(|err| StateMachineError { message: format!("Cannot encode the execution history as {}: {}", C::NAME, err) })
+    }
+
+    /// Decode a history encoded with the given codec
+    pub fn decode<C: data::DataCodec>(bytes: &[u8]) -> Result<Self, StateMachineError> {
+        C::decode(bytes).map_err(|err| StateMachineError { message: format!("Invalid execution history: {}", err) })
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::error::Error;
+use std::time::Duration;
+use serde_json::Value;
+use crate::machine::backoff::BackoffConfig;
+use crate::machine::data;
+use crate::machine::error::StateMachineError;
+use crate::machine::state::{ErrorBlock, RetryBlock, State, StateMachine, StepDefinition};
+
+
+// Define the function signature of the steps
+type StateFunction<T> = fn(&mut T) -> Result<(), Box<dyn Error>>;
+
+fn strings(value: &Value) -> Vec<String> {
+    value.as_array().into_iter().flatten().filter_map(|v| v.as_str().map(String::from)).collect()
+}
+
+// The Retry entries of a state, the backoff of a step multiplying its delay by an integer
+fn retry_blocks(name: &str, state: &Value, problems: &mut Vec<String>) -> Vec<RetryBlock> {
+    state["Retry"].as_array().into_iter().flatten().filter_map(|retrier| {
+        // the defaults of Step Functions
+        let interval = retrier["IntervalSeconds"].as_u64().unwrap_or(1);
+        let rate = retrier["BackoffRate"].as_f64().unwrap_or(2.0);
+        let attempts = retrier["MaxAttempts"].as_u64().unwrap_or(3).min(u64::from(u32::MAX)) as u32;
+        if rate.fract() != 0.0 || !(1.0..=f64::from(u32::MAX)).contains(&rate) {
+            problems.push(format!("the BackoffRate {} of state {} is not supported, it must be a whole number", rate, name));
+            return None;
+        }
+        Some(RetryBlock {
+            error_equals: strings(&retrier["ErrorEquals"]),
+            max_retries: attempts,
+            backoff: BackoffConfig {
+                max_retries: Some(attempts),
+                initial_delay: Duration::from_secs(interval),
+                multiplier: rate as u32,
+                ..Default::default()
+            },
+        })
+    }).collect()
+}
+
+impl<'a, T: data::DeserializeStateData> StateMachine<'a, T> {
+    /// Add the steps of an Amazon States Language definition, e.g. exported from AWS, mapping the
+    /// `Resource` of its Task states to local functions with the `handlers` table.
+    ///
+    /// The states are followed from `StartAt` along their `Next` transitions, until a state marked
+    /// as the end or a Succeed state. Task, Wait and Pass states are supported, with their
+    /// Retry entries as retry blocks capped at their `MaxAttempts`, whose `BackoffRate` must be a
+    /// whole number. A Catch entry is supported when it leads to a Task state
+    /// continuing with the same state as the catching one, which becomes the catch block, like
+    /// [`MachineDefinition::to_asl`](crate::machine::definition::MachineDefinition::to_asl) exports them.
+    ///
+    /// The definition is rejected, and the machine left untouched, with the list of the unmapped
+    /// resources and unsupported states, e.g. Choice or Parallel states, fractional backoff rates or
+    /// catch targets with Retry entries
+    pub fn import_asl(&mut self, asl: &Value, handlers: &HashMap<&str, StateFunction<T>>) -> Result<(), StateMachineError> {
+        let states = match asl["States"].as_object() {
+            Some(states) => states,
+            None => return Err(StateMachineError { message: String::from("Invalid ASL definition: no States") }),
+        };
+        let mut unmapped: BTreeSet<String> = BTreeSet::new();
+        let mut problems: Vec<String> = Vec::new();
+        let mut handler = |resource: &Value| -> StateFunction<T> {
+            let resource = resource.as_str().unwrap_or_default();
+            match handlers.get(resource) {
+                Some(function) => *function,
+                None => {
+                    unmapped.insert(resource.to_string());
+                    Self::pass
+                },
+            }
+        };
+
+        let mut steps: Vec<(StepDefinition<'a, T>, Vec<RetryBlock>)> = Vec::new();
+        let mut visited: HashSet<&str> = HashSet::new();
+        let mut current = asl["StartAt"].as_str();
+        while let Some(name) = current {
+            if !visited.insert(name) {
+                problems.push(format!("state {} is visited twice, loops are not supported", name));
+                break;
+            }
+            let state = match states.get(name) {
+                Some(state) => state,
+                None => {
+                    problems.push(format!("state {} is not defined", name));
+                    break;
+                },
+            };
+            let next = state["Next"].as_str();
+            match state["Type"].as_str().unwrap_or_default() {
+                "Task" => {
+                    let mut step = StepDefinition::new(name, State::Task, handler(&state["Resource"]));
+                    let mut catch = Vec::new();
+                    for catcher in state["Catch"].as_array().into_iter().flatten() {
+                        let target = catcher["Next"].as_str().unwrap_or_default();
+                        match states.get(target) {
+                            Some(block) if block["Type"] == "Task" && block["Next"].as_str() == next && block.get("Catch").is_none() => {
+                                // a catch block runs once
+                                if block.get("Retry").is_some() {
+                                    problems.push(format!("the catcher {} of state {} cannot retry, catch blocks are not retried", target, name));
+                                }
+                                visited.insert(target);
+                                catch.push(ErrorBlock { error_equals: strings(&catcher["ErrorEquals"]), next: handler(&block["Resource"]) });
+                            },
+                            _ => problems.push(format!("the catcher of state {} must be a Task state continuing with the same state", name)),
+                        }
+                    }
+                    step.catch = (!catch.is_empty()).then_some(catch);
+                    steps.push((step, retry_blocks(name, state, &mut problems)));
+                },
+                "Wait" => match state["Seconds"].as_u64() {
+                    Some(seconds) => steps.push((StepDefinition::new(name, State::Sleep(seconds), Self::pass), Vec::new())),
+                    None => problems.push(format!("Wait state {} must wait a fixed number of Seconds", name)),
+                },
+                "Pass" => steps.push((StepDefinition::new(name, State::Pass, Self::pass), Vec::new())),
+                "Succeed" => break,
+                kind => problems.push(format!("{} state {} is not supported", kind, name)),
+            }
+            current = next;
+        }
+
+        problems.extend(unmapped.iter().map(|resource| format!("resource {} is not mapped to a function", resource)));
+        if !problems.is_empty() {
+            return Err(StateMachineError {
+                message: format!("Cannot import the ASL definition:\n- {}", problems.join("\n- ")),
+            });
+        }
+        let retry_blocks: Vec<(String, Vec<RetryBlock>)> = steps.iter().map(|(step, blocks)| (step.id.clone(), blocks.clone())).collect();
+        self.steps(steps.into_iter().map(|(step, _)| step))?;
+        for (id, blocks) in retry_blocks.into_iter().filter(|(_, blocks)| !blocks.is_empty()) {
+            self.set_retry_blocks(&id, blocks)?;
+        }
+        Ok(())
+    }
+}
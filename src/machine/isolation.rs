@@ -0,0 +1,62 @@
+use std::any::Any;
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::cell::{Cell, RefCell};
+use std::error::Error;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+use crate::machine::error::PanicError;
+
+
+// Define the function signature of the steps
+type StateFunction<T> = fn(&mut T) -> Result<(), Box<dyn Error>>;
+
+static HOOK: Once = Once::new();
+
+thread_local! {
+    // set while a callback of the machine runs, its panics are reported through the history
+    static ISOLATED: Cell<bool> = const { Cell::new(false) };
+    static BACKTRACE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+// Chain a hook to the panic hook, capturing the backtrace of the panics of the step functions
+// in place of printing them. Other panics are left to the previous hook, see
+// error::capture_panic_backtraces
+pub(crate) fn install_hook() {
+    HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if !ISOLATED.with(Cell::get) {
+                return previous(info);
+            }
+            // the backtrace is only captured when enabled, see RUST_BACKTRACE
+            let backtrace = Backtrace::capture();
+            let backtrace = (backtrace.status() == BacktraceStatus::Captured).then(|| backtrace.to_string());
+            BACKTRACE.with(|cell| *cell.borrow_mut() = backtrace);
+        }));
+    });
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    match (payload.downcast_ref::<&str>(), payload.downcast_ref::<String>()) {
+        (Some(message), _) => message.to_string(),
+        (_, Some(message)) => message.clone(),
+        _ => String::from("the step panicked"),
+    }
+}
+
+/// Run a callback of the machine, e.g. a choice condition or an invariant, a panic is returned
+/// as a [`PanicError`]. The panic hook is left as it is, unless the backtraces are captured
+pub(crate) fn isolate<R>(callback: impl FnOnce() -> R) -> Result<R, PanicError> {
+    let isolated = ISOLATED.with(|cell| cell.replace(true));
+    let result = panic::catch_unwind(AssertUnwindSafe(callback));
+    ISOLATED.with(|cell| cell.set(isolated));
+    result.map_err(|payload| {
+        let backtrace = BACKTRACE.with(|cell| cell.borrow_mut().take());
+        PanicError::new(panic_message(payload.as_ref()), backtrace)
+    })
+}
+
+/// Call a step function, a panic is returned as a [`PanicError`]
+pub(crate) fn call<T>(function: StateFunction<T>, data: &mut T) -> Result<(), Box<dyn Error>> {
+    isolate(|| function(data)).unwrap_or_else(|panic| Err(Box::new(panic)))
+}
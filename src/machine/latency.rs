@@ -0,0 +1,193 @@
+use std::fmt;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::machine::definition::{DefinitionDiff, MachineDefinition};
+use crate::machine::estimate::StepBudget;
+use crate::machine::history::{EventOutcome, ExecutionHistory};
+
+
+/// The latency and failures of a step or catch block across executions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeLatency {
+    /// the label of the step or catch block, see [`HistoryEvent::label`](crate::machine::history::HistoryEvent::label)
+    pub node: String,
+    /// the number of times the step was executed, skipped steps excluded
+    pub executions: usize,
+    /// the number of executions of the step which failed
+    pub failures: usize,
+    /// the median duration, retries included
+    pub p50: Duration,
+    /// the 95th percentile of the durations
+    pub p95: Duration,
+    /// the 99th percentile of the durations
+    pub p99: Duration,
+    /// the longest duration
+    pub max: Duration,
+    /// the 99th percentile of the durations without the delays between the retries, the time
+    /// actually spent running the step
+    #[serde(default)]
+    pub p99_running: Duration,
+}
+
+impl NodeLatency {
+    /// The share of the executions of the step which failed, from 0 to 1
+    pub fn failure_rate(&self) -> f64 {
+        if self.executions == 0 {
+            return 0.0;
+        }
+        self.failures as f64 / self.executions as f64
+    }
+}
+
+/// The latency percentiles and failure rates of the steps across executions,
+/// see [`LatencyReport::aggregate`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LatencyReport {
+    /// the number of aggregated executions
+    pub executions: usize,
+    /// the steps and catch blocks, in their order of first execution
+    pub nodes: Vec<NodeLatency>,
+}
+
+// The nearest-rank percentile of sorted durations
+fn percentile(sorted: &[Duration], percent: usize) -> Duration {
+    let rank = (sorted.len() * percent).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+impl LatencyReport {
+    /// Aggregate the durations and failures of the steps over executions, e.g. the archived
+    /// executions of a machine
+    pub fn aggregate<'h, I: IntoIterator<Item = &'h ExecutionHistory>>(histories: I) -> Self {
+        let mut report = LatencyReport::default();
+        let mut samples: Vec<(String, Vec<Duration>, Vec<Duration>, usize)> = Vec::new();
+        for history in histories {
+            report.executions += 1;
+            for event in history.events.iter().filter(|event| event.outcome != EventOutcome::Skipped) {
+                let label = event.label();
+                let index = match samples.iter().position(|(node, _, _, _)| *node == label) {
+                    Some(index) => index,
+                    None => {
+                        samples.push((label, Vec::new(), Vec::new(), 0));
+                        samples.len() - 1
+                    },
+                };
+                samples[index].1.push(event.duration);
+                samples[index].2.push(event.duration.saturating_sub(event.retry_delay));
+                samples[index].3 += usize::from(matches!(event.outcome, EventOutcome::Failed(_)));
+            }
+        }
+        report.nodes = samples.into_iter().map(|(node, mut durations, mut running, failures)| {
+            durations.sort();
+            running.sort();
+            NodeLatency {
+                node,
+                executions: durations.len(),
+                failures,
+                p50: percentile(&durations, 50),
+                p95: percentile(&durations, 95),
+                p99: percentile(&durations, 99),
+                max: durations[durations.len() - 1],
+                p99_running: percentile(&running, 99),
+            }
+        }).collect();
+        report
+    }
+
+    /// The aggregate of a step or catch block
+    pub fn node(&self, label: &str) -> Option<&NodeLatency> {
+        self.nodes.iter().find(|node| node.node == label)
+    }
+
+    /// Render the report as a markdown table
+    pub fn to_markdown(&self) -> String {
+        let mut table = String::from("| step | executions | p50 | p95 | p99 | max | failure rate |\n|---|---:|---:|---:|---:|---:|---:|\n");
+        for node in &self.nodes {
+            table.push_str(&format!("| {} | {} | {:?} | {:?} | {:?} | {:?} | {:.1}% |\n", node.node, node.executions,
+                node.p50, node.p95, node.p99, node.max, node.failure_rate() * 100.0));
+        }
+        table
+    }
+}
+
+impl fmt::Display for LatencyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "latency over {} executions", self.executions)?;
+        writeln!(f, "  {:<24} {:>10} {:>12} {:>12} {:>12} {:>8}", "step", "executions", "p50", "p95", "p99", "failed")?;
+        for node in &self.nodes {
+            writeln!(f, "  {:<24} {:>10} {:>12} {:>12} {:>12} {:>7.1}%", node.node, node.executions,
+                format!("{:?}", node.p50), format!("{:?}", node.p95), format!("{:?}", node.p99), node.failure_rate() * 100.0)?;
+        }
+        Ok(())
+    }
+}
+
+/// The timeout and retry interval suggested for a step, see [`MachineDefinition::tune`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeSuggestion {
+    /// the id of the step
+    pub node: String,
+    /// the time after which an attempt of the step can be considered stuck, the 99th
+    /// percentile of its durations times the factor
+    pub timeout: Duration,
+    /// the delay before the first retry of the step, the 99th percentile of its durations
+    /// without the delays between its retries, set on its retry blocks
+    pub retry_interval: Duration,
+    /// the expected latency of the step, its median duration, set on its budget
+    pub expected_latency: Duration,
+}
+
+/// The values suggested from the history of a machine, see [`MachineDefinition::tune`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tuning {
+    /// the suggestions, in the order of the steps
+    pub suggestions: Vec<NodeSuggestion>,
+    /// the stall window covering the longest timeout, see
+    /// [`StateMachine::set_stall_timeout`](crate::machine::state::StateMachine::set_stall_timeout)
+    pub stall_timeout: Option<Duration>,
+    /// the definition with the suggested retry intervals and expected latencies
+    pub definition: MachineDefinition,
+    /// the changes from the current definition to the suggested one
+    pub patch: DefinitionDiff,
+}
+
+impl MachineDefinition {
+    /// Suggest the timeouts and retry intervals of the steps from their latency across past
+    /// executions, so that tuning them is not guesswork.
+    ///
+    /// The timeout of a step is the 99th percentile of its durations times `factor`, at least 1,
+    /// saturating at [`Duration::MAX`]. Its retry interval is the 99th percentile of its durations
+    /// without the delays between its retries, which would otherwise grow with every tuning of
+    /// the intervals. The steps have no timeout of their own, the
+    /// longest one is suggested as the stall window of the machine. The retry intervals are set
+    /// on the retry blocks of the steps, and the medians on their budgets, in the returned
+    /// definition and patch. Steps absent from the report are left untouched
+    pub fn tune(&self, report: &LatencyReport, factor: f64) -> Tuning {
+        let mut definition = self.clone();
+        let mut suggestions = Vec::new();
+        for node in &mut definition.nodes {
+            let latency = match report.node(&node.id) {
+                Some(latency) => latency,
+                None => continue,
+            };
+            let suggestion = NodeSuggestion {
+                node: node.id.clone(),
+                // a nan factor is ignored by max, an infinite or overflowing timeout saturates
+                timeout: Duration::try_from_secs_f64(latency.p99.as_secs_f64() * factor.max(1.0)).unwrap_or(Duration::MAX),
+                retry_interval: latency.p99_running.max(Duration::from_millis(1)),
+                expected_latency: latency.p50,
+            };
+            for block in &mut node.retry_blocks {
+                block.initial_delay_ms = suggestion.retry_interval.as_millis().max(1);
+            }
+            node.budget = Some(StepBudget { latency: suggestion.expected_latency, ..node.budget.unwrap_or_default() });
+            suggestions.push(suggestion);
+        }
+        Tuning {
+            stall_timeout: suggestions.iter().map(|suggestion| suggestion.timeout).max(),
+            patch: self.diff(&definition),
+            suggestions,
+            definition,
+        }
+    }
+}
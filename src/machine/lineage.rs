@@ -0,0 +1,80 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use crate::machine::history::ExecutionHistory;
+
+
+/// The steps which wrote every top-level field of the shared data during an execution,
+/// see [`ExecutionHistory::lineage`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lineage {
+    /// the id of the state machine
+    pub machine_id: String,
+    /// the labels of the steps and catch blocks which changed a field, in their order of
+    /// execution. Fields no step changed are absent
+    pub fields: BTreeMap<String, Vec<String>>,
+}
+
+impl Lineage {
+    /// The step or catch block which last changed a field
+    pub fn last_writer(&self, field: &str) -> Option<&str> {
+        self.fields.get(field).and_then(|writers| writers.last()).map(String::as_str)
+    }
+
+    /// The steps and catch blocks which changed a field, in their order of execution
+    pub fn writers(&self, field: &str) -> &[String] {
+        self.fields.get(field).map_or(&[], Vec::as_slice)
+    }
+
+    /// The fields changed by a step or catch block
+    pub fn written_by(&self, label: &str) -> Vec<&str> {
+        self.fields.iter()
+            .filter(|(_, writers)| writers.iter().any(|writer| writer == label))
+            .map(|(field, _)| field.as_str())
+            .collect()
+    }
+}
+
+fn fields(json: Option<&String>) -> Option<Map<String, Value>> {
+    match serde_json::from_str(json?) {
+        Ok(Value::Object(fields)) => Some(fields),
+        _ => None,
+    }
+}
+
+impl ExecutionHistory {
+    /// Track which steps wrote every top-level field of the shared data, by diffing the
+    /// snapshots recorded before and after each step and catch block.
+    ///
+    /// The data restored by the rollback of a transaction is written by its `<name>.Rollback`
+    /// event. It requires the snapshots to be enabled, see [`StateMachine::enable_snapshots`](crate::machine::state::StateMachine::enable_snapshots),
+    /// the events without snapshots are ignored
+    pub fn lineage(&self) -> Lineage {
+        let mut lineage = Lineage { machine_id: self.machine_id.clone(), fields: BTreeMap::new() };
+        for event in &self.events {
+            let (before, after) = match (fields(event.data_before.as_ref()), fields(event.data_after.as_ref())) {
+                (Some(before), Some(after)) => (before, after),
+                _ => continue,
+            };
+            let changed = after.iter()
+                .filter(|(field, value)| before.get(*field) != Some(value))
+                .map(|(field, _)| field)
+                .chain(before.keys().filter(|field| !after.contains_key(*field)));
+            for field in changed {
+                lineage.fields.entry(field.clone()).or_default().push(event.label());
+            }
+        }
+        lineage
+    }
+}
+
+impl fmt::Display for Lineage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "lineage of {}", self.machine_id)?;
+        for (field, writers) in &self.fields {
+            writeln!(f, "  {} <- {}", field, writers.join(", "))?;
+        }
+        Ok(())
+    }
+}
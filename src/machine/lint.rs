@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crate::machine::analysis::has_function;
+use crate::machine::definition::{MachineDefinition, NodeDefinition, StateKind};
+use crate::machine::error;
+
+
+/// A task without any retry policy
+pub const TASK_WITHOUT_RETRY: &str = "task-without-retry";
+/// A step placed after a step marked as the end, which is never executed
+pub const UNREACHABLE_STEP: &str = "unreachable-step";
+/// A step marked as the end, the execution stops before running its function
+pub const END_STEP_NOT_EXECUTED: &str = "end-step-not-executed";
+/// A catch block which can never be reached
+pub const UNREACHABLE_CATCH: &str = "unreachable-catch";
+/// A retry policy which can never be applied
+pub const UNREACHABLE_RETRY: &str = "unreachable-retry";
+/// A sleep longer than the configured maximum
+pub const SLEEP_TOO_LONG: &str = "sleep-too-long";
+/// The default maximum duration of a sleep, in seconds: one year, the longest execution of a
+/// standard Step Functions workflow
+pub const DEFAULT_MAX_SLEEP_SECS: u64 = 365 * 24 * 60 * 60;
+/// A definition text which cannot be parsed, see [`diagnose_json`](crate::machine::diagnostics::diagnose_json)
+pub const INVALID_DEFINITION: &str = "invalid-definition";
+
+/// The severity of a lint diagnostic
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Severity {
+    /// worth knowing, usually intended
+    Info,
+    /// likely a mistake
+    Warning,
+    /// a mistake
+    Error,
+}
+
+/// A diagnostic raised by a lint rule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    /// the id of the rule
+    pub rule: String,
+    /// the severity of the diagnostic
+    pub severity: Severity,
+    /// the step the diagnostic is about
+    pub node: Option<String>,
+    /// a description of the issue
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.node {
+            Some(node) => write!(f, "{:?} [{}] {}: {}", self.severity, self.rule, node, self.message),
+            None => write!(f, "{:?} [{}] {}", self.severity, self.rule, self.message),
+        }
+    }
+}
+
+/// The configuration of the linter
+#[derive(Debug, Clone)]
+pub struct LintConfig {
+    /// rules which are not run
+    pub disabled: Vec<String>,
+    /// severities overriding the default severity of a rule
+    pub severities: HashMap<String, Severity>,
+    /// maximum duration of a sleep, in seconds, checked by the `sleep-too-long` rule,
+    /// [`DEFAULT_MAX_SLEEP_SECS`] by default
+    pub max_sleep_secs: Option<u64>,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        LintConfig {
+            disabled: Vec::new(),
+            severities: HashMap::new(),
+            max_sleep_secs: Some(DEFAULT_MAX_SLEEP_SECS),
+        }
+    }
+}
+
+/// The diagnostics raised by [`MachineDefinition::lint`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LintReport {
+    /// the diagnostics, in the order of the steps
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl LintReport {
+    /// The diagnostics with at least the given severity
+    pub fn at_least(&self, severity: Severity) -> Vec<&Diagnostic> {
+        self.diagnostics.iter().filter(|d| d.severity >= severity).collect()
+    }
+
+    /// Whether a diagnostic with the error severity was raised
+    pub fn has_errors(&self) -> bool {
+        !self.at_least(Severity::Error).is_empty()
+    }
+}
+
+impl fmt::Display for LintReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for diagnostic in &self.diagnostics {
+            writeln!(f, "{}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+struct Linter<'c> {
+    config: &'c LintConfig,
+    report: LintReport,
+}
+
+impl Linter<'_> {
+    fn raise(&mut self, rule: &str, severity: Severity, node: &NodeDefinition, message: String) {
+        if self.config.disabled.iter().any(|disabled| disabled == rule) {
+            return;
+        }
+        self.report.diagnostics.push(Diagnostic {
+            rule: rule.to_string(),
+            severity: self.config.severities.get(rule).copied().unwrap_or(severity),
+            node: Some(node.id.clone()),
+            message,
+        });
+    }
+}
+
+impl MachineDefinition {
+    /// Run the lint rules on the definition
+    pub fn lint(&self, config: &LintConfig) -> LintReport {
+        let mut linter = Linter { config, report: LintReport::default() };
+        let mut ended = None;
+
+        for node in &self.nodes {
+            if let Some(end) = &ended {
+                linter.raise(UNREACHABLE_STEP, Severity::Warning, node,
+                    format!("the step is placed after the end step {} and is never executed", end));
+                continue;
+            }
+            if node.end {
+                ended = Some(node.id.clone());
+                if has_function(node) {
+                    linter.raise(END_STEP_NOT_EXECUTED, Severity::Info, node,
+                        "the execution stops before running the function of the end step".to_string());
+                }
+                continue;
+            }
+
+            let retries = !node.retry.is_empty() || node.retry_blocks.iter().any(|block| block.max_retries > 0);
+            if node.state == StateKind::Task && !retries {
+                linter.raise(TASK_WITHOUT_RETRY, Severity::Warning, node,
+                    "the task does not retry any error".to_string());
+            }
+
+            if !has_function(node) && !node.catch.is_empty() {
+                linter.raise(UNREACHABLE_CATCH, Severity::Warning, node,
+                    format!("a {} step never fails, its catch blocks are never reached", node.state));
+            }
+            if !has_function(node) && (!node.retry.is_empty() || !node.retry_blocks.is_empty()) {
+                linter.raise(UNREACHABLE_RETRY, Severity::Warning, node,
+                    format!("a {} step never fails, its retry policies are never applied", node.state));
+            }
+            if let Some(index) = node.catch.iter().position(|patterns| error::matches_everything(patterns)) {
+                if index + 1 < node.catch.len() {
+                    linter.raise(UNREACHABLE_CATCH, Severity::Warning, node,
+                        format!("the catch blocks after block {} are unreachable, it catches every error", index));
+                }
+            }
+            if let Some(index) = node.retry_blocks.iter().position(|block| error::matches_everything(&block.error_equals)) {
+                if index + 1 < node.retry_blocks.len() {
+                    linter.raise(UNREACHABLE_RETRY, Severity::Warning, node,
+                        format!("the retry blocks after block {} are unreachable, it matches every error", index));
+                }
+            }
+
+            if let (StateKind::Sleep(secs), Some(max)) = (node.state, config.max_sleep_secs) {
+                if secs > max {
+                    linter.raise(SLEEP_TOO_LONG, Severity::Error, node,
+                        format!("the step sleeps for {}s, more than the maximum of {}s", secs, max));
+                }
+            }
+        }
+
+        linter.report
+    }
+}
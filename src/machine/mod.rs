@@ -5,4 +5,70 @@ pub mod error;
 /// state machine shared data
 pub mod data;
 /// exponential backoff
-pub mod backoff;
\ No newline at end of file
+pub mod backoff;
+/// machine definitions
+pub mod definition;
+/// static analysis of machine definitions
+pub mod analysis;
+/// workflow linter
+pub mod lint;
+/// execution history
+pub mod history;
+/// test utilities
+pub mod testing;
+/// workflow coverage
+pub mod coverage;
+/// differential replay of executions
+pub mod replay;
+/// payload offloading
+pub mod payload;
+/// execution archives
+pub mod archive;
+/// A/B experiments
+pub mod experiment;
+/// batch runs
+pub mod batch;
+/// feature flags
+pub mod flags;
+/// bounded model checking of machine definitions
+pub mod check;
+/// stall detection
+pub mod watchdog;
+/// execution profiling
+pub mod profile;
+/// compiled linear machines
+pub mod compile;
+/// AWS Step Functions export
+pub mod export;
+/// Amazon States Language import
+pub mod import;
+/// data lineage
+pub mod lineage;
+/// input projections of machine definitions
+pub mod projection;
+/// shadow executions
+pub mod shadow;
+/// execution comparison
+pub mod compare;
+/// retry storm protection
+pub mod storm;
+/// executor gauges
+pub mod stats;
+/// latency and cost estimates
+pub mod estimate;
+/// latency percentiles across executions
+pub mod latency;
+/// execution sampling
+pub mod sampling;
+/// transactional groups of steps
+pub mod transaction;
+/// third-party state libraries
+pub mod extension;
+/// html and JUnit execution reports
+pub mod report;
+/// definition diagnostics with source spans
+pub mod diagnostics;
+/// lambda-style handlers
+pub mod handler;
+/// panic isolation of the steps
+pub(crate) mod isolation;
@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use crate::machine::error::StateMachineError;
+
+
+/// A reference to a payload offloaded to a [`PayloadStore`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PayloadRef {
+    /// the key of the payload in the store
+    pub key: String,
+    /// the size of the payload, in bytes
+    pub size: usize,
+}
+
+/// A store holding large payloads outside of the shared data, which only keeps a
+/// [`PayloadRef`] to them. It keeps the history and its snapshots small
+pub trait PayloadStore: fmt::Debug {
+    /// Store a payload and return a reference to it
+    fn put(&self, payload: &str) -> Result<PayloadRef, Box<dyn Error>>;
+    /// Fetch a payload by its reference
+    fn get(&self, reference: &PayloadRef) -> Result<String, Box<dyn Error>>;
+    /// Store a payload under the key of a reference created by another store, which lets the
+    /// store hold the replicas of that store, see [`ReplicatedPayloadStore`]. Stores which
+    /// cannot choose their keys do not support it
+    fn put_at(&self, reference: &PayloadRef, _payload: &str) -> Result<(), Box<dyn Error>> {
+        Err(Box::new(StateMachineError {
+            message: format!("The store cannot hold the replica of payload {}", reference.key),
+        }))
+    }
+}
+
+/// A [`PayloadStore`] keeping the payloads in memory
+#[derive(Debug, Default)]
+pub struct MemoryPayloadStore {
+    payloads: Mutex<HashMap<String, String>>,
+    next_key: AtomicU64,
+}
+
+impl MemoryPayloadStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        MemoryPayloadStore::default()
+    }
+}
+
+impl PayloadStore for MemoryPayloadStore {
+    fn put(&self, payload: &str) -> Result<PayloadRef, Box<dyn Error>> {
+        let mut payloads = self.payloads.lock().unwrap();
+        // the keys stored with put_at are never overwritten
+        let key = loop {
+            let key = format!("payload-{}", self.next_key.fetch_add(1, Ordering::SeqCst));
+            if !payloads.contains_key(&key) {
+                break key;
+            }
+        };
+        payloads.insert(key.clone(), payload.to_string());
+        Ok(PayloadRef { key, size: payload.len() })
+    }
+
+    fn get(&self, reference: &PayloadRef) -> Result<String, Box<dyn Error>> {
+        match self.payloads.lock().unwrap().get(&reference.key) {
+            Some(payload) => Ok(payload.clone()),
+            None => Err(Box::new(StateMachineError {
+                message: format!("Payload not found: {}", reference.key),
+            })),
+        }
+    }
+
+    fn put_at(&self, reference: &PayloadRef, payload: &str) -> Result<(), Box<dyn Error>> {
+        self.payloads.lock().unwrap().insert(reference.key.clone(), payload.to_string());
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct Replication {
+    pending: Mutex<usize>,
+    drained: Condvar,
+    failures: AtomicU64,
+}
+
+/// A [`PayloadStore`] mirroring the payloads of a primary store to a secondary one, e.g. in
+/// another datacenter, so that they survive the loss of the primary.
+///
+/// The payloads are written to the primary store, and copied to the secondary one in the
+/// background, the writes do not wait for the replication. The reads fall back to the
+/// secondary store when the primary one fails. Archived executions can be kept in it with
+/// [`ExecutionArchive::to_json`](crate::machine::archive::ExecutionArchive::to_json)
+#[derive(Debug)]
+pub struct ReplicatedPayloadStore {
+    primary: Box<dyn PayloadStore + Send + Sync>,
+    secondary: Arc<dyn PayloadStore + Send + Sync>,
+    queue: Mutex<Option<Sender<(PayloadRef, String)>>>,
+    worker: Option<JoinHandle<()>>,
+    replication: Arc<Replication>,
+}
+
+impl ReplicatedPayloadStore {
+    /// Replicate the payloads of the primary store to the secondary one, which must support
+    /// [`PayloadStore::put_at`]
+    pub fn new<P, S>(primary: P, secondary: S) -> Self
+    where
+        P: PayloadStore + Send + Sync + 'static,
+        S: PayloadStore + Send + Sync + 'static,
+    {
+        let (sender, receiver) = mpsc::channel::<(PayloadRef, String)>();
+        let replication = Arc::new(Replication::default());
+        let secondary: Arc<dyn PayloadStore + Send + Sync> = Arc::new(secondary);
+        let worker = {
+            let (replication, secondary) = (replication.clone(), secondary.clone());
+            thread::spawn(move || {
+                for (reference, payload) in receiver {
+                    if secondary.put_at(&reference, &payload).is_err() {
+                        replication.failures.fetch_add(1, Ordering::SeqCst);
+                    }
+                    *replication.pending.lock().unwrap() -= 1;
+                    replication.drained.notify_all();
+                }
+            })
+        };
+        ReplicatedPayloadStore {
+            primary: Box::new(primary),
+            secondary,
+            queue: Mutex::new(Some(sender)),
+            worker: Some(worker),
+            replication,
+        }
+    }
+
+    /// Wait until every stored payload was copied to the secondary store
+    pub fn flush(&self) {
+        let mut pending = self.replication.pending.lock().unwrap();
+        while *pending > 0 {
+            pending = self.replication.drained.wait(pending).unwrap();
+        }
+    }
+
+    /// Number of payloads not yet copied to the secondary store
+    pub fn pending(&self) -> usize {
+        *self.replication.pending.lock().unwrap()
+    }
+
+    /// Number of payloads the secondary store failed to hold
+    pub fn failures(&self) -> u64 {
+        self.replication.failures.load(Ordering::SeqCst)
+    }
+}
+
+impl PayloadStore for ReplicatedPayloadStore {
+    fn put(&self, payload: &str) -> Result<PayloadRef, Box<dyn Error>> {
+        let reference = self.primary.put(payload)?;
+        if let Some(queue) = self.queue.lock().unwrap().as_ref() {
+            *self.replication.pending.lock().unwrap() += 1;
+            if queue.send((reference.clone(), payload.to_string())).is_err() {
+                *self.replication.pending.lock().unwrap() -= 1;
+                self.replication.failures.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+        Ok(reference)
+    }
+
+    fn get(&self, reference: &PayloadRef) -> Result<String, Box<dyn Error>> {
+        // the error of the primary store is kept when the payload is not replicated yet
+        self.primary.get(reference).or_else(|err| self.secondary.get(reference).map_err(|_| err))
+    }
+}
+
+impl Drop for ReplicatedPayloadStore {
+    // copy the remaining payloads before stopping the replication
+    fn drop(&mut self) {
+        self.queue.lock().unwrap().take();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// A value of the shared data which can be offloaded to a [`PayloadStore`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Payload<V> {
+    /// the value is held in the shared data
+    Inline(V),
+    /// the value is held in a store
+    Offloaded(PayloadRef),
+}
+
+impl<V: Serialize + DeserializeOwned + Clone> Payload<V> {
+    /// Move the value to the store, keeping only a reference to it.
+    /// An offloaded value is left untouched
+    pub fn offload(&mut self, store: &dyn PayloadStore) -> Result<(), Box<dyn Error>> {
+        if let Payload::Inline(value) = self {
+            let reference = store.put(&serde_json::to_string(value)?)?;
+            *self = Payload::Offloaded(reference);
+        }
+        Ok(())
+    }
+
+    /// Offload the value when it is larger than the given size, in bytes, once serialized
+    pub fn offload_above(&mut self, bytes: usize, store: &dyn PayloadStore) -> Result<(), Box<dyn Error>> {
+        if let Payload::Inline(value) = self {
+            let json = serde_json::to_string(value)?;
+            if json.len() > bytes {
+                *self = Payload::Offloaded(store.put(&json)?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the value, fetching it from the store when it was offloaded
+    pub fn load(&self, store: &dyn PayloadStore) -> Result<V, Box<dyn Error>> {
+        match self {
+            Payload::Inline(value) => Ok(value.clone()),
+            Payload::Offloaded(reference) => Ok(serde_json::from_str(&store.get(reference)?)?),
+        }
+    }
+
+    /// Whether the value was offloaded
+    pub fn is_offloaded(&self) -> bool {
+        matches!(self, Payload::Offloaded(_))
+    }
+}
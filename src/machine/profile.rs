@@ -0,0 +1,122 @@
+use std::cmp::Reverse;
+use std::fmt;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::machine::history::ExecutionHistory;
+
+
+/// The time spent in a step or a catch block of an execution
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeProfile {
+    /// the label of the step or catch block, see [`HistoryEvent::label`](crate::machine::history::HistoryEvent::label)
+    pub node: String,
+    /// the total time spent
+    pub wall: Duration,
+    /// the time spent waiting between the retries
+    pub retry: Duration,
+    /// the time spent sleeping in a sleep step
+    pub wait: Duration,
+    /// the time spent serializing the shared data
+    pub serialization: Duration,
+    /// the share of the execution time, in percent
+    pub percent: f64,
+}
+
+impl NodeProfile {
+    /// The time spent in the functions of the step, waits and serialization excluded
+    pub fn own(&self) -> Duration {
+        self.wall.saturating_sub(self.retry).saturating_sub(self.wait).saturating_sub(self.serialization)
+    }
+}
+
+/// The profile of an execution, see [`ExecutionHistory::profile`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    /// the id of the state machine
+    pub machine_id: String,
+    /// the time spent in all the steps
+    pub total: Duration,
+    /// the steps and catch blocks, in their order of execution
+    pub nodes: Vec<NodeProfile>,
+}
+
+impl ExecutionHistory {
+    /// Summarize the time spent in every step of the execution
+    pub fn profile(&self) -> Profile {
+        let mut nodes: Vec<NodeProfile> = Vec::new();
+        for event in &self.events {
+            let label = event.label();
+            let index = match nodes.iter().position(|node| node.node == label) {
+                Some(index) => index,
+                None => {
+                    nodes.push(NodeProfile {
+                        node: label,
+                        wall: Duration::ZERO,
+                        retry: Duration::ZERO,
+                        wait: Duration::ZERO,
+                        serialization: Duration::ZERO,
+                        percent: 0.0,
+                    });
+                    nodes.len() - 1
+                },
+            };
+            let node = &mut nodes[index];
+            node.wall += event.duration;
+            node.retry += event.retry_delay;
+            node.wait += event.wait;
+            node.serialization += event.serialization;
+        }
+        let total: Duration = nodes.iter().map(|node| node.wall).sum();
+        for node in &mut nodes {
+            node.percent = if total.is_zero() { 0.0 } else { node.wall.as_secs_f64() * 100.0 / total.as_secs_f64() };
+        }
+        Profile { machine_id: self.machine_id.clone(), total, nodes }
+    }
+}
+
+impl Profile {
+    /// The `n` slowest steps, slowest first
+    pub fn slowest(&self, n: usize) -> Vec<&NodeProfile> {
+        let mut nodes: Vec<&NodeProfile> = self.nodes.iter().collect();
+        nodes.sort_by_key(|node| Reverse(node.wall));
+        nodes.truncate(n);
+        nodes
+    }
+
+    /// Render the profile in the folded stack format of flamegraph tools, one
+    /// `machine;step[;retry|wait|serialization] <microseconds>` line per non-empty frame
+    pub fn to_flamegraph_folded(&self) -> String {
+        let mut folded = String::new();
+        for node in &self.nodes {
+            let frames = [
+                (None, node.own()),
+                (Some("retry"), node.retry),
+                (Some("wait"), node.wait),
+                (Some("serialization"), node.serialization),
+            ];
+            for (frame, duration) in frames {
+                if duration.is_zero() {
+                    continue;
+                }
+                match frame {
+                    Some(frame) => folded.push_str(&format!("{};{};{} {}\n", self.machine_id, node.node, frame, duration.as_micros())),
+                    None => folded.push_str(&format!("{};{} {}\n", self.machine_id, node.node, duration.as_micros())),
+                }
+            }
+        }
+        folded
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "profile of {}: {:?}", self.machine_id, self.total)?;
+        writeln!(f, "  {:<24} {:>12} {:>12} {:>12} {:>12} {:>7}", "step", "wall", "retry", "wait", "serialization", "%")?;
+        for node in &self.nodes {
+            writeln!(f, "  {:<24} {:>12} {:>12} {:>12} {:>12} {:>6.1}%", node.node,
+                format!("{:?}", node.wall), format!("{:?}", node.retry), format!("{:?}", node.wait),
+                format!("{:?}", node.serialization), node.percent)?;
+        }
+        Ok(())
+    }
+}
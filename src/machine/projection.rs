@@ -0,0 +1,107 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crate::machine::definition::{MachineDefinition, NodeDefinition, StateKind};
+
+
+/// What is known of a class of inputs, see [`MachineDefinition::project`].
+///
+/// Whatever is not set is considered unknown, and the steps depending on it reachable
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputSample {
+    /// the routing bucket of the executions, see [`StateMachine::routing_bucket`](crate::machine::state::StateMachine::routing_bucket)
+    pub routing_bucket: Option<u8>,
+    /// the state of the feature flags, by name
+    pub flags: BTreeMap<String, bool>,
+    /// the result of the condition of choice steps, by step id
+    pub choices: BTreeMap<String, bool>,
+}
+
+impl InputSample {
+    /// Set the routing bucket of the sample
+    pub fn routing_bucket(mut self, bucket: u8) -> Self {
+        self.routing_bucket = Some(bucket);
+        self
+    }
+
+    /// Set the state of a feature flag
+    pub fn flag(mut self, name: &str, enabled: bool) -> Self {
+        self.flags.insert(name.to_string(), enabled);
+        self
+    }
+
+    /// Set the result of the condition of a choice step
+    pub fn choice(mut self, node: &str, taken: bool) -> Self {
+        self.choices.insert(node.to_string(), taken);
+        self
+    }
+
+    // Whether the function of the step may run for the inputs of the sample
+    fn reaches(&self, node: &NodeDefinition) -> bool {
+        let enabled = node.flag.as_ref().and_then(|flag| self.flags.get(flag)).copied().unwrap_or(true);
+        let taken = match node.state {
+            StateKind::Route(from, to) => self.routing_bucket.map_or(true, |bucket| (from..to).contains(&bucket)),
+            StateKind::Choice => self.choices.get(&node.id).copied().unwrap_or(true),
+            _ => true,
+        };
+        enabled && taken
+    }
+}
+
+/// The view of a definition restricted to the steps reachable by a set of input samples,
+/// see [`MachineDefinition::project`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Projection {
+    /// the definition without the pruned steps, the executions of the samples visit its
+    /// steps in the same order
+    pub definition: MachineDefinition,
+    /// the steps whose function never runs for the samples, in their order of execution
+    pub pruned: Vec<String>,
+}
+
+impl fmt::Display for Projection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "projection of {}, {} pruned steps", self.definition.id, self.pruned.len())?;
+        for node in &self.definition.nodes {
+            writeln!(f, "  {} {}", node.id, node.state)?;
+        }
+        for id in &self.pruned {
+            writeln!(f, "- {}", id)?;
+        }
+        Ok(())
+    }
+}
+
+impl MachineDefinition {
+    /// Prune the steps which no input of the samples executes, to review what a class of inputs
+    /// actually runs.
+    ///
+    /// A step is pruned for a sample when its function is not executed: a route step not covering
+    /// the routing bucket of the sample, a choice step whose condition is false, or a step whose
+    /// feature flag is off, its fallback function is then executed in its place. The steps after
+    /// the step marked as the end are always pruned. A step is kept when one of the samples
+    /// reaches it. Without any sample every input is possible, and only the steps after the end
+    /// are pruned
+    pub fn project(&self, samples: &[InputSample]) -> Projection {
+        let end = self.nodes.iter().position(|node| node.end).map_or(self.nodes.len(), |end| end + 1);
+        let unknown = [InputSample::default()];
+        let samples = if samples.is_empty() { &unknown[..] } else { samples };
+
+        let mut definition = MachineDefinition { nodes: Vec::new(), ..self.clone() };
+        let mut pruned = Vec::new();
+        for (index, node) in self.nodes.iter().enumerate() {
+            // the step marked as the end is not executed either, but it ends the execution
+            if index < end && (node.end || samples.iter().any(|sample| sample.reaches(node))) {
+                definition.nodes.push(node.clone());
+            } else {
+                pruned.push(node.id.clone());
+            }
+        }
+        // the transactions keep their remaining steps
+        for transaction in &mut definition.transactions {
+            transaction.steps.retain(|id| !pruned.contains(id));
+        }
+        definition.transactions.retain(|transaction| !transaction.steps.is_empty());
+        Projection { definition, pruned }
+    }
+}
@@ -0,0 +1,218 @@
+use std::error::Error;
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::machine::{data, isolation};
+use crate::machine::error::{self, StateMachineError};
+use crate::machine::history::{EventOutcome, ExecutionHistory};
+use crate::machine::state::{code_of, CodeExtractor, State, StateMachine, StateNode};
+
+
+/// A value of the shared data which differs between the recorded and the replayed step
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DataDifference {
+    /// the path of the value in the json snapshot, `$` being the root, e.g. `$.items[0].name`
+    pub path: String,
+    /// the recorded value rendered as json, `None` when it was absent
+    pub recorded: Option<String>,
+    /// the replayed value rendered as json, `None` when it is absent
+    pub live: Option<String>,
+}
+
+/// A step replayed with the current handlers, see [`StateMachine::replay_with_live_handlers`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StepReplay {
+    /// the id of the step
+    pub node: String,
+    /// the recorded outcome of the step, `Caught` when a catch block handled its error
+    pub recorded: EventOutcome,
+    /// the outcome of the step with the current handlers, `None` when the step no longer exists
+    pub live: Option<EventOutcome>,
+    /// the values of the shared data which differ after the step
+    pub differences: Vec<DataDifference>,
+}
+
+impl StepReplay {
+    /// Whether the step behaves as it did when it was recorded
+    pub fn matches(&self) -> bool {
+        self.live.as_ref() == Some(&self.recorded) && self.differences.is_empty()
+    }
+}
+
+/// The result of a differential replay, see [`StateMachine::replay_with_live_handlers`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayReport {
+    /// the id of the state machine
+    pub machine_id: String,
+    /// the replayed steps, in the order of the recorded execution
+    pub steps: Vec<StepReplay>,
+}
+
+impl ReplayReport {
+    /// The steps which no longer behave as they did when they were recorded
+    pub fn regressions(&self) -> Vec<&StepReplay> {
+        self.steps.iter().filter(|step| !step.matches()).collect()
+    }
+
+    /// Whether every step behaves as it did when it was recorded
+    pub fn is_identical(&self) -> bool {
+        self.steps.iter().all(StepReplay::matches)
+    }
+}
+
+fn render(value: Option<&String>) -> &str {
+    value.map_or("<absent>", String::as_str)
+}
+
+impl fmt::Display for ReplayReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let regressions = self.regressions();
+        if regressions.is_empty() {
+            return writeln!(f, "replay of {}: no regressions", self.machine_id);
+        }
+        writeln!(f, "replay of {}: {} regressions", self.machine_id, regressions.len())?;
+        for step in regressions {
+            match &step.live {
+                None => writeln!(f, "  {}: the step no longer exists", step.node)?,
+                Some(live) if *live != step.recorded => {
+                    writeln!(f, "  {}: outcome {:?} -> {:?}", step.node, step.recorded, live)?
+                },
+                Some(_) => (),
+            }
+            for difference in &step.differences {
+                writeln!(f, "  {} {}: {} -> {}", step.node, difference.path,
+                    render(difference.recorded.as_ref()), render(difference.live.as_ref()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Collect the differences between two json values, recursing into objects and arrays
+pub(crate) fn diff_values(path: &str, recorded: Option<&Value>, live: Option<&Value>, differences: &mut Vec<DataDifference>) {
+    match (recorded, live) {
+        (Some(Value::Object(before)), Some(Value::Object(after))) => {
+            for (key, value) in before {
+                diff_values(&format!("{}.{}", path, key), Some(value), after.get(key), differences);
+            }
+            for (key, value) in after.iter().filter(|(key, _)| !before.contains_key(*key)) {
+                diff_values(&format!("{}.{}", path, key), None, Some(value), differences);
+            }
+        },
+        (Some(Value::Array(before)), Some(Value::Array(after))) => {
+            for index in 0..before.len().max(after.len()) {
+                diff_values(&format!("{}[{}]", path, index), before.get(index), after.get(index), differences);
+            }
+        },
+        (before, after) if before != after => differences.push(DataDifference {
+            path: path.to_string(),
+            recorded: before.map(Value::to_string),
+            live: after.map(Value::to_string),
+        }),
+        _ => (),
+    }
+}
+
+fn no_snapshot(node: &str) -> StateMachineError {
+    StateMachineError { message: format!("no snapshot recorded for step {}", node) }
+}
+
+/// Replay a single step on the given data, retrying up to the recorded number of attempts
+/// without waiting, and running the catch block matching a remaining error
+fn replay_step<T: data::DeserializeStateData>(node: &StateNode<'_, T>, codes: &[CodeExtractor], attempts: u32, bucket: u8, enabled: bool, data: &mut T) -> EventOutcome {
+    if let (Some(next), true) = (node.next, enabled) {
+        if let Err(err) = isolation::call(next, data) {
+            return EventOutcome::Failed(code_of(codes, err.as_ref()));
+        }
+    }
+    let fallback = node.flag.as_ref().and_then(|flag| flag.fallback);
+    let function = match node.state {
+        _ if !enabled => match fallback {
+            Some(fallback) => fallback,
+            None => return EventOutcome::Skipped,
+        },
+        State::Task => node.state_function,
+        State::Choice(condition) => match isolation::isolate(condition) {
+            Ok(true) => node.state_function,
+            Ok(false) => return EventOutcome::Skipped,
+            Err(panic) => return EventOutcome::Failed(code_of(codes, &panic)),
+        },
+        State::Route(from, to) if (from..to).contains(&bucket) => node.state_function,
+        State::Route(..) => return EventOutcome::Skipped,
+        _ => return EventOutcome::Succeeded,
+    };
+
+    let mut result: Result<(), Box<dyn Error>> = Ok(());
+    for _ in 0..attempts.max(1) {
+        result = isolation::call(function, data);
+        if result.is_ok() {
+            break;
+        }
+    }
+    let error_code = match result {
+        Ok(()) => return EventOutcome::Succeeded,
+        Err(err) => code_of(codes, err.as_ref()),
+    };
+
+    let catcher = node.catch.as_ref()
+        .and_then(|catch| catch.iter().enumerate().find(|(_, block)| error::matches_any(&block.error_equals, &error_code)));
+    match catcher {
+        Some((block, handler)) => match isolation::call(handler.next, data) {
+            Ok(()) => EventOutcome::Caught { block, error: error_code },
+            Err(err) => EventOutcome::Failed(code_of(codes, err.as_ref())),
+        },
+        None => EventOutcome::Failed(error_code),
+    }
+}
+
+impl<'a, T: data::DeserializeStateData + Serialize> StateMachine<'a, T> {
+    /// Replay a recorded execution with the current handlers of the steps, to catch the
+    /// behavior changes introduced by new code.
+    ///
+    /// Every recorded step is executed on the data recorded before it, independently of the
+    /// other steps, and its outcome and resulting data are compared with the recorded ones.
+    /// Retries are replayed up to the recorded number of attempts without any delay, and sleeps
+    /// are skipped. Feature flags are checked as they are now. The shared data of the machine is
+    /// left untouched.
+    ///
+    /// It requires the history to be recorded with snapshots, see [`StateMachine::enable_snapshots`]
+    pub fn replay_with_live_handlers(&self, history: &ExecutionHistory) -> Result<ReplayReport, StateMachineError> {
+        let mut report = ReplayReport { machine_id: history.machine_id.clone(), steps: Vec::new() };
+        // the commits and rollbacks of the transactions are not steps, they are not replayed
+        let mut events = history.events.iter().filter(|event| event.transaction.is_none()).peekable();
+        while let Some(event) = events.next() {
+            // the catch block handling the error of the step, recorded as a separate event
+            let catch = events.next_if(|next| next.node == event.node && matches!(next.outcome, EventOutcome::Caught { .. }));
+            let (recorded, recorded_after) = match catch {
+                // the handler of the catch block failed when the execution ended on it
+                Some(catch) if events.peek().is_none() && history.error.is_some() => {
+                    (EventOutcome::Failed(history.error.clone().unwrap_or_default()), catch.data_after.as_ref())
+                },
+                Some(catch) => (catch.outcome.clone(), catch.data_after.as_ref()),
+                None => (event.outcome.clone(), event.data_after.as_ref()),
+            };
+
+            let node = match self.nodes.iter().find(|node| node.id == event.node) {
+                Some(node) => node,
+                None => {
+                    report.steps.push(StepReplay { node: event.node.clone(), recorded, live: None, differences: Vec::new() });
+                    continue;
+                },
+            };
+            let before = event.data_before.as_ref().ok_or_else(|| no_snapshot(&event.node))?;
+            let mut data = T::from_json(before).map_err(|err| StateMachineError { message: err.to_string() })?;
+            let live = replay_step(node, &self.codes, event.attempts, history.routing_bucket, node.enabled(self.flags.as_ref()), &mut data);
+
+            let mut differences = Vec::new();
+            if let Some(recorded_after) = recorded_after {
+                let recorded_value: Value = serde_json::from_str(recorded_after)
+                    .map_err(|err| StateMachineError { message: err.to_string() })?;
+                let live_value = serde_json::to_value(&data)
+                    .map_err(|err| StateMachineError { message: err.to_string() })?;
+                diff_values("$", Some(&recorded_value), Some(&live_value), &mut differences);
+            }
+            report.steps.push(StepReplay { node: event.node.clone(), recorded, live: Some(live), differences });
+        }
+        Ok(report)
+    }
+}
@@ -0,0 +1,160 @@
+use std::fmt::Write;
+use crate::machine::archive::ExecutionArchive;
+use crate::machine::history::{EventOutcome, ExecutionHistory, HistoryEvent};
+
+
+const STYLE: &str = "body{font-family:sans-serif;margin:2em;color:#222}\
+table{border-collapse:collapse;margin-bottom:1.5em}\
+th,td{border:1px solid #ccc;padding:4px 8px;text-align:left;vertical-align:top}\
+.status.succeeded{color:#1a7f37}.status.failed{color:#cf222e}\
+ol.graph{list-style:none;padding:0}\
+ol.graph li{display:inline-block;margin:0 1.5em 0.5em 0;padding:6px 10px;border:1px solid #999;border-radius:6px;position:relative;background:#f6f8fa;color:#888}\
+ol.graph li:not(:last-child)::after{content:\"\\2192\";position:absolute;right:-1.2em;color:#999}\
+ol.graph li.taken{background:#dafbe1;border-color:#1a7f37;color:#222;font-weight:bold}\
+ol.graph li.failed{background:#ffebe9;border-color:#cf222e;color:#222;font-weight:bold}\
+ol.graph li.skipped{background:#fff8c5;border-color:#9a6700;color:#222}\
+.state{font-weight:normal;font-size:0.8em;margin-left:0.5em}\
+pre{background:#f6f8fa;padding:6px;margin:4px 0;white-space:pre-wrap}";
+
+// Escape the text of an element or of an attribute value
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// A snapshot indented for reading, as recorded when it is not valid json
+fn pretty(snapshot: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(snapshot)
+        .ok()
+        .and_then(|value| serde_json::to_string_pretty(&value).ok())
+        .unwrap_or_else(|| snapshot.to_string())
+}
+
+fn outcome(event: &HistoryEvent) -> String {
+    match &event.outcome {
+        EventOutcome::Succeeded => String::from("Succeeded"),
+        EventOutcome::Skipped => String::from("Skipped"),
+        EventOutcome::Failed(error) => format!("Failed: {}", error),
+        EventOutcome::Caught { error, .. } => format!("Caught: {}", error),
+    }
+}
+
+impl ExecutionArchive {
+    /// Render the execution as a self-contained html page, e.g. to attach it to an incident
+    /// ticket.
+    ///
+    /// The page shows the steps of the machine in their order with the path taken by the
+    /// execution highlighted, then every event with its outcome, attempts, retry delay and
+    /// duration. The snapshots of the shared data, when recorded, can be expanded under their event
+    pub fn to_html(&self) -> String {
+        let history = &self.history;
+        let title = format!("Execution of {}", escape(&history.machine_id));
+        let mut html = String::new();
+        let _ = write!(html, "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<h1>{}</h1>\n",
+            title, STYLE, title);
+        match &history.error {
+            Some(error) => { let _ = writeln!(html, "<p class=\"status failed\">Failed: {}</p>", escape(error)); },
+            None => html.push_str("<p class=\"status succeeded\">Succeeded</p>\n"),
+        }
+        if !history.tags.is_empty() {
+            html.push_str("<table class=\"tags\">\n");
+            for (key, value) in &history.tags {
+                let _ = writeln!(html, "<tr><th>{}</th><td>{}</td></tr>", escape(key), escape(value));
+            }
+            html.push_str("</table>\n");
+        }
+
+        html.push_str("<h2>Steps</h2>\n<ol class=\"graph\">\n");
+        for node in &self.definition.nodes {
+            let events: Vec<&HistoryEvent> = history.events.iter().filter(|event| event.node == node.id).collect();
+            let class = if events.is_empty() {
+                ""
+            } else if events.iter().any(|event| matches!(event.outcome, EventOutcome::Failed(_))) {
+                "failed"
+            } else if events.iter().all(|event| event.outcome == EventOutcome::Skipped) {
+                "skipped"
+            } else {
+                "taken"
+            };
+            let _ = writeln!(html, "<li class=\"{}\" title=\"{}\">{}<span class=\"state\">{}</span></li>",
+                class, escape(&node.id), escape(&node.id), escape(&node.state.to_string()));
+        }
+        html.push_str("</ol>\n");
+
+        html.push_str("<h2>Events</h2>\n<table class=\"events\">\n<tr><th>#</th><th>step</th><th>outcome</th><th>attempts</th><th>retry delay</th><th>duration</th></tr>\n");
+        for (index, event) in history.events.iter().enumerate() {
+            let _ = writeln!(html, "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{:?}</td><td>{:?}</td></tr>",
+                index + 1, escape(&event.label()), escape(&outcome(event)), event.attempts, event.retry_delay, event.duration);
+            if event.data_before.is_none() && event.data_after.is_none() && event.causes.is_empty() {
+                continue;
+            }
+            html.push_str("<tr><td></td><td colspan=\"5\"><details><summary>details</summary>\n");
+            for cause in &event.causes {
+                let _ = writeln!(html, "<p>caused by: {}</p>", escape(cause));
+            }
+            for (name, snapshot) in [("data before", &event.data_before), ("data after", &event.data_after)] {
+                if let Some(snapshot) = snapshot {
+                    let _ = writeln!(html, "<p>{}</p>\n<pre>{}</pre>", name, escape(&pretty(snapshot)));
+                }
+            }
+            html.push_str("</details></td></tr>\n");
+        }
+        html.push_str("</table>\n</body>\n</html>\n");
+        html
+    }
+}
+
+fn seconds(event: &HistoryEvent) -> String {
+    format!("{:.3}", event.duration.as_secs_f64())
+}
+
+impl ExecutionHistory {
+    /// Render the execution as a JUnit XML test suite, so that CI systems display the health of
+    /// a workflow run as a test, e.g. with [`ExecutionAssert`](crate::machine::testing::ExecutionAssert).
+    ///
+    /// Every event is a test case named after its label, with its duration. Skipped steps are
+    /// skipped test cases, and failed steps failed ones carrying the error and its causes. A
+    /// failure handled by a catch block passes, the catch block being a test case of its own
+    pub fn to_junit(&self) -> String {
+        let name = escape(&self.machine_id);
+        let caught = |index: usize| self.events.get(index + 1)
+            .is_some_and(|next| next.node == self.events[index].node && matches!(next.outcome, EventOutcome::Caught { .. }));
+        let failures = (0..self.events.len())
+            .filter(|index| matches!(self.events[*index].outcome, EventOutcome::Failed(_)) && !caught(*index))
+            .count();
+        let skipped = self.events.iter().filter(|event| event.outcome == EventOutcome::Skipped).count();
+        let time: f64 = self.events.iter().map(|event| event.duration.as_secs_f64()).sum();
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let _ = writeln!(xml, "<testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.3}\">",
+            name, self.events.len(), failures, skipped, time);
+        for (index, event) in self.events.iter().enumerate() {
+            let _ = write!(xml, "  <testcase classname=\"{}\" name=\"{}\" time=\"{}\"", name, escape(&event.label()), seconds(event));
+            match &event.outcome {
+                EventOutcome::Skipped => xml.push_str(">\n    <skipped/>\n  </testcase>\n"),
+                EventOutcome::Failed(error) if caught(index) => {
+                    let _ = write!(xml, ">\n    <system-out>caught: {}</system-out>\n  </testcase>\n", escape(error));
+                },
+                EventOutcome::Failed(error) => {
+                    let mut details: Vec<&str> = event.causes.iter().map(String::as_str).collect();
+                    details.extend(event.backtrace.as_deref());
+                    let _ = write!(xml, ">\n    <failure message=\"{}\" type=\"{}\">{}</failure>\n  </testcase>\n",
+                        escape(error), escape(error), escape(&details.join("\n")));
+                },
+                _ => xml.push_str("/>\n"),
+            }
+        }
+        xml.push_str("</testsuite>\n");
+        xml
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::machine::{data, state};
+use crate::machine::history::ExecutionHistory;
+use crate::machine::state::StateMachine;
+
+
+/// Which executions keep their full history, see [`StateMachine::set_sampling_policy`].
+///
+/// The decision is taken once the execution ended, so that failed and slow executions are
+/// always kept. The default policy keeps every execution
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplingPolicy {
+    /// the duration from which an execution is kept as slow, `None` to keep none as slow
+    pub slow: Option<Duration>,
+    /// the share, from 0 to 1, of the other successful executions which are kept
+    pub success_rate: f64,
+}
+
+impl Default for SamplingPolicy {
+    fn default() -> Self {
+        SamplingPolicy { slow: None, success_rate: 1.0 }
+    }
+}
+
+/// Why the full history of an execution was kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RetentionReason {
+    /// the execution failed
+    Failed,
+    /// the execution lasted at least the slow duration of the policy
+    Slow,
+    /// the execution was part of the sample of the successful executions
+    Sampled,
+}
+
+/// The cheap record of an execution whose full history was not kept
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionSummary {
+    /// the id of the state machine
+    pub machine_id: String,
+    /// the tags attached to the execution
+    pub tags: BTreeMap<String, String>,
+    /// the seed of the execution, reproducing it with its tags and routing key
+    pub seed: u64,
+    /// the routing key of the execution
+    pub routing_key: Option<String>,
+    /// number of visited steps and catch blocks
+    pub steps: usize,
+    /// number of retries of all the steps
+    pub retries: u32,
+    /// time spent in the steps
+    pub duration: Duration,
+}
+
+impl ExecutionSummary {
+    /// Summarize an execution
+    pub fn of(history: &ExecutionHistory) -> Self {
+        ExecutionSummary {
+            machine_id: history.machine_id.clone(),
+            tags: history.tags.clone(),
+            seed: history.seed,
+            routing_key: history.routing_key.clone(),
+            steps: history.events.len(),
+            retries: history.total_retries(),
+            duration: duration(history),
+        }
+    }
+}
+
+/// An execution as retained by a [`SamplingPolicy`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RetainedExecution {
+    /// the full history, snapshots included
+    Full {
+        /// why the history was kept
+        reason: RetentionReason,
+        /// the history of the execution
+        history: ExecutionHistory,
+    },
+    /// the summary of a successful execution outside of the sample
+    Summary(ExecutionSummary),
+}
+
+fn duration(history: &ExecutionHistory) -> Duration {
+    history.events.iter().map(|event| event.duration).sum()
+}
+
+impl SamplingPolicy {
+    /// Why the full history of an execution is kept, `None` when only its summary is.
+    ///
+    /// The sampled executions derive from their seed, the same execution is always sampled
+    pub fn reason(&self, history: &ExecutionHistory) -> Option<RetentionReason> {
+        if !history.succeeded() {
+            return Some(RetentionReason::Failed);
+        }
+        if self.slow.is_some_and(|slow| duration(history) >= slow) {
+            return Some(RetentionReason::Slow);
+        }
+        let draw = (state::hash(history.seed, "sampling") % 10_000) as f64 / 10_000.0;
+        (draw < self.success_rate).then_some(RetentionReason::Sampled)
+    }
+
+    /// Retain an execution, its full history or its summary
+    pub fn retain(&self, history: &ExecutionHistory) -> RetainedExecution {
+        match self.reason(history) {
+            Some(reason) => RetainedExecution::Full { reason, history: history.clone() },
+            None => RetainedExecution::Summary(ExecutionSummary::of(history)),
+        }
+    }
+}
+
+impl<'a, T: data::DeserializeStateData> StateMachine<'a, T> {
+    /// Keep the full history of the failed and slow executions, and of a sample of the
+    /// successful ones, see [`SamplingPolicy`].
+    ///
+    /// The snapshots of the executions which are not kept are dropped from the history once
+    /// they end, see [`StateMachine::retained_execution`]
+    pub fn set_sampling_policy(&mut self, policy: SamplingPolicy) {
+        self.sampling = Some(policy);
+    }
+
+    /// The last execution as retained by the sampling policy of the machine, to be stored in
+    /// place of its history. Every execution is kept when no policy is set
+    pub fn retained_execution(&self) -> RetainedExecution {
+        self.sampling.unwrap_or_default().retain(&self.history)
+    }
+
+    // Drop the snapshots of the last execution when it is not kept
+    pub(crate) fn apply_sampling(&mut self) {
+        if let Some(None) = self.sampling.map(|policy| policy.reason(&self.history)) {
+            for event in &mut self.history.events {
+                event.data_before = None;
+                event.data_after = None;
+            }
+        }
+    }
+}
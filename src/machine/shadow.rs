@@ -0,0 +1,115 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crate::machine::data;
+use crate::machine::error::ExecutionError;
+use crate::machine::history::{EventOutcome, ExecutionHistory};
+use crate::machine::replay::{diff_values, DataDifference};
+use crate::machine::state::{ExecutionOptions, StateMachine};
+
+
+/// The comparison of an execution with its shadow, see [`StateMachine::execute_with_shadow`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShadowReport {
+    /// the id of the live state machine
+    pub machine_id: String,
+    /// the id of the shadow state machine
+    pub shadow_id: String,
+    /// the steps visited by the live execution, with their outcome
+    pub live_path: Vec<(String, EventOutcome)>,
+    /// the steps visited by the shadow execution, with their outcome
+    pub shadow_path: Vec<(String, EventOutcome)>,
+    /// the error of the live execution
+    pub live_error: Option<String>,
+    /// the error of the shadow execution
+    pub shadow_error: Option<String>,
+    /// the values of the final shared data which differ, `recorded` holding the live value
+    /// and `live` the shadow value
+    pub differences: Vec<DataDifference>,
+}
+
+impl ShadowReport {
+    /// The index of the first step where the paths diverge, `None` when they are identical
+    pub fn divergence(&self) -> Option<usize> {
+        let common = self.live_path.iter().zip(&self.shadow_path).position(|(live, shadow)| live != shadow);
+        match common {
+            Some(index) => Some(index),
+            None if self.live_path.len() != self.shadow_path.len() => Some(self.live_path.len().min(self.shadow_path.len())),
+            None => None,
+        }
+    }
+
+    /// Whether the shadow behaved like the live execution: same path, error and final data
+    pub fn matches(&self) -> bool {
+        self.divergence().is_none() && self.live_error == self.shadow_error && self.differences.is_empty()
+    }
+}
+
+fn path(history: &ExecutionHistory) -> Vec<(String, EventOutcome)> {
+    history.events.iter().map(|event| (event.node.clone(), event.outcome.clone())).collect()
+}
+
+fn render(step: Option<&(String, EventOutcome)>) -> String {
+    step.map_or(String::from("<end>"), |(node, outcome)| format!("{} {:?}", node, outcome))
+}
+
+impl fmt::Display for ShadowReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.matches() {
+            return writeln!(f, "shadow {} of {}: no differences", self.shadow_id, self.machine_id);
+        }
+        writeln!(f, "shadow {} of {}: differences", self.shadow_id, self.machine_id)?;
+        if let Some(index) = self.divergence() {
+            writeln!(f, "  paths diverge at step {}: {} -> {}", index,
+                render(self.live_path.get(index)), render(self.shadow_path.get(index)))?;
+        }
+        if self.live_error != self.shadow_error {
+            writeln!(f, "  error: {:?} -> {:?}", self.live_error, self.shadow_error)?;
+        }
+        for difference in &self.differences {
+            writeln!(f, "  {}: {} -> {}", difference.path,
+                difference.recorded.as_deref().unwrap_or("<absent>"), difference.live.as_deref().unwrap_or("<absent>"))?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, T: data::DeserializeStateData + Serialize> StateMachine<'a, T> {
+    /// Execute the machine, then run a new version of it in shadow on a copy of the same input,
+    /// and compare their paths, errors and resulting data before switching the traffic to it.
+    ///
+    /// The shadow machine should be built with side-effect-free handlers, e.g. stubs or handlers
+    /// replaying recorded outputs. Its shared data is overwritten with the input of the live
+    /// execution, copied through json, and it reproduces the tags, routing key and seed of the
+    /// live execution, see [`ExecutionOptions::reproducing`], so that both take the same routes
+    /// given the same routing seed. It runs after the live execution, whose result is returned unchanged. When
+    /// the input cannot be copied the shadow does not run, and the report holds the reason as
+    /// its error
+    pub fn execute_with_shadow(&mut self, options: ExecutionOptions, shadow: &mut StateMachine<'_, T>) -> (Result<(), ExecutionError>, ShadowReport) {
+        let input = serde_json::to_string(&*self.shared_data).map_err(|err| err.to_string())
+            .and_then(|json| T::from_json(&json).map_err(|err| err.to_string()));
+        let result = self.execute_with(options);
+
+        let mut report = ShadowReport {
+            machine_id: self.id.clone(),
+            shadow_id: shadow.id.clone(),
+            live_path: path(&self.history),
+            shadow_path: Vec::new(),
+            live_error: self.history.error.clone(),
+            shadow_error: None,
+            differences: Vec::new(),
+        };
+        match input {
+            Ok(input) => {
+                *shadow.shared_data = input;
+                let _ = shadow.execute_with(ExecutionOptions::reproducing(&self.history));
+                report.shadow_path = path(&shadow.history);
+                report.shadow_error = shadow.history.error.clone();
+                let live = serde_json::to_value(&*self.shared_data).ok();
+                let shadowed = serde_json::to_value(&*shadow.shared_data).ok();
+                diff_values("$", live.as_ref(), shadowed.as_ref(), &mut report.differences);
+            },
+            Err(err) => report.shadow_error = Some(format!("cannot copy the input: {}", err)),
+        }
+        (result, report)
+    }
+}
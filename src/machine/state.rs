@@ -1,14 +1,18 @@
-use std::collections::HashSet;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, Mutex};
+use serde::Serialize;
 use std::error::Error;
-use std::{thread, time::Duration};
+use std::{thread, time::{Duration, Instant}};
 use crate::machine::{error, backoff};
-use crate::machine::data;
+use crate::machine::estimate::StepBudget;
+use crate::machine::{coverage, data, experiment, flags, history, isolation, sampling, stats, storm, transaction, watchdog};
 // use log::{error, info, LevelFilter};
 // use env_logger::Builder;
 // use std::env;
 
 
-/// A logger method
+// A logger method
 // pub fn init_logger() {
 //     // Check if the RUST_LOG environment variable is set
 //     if let Ok(log_var) = env::var("RUST_LOG") {
@@ -36,6 +40,10 @@ pub enum State {
     Task,
     /// choice state is only executed if it the condition is true
     Choice(fn() -> bool),
+    /// route state is only executed for the executions whose routing bucket, from 0 to 99,
+    /// is within `[from, to)`. Steps with complementary ranges split the executions between
+    /// two variants of a workflow, see [`ExecutionOptions::routing_key`]
+    Route(u8, u8),
     /// sleep state does nothing but put the main thread to sleep for a while
     Sleep(u64),
     /// pass state does absolutely nothing
@@ -57,6 +65,39 @@ pub enum State {
 
 // Define the function signature for the state nodes
 type StateFunction<T> = fn(&mut T) -> Result<(), Box<dyn Error>>;
+// Define the function signature serializing the shared data into snapshots
+type SnapshotFunction<T> = fn(&T) -> Result<String, Box<dyn Error>>;
+// Define the function signature measuring the encoded size of the shared data
+type SizeFunction<T> = fn(&T) -> Result<usize, Box<dyn Error>>;
+// Define the function signature of the invariants of the shared data
+type InvariantFunction<T> = fn(&T) -> Result<(), String>;
+// Define the function signature reading the retry hints of an error, see [`error::Retryable`]
+type RetryClassifier = fn(&(dyn Error + 'static)) -> Option<(bool, Option<Duration>)>;
+
+// Define the function signature reading the code of an error, see [`error::ErrorCode`]
+pub(crate) type CodeExtractor = fn(&(dyn Error + 'static)) -> Option<String>;
+
+// The code of an error, given by the first registered error type matching it, or its message
+pub(crate) fn code_of(codes: &[CodeExtractor], err: &(dyn Error + 'static)) -> String {
+    codes.iter().find_map(|code| code(err)).unwrap_or_else(|| err.to_string())
+}
+
+// Record the messages of the sources of the error failing a step, outermost first, and the
+// backtrace of a panic
+fn record_causes(event: &mut history::HistoryEvent, err: &(dyn Error + 'static)) {
+    event.causes.clear();
+    let mut source = err.source();
+    while let Some(cause) = source {
+        event.causes.push(cause.to_string());
+        source = cause.source();
+    }
+    event.backtrace = err.downcast_ref::<error::PanicError>().and_then(|panic| panic.backtrace().map(String::from));
+}
+
+// The retry hints of the first registered error type matching the error
+fn classify(classifiers: &[RetryClassifier], err: &(dyn Error + 'static)) -> Option<(bool, Option<Duration>)> {
+    classifiers.iter().find_map(|classifier| classifier(err))
+}
 
 
 /// error block
@@ -68,20 +109,187 @@ pub struct ErrorBlock<T: data::DeserializeStateData>  {
     pub next: StateFunction<T>,
 }
 
+/// retry block
+///
+/// Defines how the errors listed in `error_equals` are retried. When several blocks of a step
+/// match an error, the first one is used
+#[derive(Debug, Clone)]
+pub struct RetryBlock {
+    /// error strings
+    pub error_equals: Vec<String>,
+    /// maximum number of retries, zero disables the retries for these errors
+    pub max_retries: u32,
+    /// backoff applied between the retries
+    pub backoff: backoff::BackoffConfig,
+}
+
+/// A step definition, used to add steps in bulk via [`StateMachine::steps`]
+///
+/// The fields mirror the arguments of [`StateMachine::step`], which makes it convenient
+/// to generate machines programmatically from data
+#[derive(Debug)]
+pub struct StepDefinition<'a, T: data::DeserializeStateData> {
+    /// the unique id of the step
+    pub id: String,
+    /// the state of the step
+    pub state: State,
+    /// the function executed by the step
+    pub state_function: StateFunction<T>,
+    /// an optional function executed before the step function
+    pub next: Option<StateFunction<T>>,
+    /// the errors caught by the step
+    pub catch: Option<Vec<ErrorBlock<T>>>,
+    /// the errors retried by the step
+    pub retry: Option<Vec<&'a str>>,
+    /// marks the step as the last one of the state machine
+    pub end: Option<bool>,
+}
+
+impl<'a, T: data::DeserializeStateData> StepDefinition<'a, T> {
+    /// Create a step definition without next, catch, retry and end attributes
+    pub fn new(id: &str, state: State, state_function: StateFunction<T>) -> Self {
+        StepDefinition {
+            id: id.to_string(),
+            state,
+            state_function,
+            next: None,
+            catch: None,
+            retry: None,
+            end: None,
+        }
+    }
+}
+
+/// The optional attributes of a step, see [`StateMachine::step_with`]
+///
+/// It is built with `..Default::default()`, or converted from the catch blocks or the retried
+/// errors when the step only needs one of them
+#[derive(Debug)]
+pub struct StepOptions<'a, T: data::DeserializeStateData> {
+    /// an optional function executed before the step function
+    pub next: Option<StateFunction<T>>,
+    /// the errors caught by the step
+    pub catch: Option<Vec<ErrorBlock<T>>>,
+    /// the errors retried by the step
+    pub retry: Option<Vec<&'a str>>,
+    /// marks the step as the last one of the state machine
+    pub end: bool,
+}
+
+impl<'a, T: data::DeserializeStateData> Default for StepOptions<'a, T> {
+    fn default() -> Self {
+        StepOptions { next: None, catch: None, retry: None, end: false }
+    }
+}
+
+impl<'a, T: data::DeserializeStateData> From<Vec<ErrorBlock<T>>> for StepOptions<'a, T> {
+    fn from(catch: Vec<ErrorBlock<T>>) -> Self {
+        StepOptions { catch: Some(catch), ..Default::default() }
+    }
+}
+
+impl<'a, T: data::DeserializeStateData> From<Vec<&'a str>> for StepOptions<'a, T> {
+    fn from(retry: Vec<&'a str>) -> Self {
+        StepOptions { retry: Some(retry), ..Default::default() }
+    }
+}
+
+/// Options of a single execution, see [`StateMachine::execute_with`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExecutionOptions {
+    /// key/value tags attached to the execution, e.g. the customer, region or trigger source.
+    /// They are recorded in the history of the execution
+    pub tags: BTreeMap<String, String>,
+    /// the key selecting the routing bucket of the execution, e.g. an execution or customer id.
+    /// Executions with the same key take the same route, executions without a key are
+    /// spread across the buckets
+    pub routing_key: Option<String>,
+    /// the seed from which every random choice of the execution derives, e.g. its routing
+    /// bucket when it has no routing key. It is recorded in the history, and generated from the
+    /// number of executions of the machine when not set
+    pub seed: Option<u64>,
+}
+
+impl ExecutionOptions {
+    /// The options reproducing a recorded execution: same tags, routing key and seed.
+    ///
+    /// The execution is reproduced by a machine with the same definition and routing seed
+    pub fn reproducing(history: &history::ExecutionHistory) -> Self {
+        ExecutionOptions {
+            tags: history.tags.clone(),
+            routing_key: history.routing_key.clone(),
+            seed: Some(history.seed),
+        }
+    }
+
+    /// Attach a tag to the execution
+    pub fn tag(mut self, key: &str, value: &str) -> Self {
+        self.tags.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Set the routing key of the execution
+    pub fn routing_key(mut self, key: &str) -> Self {
+        self.routing_key = Some(key.to_string());
+        self
+    }
+
+    /// Set the seed of the execution
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+}
+
+// FNV-1a hash of a seed and a key, stable across releases and platforms
+pub(crate) fn hash(seed: u64, key: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in seed.to_le_bytes().iter().chain(key.as_bytes()) {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// The routing bucket, from 0 to 99, of a routing key
+pub(crate) fn routing_bucket(seed: u64, key: &str) -> u8 {
+    (hash(seed, key) % 100) as u8
+}
+
+// The first invariant broken by the data after a step or catch block
+fn check_invariants<T>(invariants: &[(String, InvariantFunction<T>)], data: &T, node: &str) -> Option<error::ExecutionError> {
+    // a panicking invariant is violated, with the panic message
+    invariants.iter().find_map(|(name, invariant)| {
+        let result = isolation::isolate(|| invariant(data)).unwrap_or_else(|panic| Err(panic.message().to_string()));
+        result.err().map(|message| error::ExecutionError::InvariantViolated { invariant: name.clone(), node: node.to_string(), message })
+    })
+}
+
+// The feature flag guarding a step, see [`StateMachine::set_feature_flag`]
+#[derive(Debug)]
+pub(crate) struct NodeFlag<T> {
+    pub(crate) name: String,
+    pub(crate) fallback: Option<StateFunction<T>>,
+}
+
 /// Define the data structure for each element in the linked list
 #[derive(Debug)]
 pub struct StateNode<'a, T: data::DeserializeStateData> {
-    id: String,
-    state: State,
-    state_function: StateFunction<T>,
-    next: Option<StateFunction<T>>,
-    catch: Option<Vec<ErrorBlock<T>>>,
-    retry: Option<Vec<&'a str>>,
-    invocation_count: i8,
-    end: Option<bool>
+    pub(crate) id: String,
+    pub(crate) state: State,
+    pub(crate) state_function: StateFunction<T>,
+    pub(crate) next: Option<StateFunction<T>>,
+    pub(crate) catch: Option<Vec<ErrorBlock<T>>>,
+    pub(crate) retry: Option<Vec<&'a str>>,
+    pub(crate) retry_blocks: Vec<RetryBlock>,
+    pub(crate) flag: Option<NodeFlag<T>>,
+    pub(crate) budget: Option<StepBudget>,
+    pub(crate) invocation_count: i8,
+    pub(crate) end: Option<bool>
 }
 
 impl<'a, T: data::DeserializeStateData> StateNode<'a, T> {
+    #[allow(clippy::too_many_arguments)]
     fn new(id: &str, state: State, state_function: StateFunction<T>, next: Option<StateFunction<T>>, catch: Option<Vec<ErrorBlock<T>>>, retry: Option<Vec<&'a str>>, end: Option<bool>) -> Self {
         StateNode {
         id: id.to_string(),
@@ -90,33 +298,50 @@ impl<'a, T: data::DeserializeStateData> StateNode<'a, T> {
         invocation_count: 0,
         catch,
         retry,
+        retry_blocks: Vec::new(),
+        flag: None,
+        budget: None,
         next,
         end,
         }
     }
 
-    fn execute(&mut self, data: &mut T) -> Result<(), Box<dyn Error>> {
+    /// Whether the feature flag of the step, if any, is enabled
+    pub(crate) fn enabled(&self, provider: Option<&Arc<dyn flags::FeatureFlagProvider>>) -> bool {
+        match (&self.flag, provider) {
+            (Some(flag), Some(provider)) => provider.is_enabled(&flag.name),
+            _ => true,
+        }
+    }
+
+    /// Execute the step, returns whether its function was executed
+    fn execute(&self, data: &mut T, bucket: u8, enabled: bool) -> Result<bool, Box<dyn Error>> {
+        // a step disabled by its feature flag runs its fallback function, if any, in place of its own
+        if !enabled {
+            return match self.flag.as_ref().and_then(|flag| flag.fallback) {
+                Some(fallback) => isolation::call(fallback, data).map(|_| true),
+                None => Ok(false),
+            };
+        }
         // Perform actions specific to each state if needed
         match self.state {
             State::Task => {
                 // Execute the assigned function for the state
-                match (self.state_function)(data) {
-                    Ok(_) => (),
-                    Err(e) => {
-                        return Err(e);
-                    },
-                };
+                isolation::call(self.state_function, data)?;
             }
             State::Choice(func) => {
-                if func() {
-                    // Execute the assigned function for the state
-                    match (self.state_function)(data) {
-                        Ok(_) => (),
-                        Err(e) => {
-                            return Err(e);
-                        },
-                    };
+                if !isolation::isolate(func)? {
+                    return Ok(false);
                 }
+                // Execute the assigned function for the state
+                isolation::call(self.state_function, data)?;
+            }
+            State::Route(from, to) => {
+                if !(from..to).contains(&bucket) {
+                    return Ok(false);
+                }
+                // Execute the assigned function for the state
+                isolation::call(self.state_function, data)?;
             }
             State::Sleep(v) => {
                 thread::sleep(Duration::from_secs(v));
@@ -128,47 +353,230 @@ impl<'a, T: data::DeserializeStateData> StateNode<'a, T> {
             State::Map => {}
             State::CustomState => {}
         }
-        Ok(())
+        Ok(true)
     }
 }
 
 /// Define the StateMachine struct
 #[derive(Debug)]
 pub struct StateMachine<'a, T: data::DeserializeStateData> {
-    id: String,
-    nodes: Vec<StateNode<'a, T>>,
-    node_ids: HashSet<String>,
-    retries: i32,
-    shared_data: &'a mut T,
-    error_string: Option<String>
+    pub(crate) id: String,
+    pub(crate) nodes: Vec<StateNode<'a, T>>,
+    pub(crate) node_ids: HashSet<String>,
+    pub(crate) retries: i32,
+    pub(crate) backoff: backoff::BackoffConfig,
+    pub(crate) retry_budget: Option<u32>,
+    pub(crate) shared_data: &'a mut T,
+    pub(crate) error_string: Option<String>,
+    pub(crate) history: history::ExecutionHistory,
+    pub(crate) coverage: Option<Arc<Mutex<coverage::Coverage>>>,
+    pub(crate) storm: Option<Arc<storm::RetryStormGuard>>,
+    pub(crate) stats: Option<Arc<stats::ExecutorStats>>,
+    pub(crate) snapshot: Option<SnapshotFunction<T>>,
+    pub(crate) data_limit: Option<(usize, SizeFunction<T>)>,
+    pub(crate) routing_seed: u64,
+    pub(crate) executions: u64,
+    pub(crate) experiment: Option<experiment::Experiment>,
+    pub(crate) flags: Option<Arc<dyn flags::FeatureFlagProvider>>,
+    pub(crate) stall: Option<watchdog::StallConfig>,
+    pub(crate) sampling: Option<sampling::SamplingPolicy>,
+    pub(crate) transactions: Vec<transaction::Transaction<T>>,
+    pub(crate) invariants: Vec<(String, InvariantFunction<T>)>,
+    pub(crate) classifiers: Vec<RetryClassifier>,
+    pub(crate) codes: Vec<CodeExtractor>,
 }
 
 impl<'a, T: data::DeserializeStateData> StateMachine<'a, T> {
     /// Initialize the state machine with an empty list of nodes and an empty set of node IDs
     pub fn new(id: String, shared_data: &'a mut T, retries: i32) -> Self {
-        println!("Executing state machine: {} ........", id);
         StateMachine {
-            id,
             nodes: Vec::new(),
             node_ids: HashSet::new(),
             retries,
+            backoff: backoff::BackoffConfig::default(),
+            retry_budget: None,
+            history: history::ExecutionHistory::new(&id),
+            coverage: None,
+            storm: None,
+            stats: None,
+            snapshot: None,
+            data_limit: None,
+            routing_seed: 0,
+            executions: 0,
+            experiment: None,
+            flags: None,
+            stall: None,
+            sampling: None,
+            transactions: Vec::new(),
+            invariants: Vec::new(),
+            classifiers: Vec::new(),
+            codes: Vec::new(),
             shared_data,
             error_string: None,
+            id,
+        }
+    }
+
+    /// Set the configuration of the backoff used when retrying steps
+    pub fn set_backoff_config(&mut self, config: backoff::BackoffConfig) {
+        self.backoff = config;
+    }
+
+    /// Set the maximum number of retries, across all the steps, of a single execution.
+    ///
+    /// Once the budget is exhausted, a failing step fails with the
+    /// `States.RetryBudgetExhausted` error, which can be caught like any other error
+    pub fn set_retry_budget(&mut self, budget: u32) {
+        self.retry_budget = Some(budget);
+    }
+
+    /// Set the seed of the routing buckets, changing it reshuffles the executions between the routes
+    pub fn set_routing_seed(&mut self, seed: u64) {
+        self.routing_seed = seed;
+    }
+
+    /// The routing bucket, from 0 to 99, taken by the executions with the given routing key
+    pub fn routing_bucket(&self, key: &str) -> u8 {
+        routing_bucket(self.routing_seed, key)
+    }
+
+    /// Set the retry blocks of a step, which take precedence over its plain list of retried errors
+    pub fn set_retry_blocks(&mut self, node_id: &str, retry_blocks: Vec<RetryBlock>) -> Result<(), error::StateMachineError> {
+        match self.nodes.iter_mut().find(|node| node.id == node_id) {
+            Some(node) => {
+                node.retry_blocks = retry_blocks;
+                Ok(())
+            },
+            None => Err(error::StateMachineError {
+                message: format!("Node ID not found: {}", node_id),
+            }),
+        }
+    }
+
+    /// Report the executions which make no progress, that is no step completed within the
+    /// given window, instead of hanging silently.
+    ///
+    /// A watchdog thread calls `on_stall` at most once per step, the stalls are recorded in the
+    /// history. Sleep steps are expected waits and are never reported
+    pub fn set_stall_timeout(&mut self, window: Duration, on_stall: Option<fn(&watchdog::StallEvent)>) {
+        self.stall = Some(watchdog::StallConfig { window, on_stall });
+    }
+
+    /// Retry the errors of type `E` returned by the steps according to their [`error::Retryable`]
+    /// implementation.
+    ///
+    /// A retryable error is retried with the retries and backoff of the machine when no retry
+    /// block or retry list of the step matches it, and a retry-after delay replaces the backoff
+    /// delay of the next attempt. Retries stop at the first error which is not retryable, unless
+    /// the retries were requested by a retry block or list of the step
+    pub fn retry_errors_of<E: Error + error::Retryable + 'static>(&mut self) {
+        self.classifiers.push(|err| err.downcast_ref::<E>().map(|err| (err.is_retryable(), err.retry_after())));
+    }
+
+    /// Identify the errors of type `E` returned by the steps by their [`error::ErrorCode`] rather
+    /// than their message, in the retry and catch lists, the history and the execution errors
+    pub fn error_codes_of<E: Error + error::ErrorCode + 'static>(&mut self) {
+        self.codes.push(|err| err.downcast_ref::<E>().map(error::ErrorCode::code));
+    }
+
+    /// Add an invariant of the shared data, checked after every step and catch block.
+    ///
+    /// An invariant returning an error fails the execution with
+    /// [`ExecutionError::InvariantViolated`](error::ExecutionError::InvariantViolated), naming the
+    /// invariant and the step which broke it. The violation is not retried nor caught
+    pub fn add_invariant(&mut self, name: &str, invariant: InvariantFunction<T>) {
+        self.invariants.push((name.to_string(), invariant));
+    }
+
+    /// Set the provider of the feature flags guarding the steps, see [`StateMachine::set_feature_flag`]
+    pub fn set_feature_flags(&mut self, provider: Arc<dyn flags::FeatureFlagProvider>) {
+        self.flags = Some(provider);
+    }
+
+    /// Guard a step with a feature flag, checked before every execution of the step.
+    ///
+    /// When the flag is off, the step runs the fallback function in place of its own function
+    /// and next function, or is skipped without a fallback. Steps are enabled while no provider
+    /// is set
+    pub fn set_feature_flag(&mut self, node_id: &str, flag: &str, fallback: Option<StateFunction<T>>) -> Result<(), error::StateMachineError> {
+        match self.nodes.iter_mut().find(|node| node.id == node_id) {
+            Some(node) => {
+                node.flag = Some(NodeFlag { name: flag.to_string(), fallback });
+                Ok(())
+            },
+            None => Err(error::StateMachineError {
+                message: format!("Node ID not found: {}", node_id),
+            }),
         }
     }
 
     /// Add a new node to the state machine
+    #[allow(clippy::too_many_arguments)]
     pub fn step(&mut self, id: &str, state: State, state_function: StateFunction<T>, next: Option<StateFunction<T>>, catch: Option<Vec<ErrorBlock<T>>>, retry: Option<Vec<&'a str>>, end: Option<bool>) {
+        self.step_with(id, state, state_function, StepOptions { next, catch, retry, end: end.unwrap_or(false) });
+    }
+
+    /// Add a new node to the state machine, with its optional attributes gathered in
+    /// [`StepOptions`], e.g. `StepOptions { end: true, ..Default::default() }`
+    pub fn step_with<O: Into<StepOptions<'a, T>>>(&mut self, id: &str, state: State, state_function: StateFunction<T>, options: O) {
         // Check for duplicate node IDs
         if !self.node_ids.insert(id.to_string()) {
         panic!("Duplicate node ID found: {}", id);
         }
 
         // Create and add the new node
-        let new_node = StateNode::new(id, state, state_function, next, catch, retry, end);
+        let options = options.into();
+        let end = options.end.then_some(true);
+        let new_node = StateNode::new(id, state, state_function, options.next, options.catch, options.retry, end);
         self.nodes.push(new_node);
     }
 
+    /// Add a set of steps to the state machine
+    ///
+    /// The ids of all the steps are validated in a single pass before any of them is added,
+    /// so the state machine is left untouched when a duplicate id is found
+    pub fn steps<I>(&mut self, steps: I) -> Result<(), error::StateMachineError>
+    where
+        I: IntoIterator<Item = StepDefinition<'a, T>>,
+    {
+        let steps: Vec<StepDefinition<'a, T>> = steps.into_iter().collect();
+        let mut ids: HashSet<&str> = HashSet::with_capacity(steps.len());
+        let mut duplicates: Vec<&str> = Vec::new();
+        for step in &steps {
+            if self.node_ids.contains(&step.id) || !ids.insert(step.id.as_str()) {
+                duplicates.push(step.id.as_str());
+            }
+        }
+        if !duplicates.is_empty() {
+            return Err(error::StateMachineError {
+                message: format!("Duplicate node IDs found: {}", duplicates.join(", ")),
+            });
+        }
+
+        self.nodes.reserve(steps.len());
+        self.node_ids.reserve(steps.len());
+        for step in steps {
+            let new_node = StateNode::new(&step.id, step.state, step.state_function, step.next, step.catch, step.retry, step.end);
+            self.node_ids.insert(step.id);
+            self.nodes.push(new_node);
+        }
+        Ok(())
+    }
+
+    /// Add the steps defined by another module or crate under a prefix, their ids becoming
+    /// `<prefix>.<id>`, so that large workflows can be assembled from several modules without
+    /// id collisions.
+    ///
+    /// The steps are appended in their order and validated as with [`StateMachine::steps`].
+    /// The setters taking a step id, e.g. [`StateMachine::set_retry_blocks`], expect the
+    /// prefixed id
+    pub fn merge<I>(&mut self, prefix: &str, steps: I) -> Result<(), error::StateMachineError>
+    where
+        I: IntoIterator<Item = StepDefinition<'a, T>>,
+    {
+        self.steps(steps.into_iter().map(|step| StepDefinition { id: format!("{}.{}", prefix, step.id), ..step }))
+    }
+
     /// Validate the uniqueness of node IDs
     pub fn validate_node_ids(&self) {
         if self.nodes.len() != self.node_ids.len() {
@@ -176,6 +584,16 @@ impl<'a, T: data::DeserializeStateData> StateMachine<'a, T> {
         }
     }
 
+    /// The history of the last execution
+    pub fn history(&self) -> &history::ExecutionHistory {
+        &self.history
+    }
+
+    /// The data shared between the steps
+    pub fn data(&self) -> &T {
+        self.shared_data
+    }
+
     /// get node ids
     pub fn get_node_ids(&self) -> Vec<&str> {
         let v: Vec<&str> = self.node_ids.iter().map(|v| v.as_str()).collect();
@@ -186,8 +604,8 @@ impl<'a, T: data::DeserializeStateData> StateMachine<'a, T> {
     pub fn execute_by_id(&mut self, node_id: &str) -> Result<(), error::StateMachineError> {
         for node in &mut self.nodes {
             if node.id == node_id {
-                if let Err(err) = node.execute(self.shared_data) {
-                    println!("Error: {}", err);
+                let enabled = node.enabled(self.flags.as_ref());
+                if let Err(err) = node.execute(self.shared_data, self.history.routing_bucket, enabled) {
                     return Err(error::StateMachineError {
                         message: err.to_string(),
                     });
@@ -221,86 +639,353 @@ impl<'a, T: data::DeserializeStateData> StateMachine<'a, T> {
     }
 
     /// Execute the state machine and handle errors
-    pub fn execute(&mut self) -> Result<(), error::StateMachineError> {
-        for node in &mut self.nodes {
+    ///
+    /// The visited steps are recorded in the history, see [`StateMachine::history`]
+    pub fn execute(&mut self) -> Result<(), error::ExecutionError> {
+        self.execute_with(ExecutionOptions::default())
+    }
+
+    /// Execute the state machine with the given options, see [`StateMachine::execute`]
+    pub fn execute_with(&mut self, options: ExecutionOptions) -> Result<(), error::ExecutionError> {
+        // the state of the previous execution, its error would fail the steps of this one
+        self.history = history::ExecutionHistory::new(&self.id);
+        self.error_string = None;
+        self.history.tags = options.tags;
+        self.executions += 1;
+        let seed = options.seed.unwrap_or_else(|| hash(self.routing_seed, &self.executions.to_string()));
+        self.history.seed = seed;
+        self.history.routing_key = options.routing_key.clone();
+        let key = options.routing_key.unwrap_or_else(|| seed.to_string());
+        self.history.routing_bucket = routing_bucket(self.routing_seed, &key);
+        if let Some(experiment) = &self.experiment {
+            if let Some(variant) = experiment.variant(self.history.routing_bucket) {
+                self.history.tags.insert(experiment.name.clone(), variant.to_string());
+            }
+        }
+        let watchdog = self.stall.map(|config| watchdog::Watchdog::start(&self.id, config));
+        let stats = self.stats.clone();
+        let running = stats.as_ref().map(|stats| stats.execution(&self.id));
+        let result = self.run(watchdog.as_ref());
+        drop(running);
+        if let Some(watchdog) = watchdog {
+            self.history.stalls = watchdog.stop();
+        }
+        self.history.error = result.as_ref().err().map(|err| err.to_string());
+        if let Some(coverage) = &self.coverage {
+            coverage.lock().unwrap().record(&self.history);
+        }
+        self.apply_sampling();
+        result
+    }
+
+    fn run(&mut self, watchdog: Option<&watchdog::Watchdog>) -> Result<(), error::ExecutionError> {
+        let mut retries_used: u32 = 0;
+        let (snapshot, data_limit) = (self.snapshot, self.data_limit);
+        let bucket = self.history.routing_bucket;
+        let flags = self.flags.clone();
+        let (storm, in_flight, machine_id) = (self.storm.clone(), self.stats.clone(), self.id.clone());
+        // the alerts raised by the attempts of a step, moved to the history once the step is over
+        let alerts = RefCell::new(Vec::new());
+        let record = |node: &str, failed: bool| {
+            if let Some(alert) = storm.as_ref().and_then(|storm| storm.record(&machine_id, node, failed)) {
+                alerts.borrow_mut().push(alert);
+            }
+        };
+        // the time spent serializing the data is accounted in the event, see HistoryEvent::serialization
+        let take_snapshot = |data: &T, spent: &mut Duration| {
+            let started = Instant::now();
+            let json = snapshot.and_then(|serialize| isolation::isolate(|| serialize(data)).ok()?.ok());
+            *spent += started.elapsed();
+            json
+        };
+        // the positions of the transactions, the open one with its snapshot, and the last step
+        // of a rolled back one, see transaction::Transaction
+        let groups = transaction::ranges(&self.transactions, &self.nodes);
+        let mut open: Option<(usize, String)> = None;
+        let mut skip_until: Option<usize> = None;
+        'nodes: for (index, node) in self.nodes.iter_mut().enumerate() {
+            if skip_until.is_some_and(|last| index <= last) {
+                continue;
+            }
             // break if the last node/step
             if node.end.is_some() && node.end.unwrap() {
-                break
-            }
-            // check for invocations more than three times
-            if node.invocation_count == 2 {
-                let error = format!("state machine {} failed for step {}. Step have been invoked upto three times", self.id, node.id);
-                return Err(error::StateMachineError {
-                    message: error,
-                });
+                break 'nodes
             }
 
-            // if there is an error in the state and the current node is to catch some errors
-            if self.error_string.is_some() && !node.catch.is_some() {
-                return Err(error::StateMachineError {
-                    message: format!("{:?}", self.error_string),
-                });
+            if let Some(group) = groups.iter().position(|(first, _)| *first == index) {
+                let snapshot = self.transactions[group].snapshot;
+                match isolation::isolate(|| snapshot(self.shared_data)).unwrap_or_else(|panic| Err(Box::new(panic))) {
+                    Ok(snapshot) => open = Some((group, snapshot)),
+                    Err(err) => return Err(error::ExecutionError::NodeFailed { node: node.id.clone(), error: err.to_string() }),
+                }
             }
-            
-            if self.error_string.is_some() && node.catch.is_some() {
-                let catch = node.catch.as_ref().unwrap();
-                for val in catch.iter() {
-                    if  val.error_equals.contains(&self.error_string.as_ref().unwrap()) {
-                        match (val.next)(self.shared_data) {
-                            Ok(_) => (),
-                            Err(e) => {
-                                self.error_string = Some(e.to_string());
-                                return Err(error::StateMachineError {
-                                    message: format!("{:?}", self.error_string),
-                                });
-                            },
-                        };
+
+            // the uncaught failures of the step, which roll back its transaction
+            let outcome = 'step: {
+                // check for invocations more than three times
+                if node.invocation_count == 2 {
+                    let error = format!("state machine {} failed for step {}. Step have been invoked upto three times", self.id, node.id);
+                    break 'step Err(error::ExecutionError::Validation {
+                        message: error,
+                    });
+                }
+
+                // if there is an error in the state and the current node is to catch some errors
+                if let (Some(error_string), None) = (self.error_string.as_ref(), node.catch.as_ref()) {
+                    break 'step Err(error::ExecutionError::NodeFailed {
+                        node: node.id.clone(),
+                        error: error_string.clone(),
+                    });
+                }
+                
+                if let (Some(error_string), Some(catch)) = (self.error_string.as_ref(), node.catch.as_ref()) {
+                    for val in catch.iter() {
+                        if  error::matches_any(&val.error_equals, error_string) {
+                            match isolation::call(val.next, self.shared_data) {
+                                Ok(_) => (),
+                                Err(e) => {
+                                    let code = code_of(&self.codes, e.as_ref());
+                                    self.error_string = Some(code.clone());
+                                    break 'step Err(error::ExecutionError::CatchFailed {
+                                        node: node.id.clone(),
+                                        error: code,
+                                    });
+                                },
+                            };
+                        }
                     }
                 }
-            }
 
-            if node.next.is_some() {
-                match Some(node.next) {
-                    Some(v) => {
-                        let fffn = v.unwrap();
-                        match fffn(self.shared_data) {
-                            Ok(_) => (),
-                            Err(e) => {
-                                self.error_string = Some(e.to_string());
-                                return Err(error::StateMachineError {
-                                    message: format!("{:?}", self.error_string),
-                                })
-                            }
-                        };
-                    },
-                    None => (),
+                if let Some(watchdog) = watchdog {
+                    watchdog.enter(&node.id, matches!(node.state, State::Sleep(_)), retries_used);
                 }
-            }
+                // the step is in flight until the end of the iteration, or the return of the execution
+                let _in_flight = in_flight.as_ref().map(|stats| stats.step(&machine_id, &node.id));
+                let started = Instant::now();
+                let mut event = history::HistoryEvent::new(&node.id, history::EventOutcome::Succeeded);
+                event.data_before = take_snapshot(self.shared_data, &mut event.serialization);
 
+                // the next function is part of the step, it is not executed when the step is disabled
+                let enabled = node.enabled(flags.as_ref());
+                if let (Some(fffn), true) = (node.next, enabled) {
+                    match isolation::call(fffn, self.shared_data) {
+                        Ok(_) => (),
+                        Err(e) => {
+                            let code = code_of(&self.codes, e.as_ref());
+                            self.error_string = Some(code.clone());
+                            event.outcome = history::EventOutcome::Failed(code.clone());
+                            record_causes(&mut event, e.as_ref());
+                            event.data_after = take_snapshot(self.shared_data, &mut event.serialization);
+                            event.duration = started.elapsed();
+                            self.history.events.push(event);
+                            break 'step Err(error::ExecutionError::NodeFailed {
+                                node: node.id.clone(),
+                                error: code,
+                            })
+                        }
+                    };
+                }
 
-            if let Err(err) = node.execute(self.shared_data) {
-                // Propagate errors when they occur, and the current node becomes the exit
-                if node.retry.is_some() {
-                    // if  node.retry.as_ref().unwrap().contains(&self.error_string.as_ref().unwrap().as_str()) {
-                    if  node.retry.as_ref().unwrap().contains(&err.to_string().as_str()) {
-                        match backoff::exponential_backoff(|x| node.execute(x), self.shared_data, Some(self.retries)) {
-                            Ok(_) => println!("Operation completed successfully"),
-                            Err(_) => println!("Operation failed for step {} after multiple retries", node.id),
+                event.attempts = 1;
+                let mut pending_error = None;
+                let result = node.execute(self.shared_data, bucket, enabled);
+                if !matches!(result, Ok(false)) {
+                    record(&node.id, result.is_err());
+                }
+                match result {
+                    Ok(true) => (),
+                    Ok(false) => {
+                        event.attempts = 0;
+                        event.outcome = history::EventOutcome::Skipped;
+                    },
+                    Err(err) => {
+                        // Propagate errors when they occur, and the current node becomes the exit
+                        // unless one of its catch blocks matches the error
+                        let mut error_code = code_of(&self.codes, err.as_ref());
+                        record_causes(&mut event, err.as_ref());
+                        let classifiers = &self.classifiers;
+                        let explicit = match node.retry_blocks.iter().find(|block| error::matches_any(&block.error_equals, &error_code)) {
+                            Some(block) => Some((block.max_retries, block.backoff)),
+                            None if node.retry.as_ref().is_some_and(|retry| error::matches_any(retry, &error_code)) => {
+                                Some((self.retries.max(0) as u32, self.backoff))
+                            },
+                            None => None,
                         };
+                        let policy = explicit.or_else(|| match classify(classifiers, &*err) {
+                            Some((true, _)) => Some((self.retries.max(0) as u32, self.backoff)),
+                            _ => None,
+                        });
+                        let mut failed = true;
+                        if let Some((requested, config)) = policy {
+                            // the retries of the step are bounded by what is left of the retry budget
+                            let remaining = self.retry_budget.map(|budget| budget.saturating_sub(retries_used));
+                            let retries = remaining.map_or(requested, |remaining| requested.min(remaining));
+
+                            // the failure which triggered the retries counts as the first attempt
+                            let mut first_failure = Some(err);
+                            let operation = |x: &mut T| match first_failure.take() {
+                                Some(err) => Err(err),
+                                None => {
+                                    let result = node.execute(x, bucket, enabled).map(|_| ());
+                                    record(&node.id, result.is_err());
+                                    result
+                                },
+                            };
+                            // the errors registered with retry_errors_of may stop the retries, or delay the next attempt
+                            #[allow(clippy::borrowed_box)]
+                            let hint = |err: &Box<dyn Error>| {
+                                let (retryable, retry_after) = classify(classifiers, err.as_ref()).unwrap_or((true, None));
+                                // the retries of a step failing across the executions are paused, see storm::RetryStormGuard
+                                let paused = storm.as_ref().is_some_and(|storm| storm.is_paused(&machine_id, &node.id));
+                                ((retryable || explicit.is_some()) && !paused, retry_after)
+                            };
+                            match backoff::backoff_with_hints(operation, self.shared_data, Some(retries as i32), &config, hint) {
+                                Ok(report) => {
+                                    retries_used += report.attempts - 1;
+                                    event.attempts = report.attempts;
+                                    event.retry_delay = report.total_delay;
+                                    failed = false;
+                                },
+                                Err(failure) => {
+                                    retries_used += failure.attempts - 1;
+                                    event.attempts = failure.attempts;
+                                    event.retry_delay = failure.total_delay;
+                                    error_code = code_of(&self.codes, failure.error.as_ref());
+                                    record_causes(&mut event, failure.error.as_ref());
+                                    if remaining.is_some_and(|remaining| remaining < requested && failure.attempts - 1 == remaining) {
+                                        error_code = error::RETRY_BUDGET_EXHAUSTED.to_string();
+                                    }
+                                }
+                            };
+                        }
+
+                        if failed {
+                            event.outcome = history::EventOutcome::Failed(error_code.clone());
+                            pending_error = Some(match policy {
+                                Some(_) if error_code == error::RETRY_BUDGET_EXHAUSTED => error::ExecutionError::RetryBudgetExhausted {
+                                    node: node.id.clone(),
+                                },
+                                Some(_) if event.attempts > 1 => error::ExecutionError::RetryExhausted {
+                                    node: node.id.clone(),
+                                    error: error_code,
+                                    attempts: event.attempts,
+                                },
+                                _ => error::ExecutionError::NodeFailed {
+                                    node: node.id.clone(),
+                                    error: error_code,
+                                },
+                            });
+                        }
+                    },
+                }
+                if let (None, Some((limit, measure))) = (&pending_error, data_limit) {
+                    let serializing = Instant::now();
+                    let size = isolation::isolate(|| measure(self.shared_data)).ok().and_then(Result::ok).unwrap_or(0);
+                    event.serialization += serializing.elapsed();
+                    if size > limit {
+                        event.causes = vec![format!("the data is {} bytes, over the limit of {} bytes", size, limit)];
+                        event.outcome = history::EventOutcome::Failed(error::DATA_LIMIT_EXCEEDED.to_string());
+                        pending_error = Some(error::ExecutionError::DataLimitExceeded { node: node.id.clone() });
                     }
                 }
+                let mut violation = None;
+                if pending_error.is_none() {
+                    violation = check_invariants(&self.invariants, self.shared_data, &node.id);
+                    if violation.is_some() {
+                        event.outcome = history::EventOutcome::Failed(error::INVARIANT_VIOLATED.to_string());
+                    }
+                }
+                event.data_after = take_snapshot(self.shared_data, &mut event.serialization);
+                event.duration = started.elapsed();
+                if let State::Sleep(_) = node.state {
+                    event.wait = event.duration.saturating_sub(event.serialization);
+                }
+                self.history.events.push(event);
+                if let Some(violation) = violation {
+                    break 'step Err(violation);
+                }
 
-                return Err(error::StateMachineError {
-                    message: err.to_string(),
-                });
-            }
+                if let Some(failure) = pending_error {
+                    let error_code = failure.to_string();
+                    let catcher = node.catch.as_ref()
+                        .and_then(|catch| catch.iter().enumerate().find(|(_, block)| error::matches_any(&block.error_equals, &error_code)));
+                    match catcher {
+                        Some((index, block)) => {
+                            let started = Instant::now();
+                            let outcome = history::EventOutcome::Caught { block: index, error: error_code };
+                            let mut event = history::HistoryEvent::new(&node.id, outcome);
+                            event.attempts = 1;
+                            event.data_before = take_snapshot(self.shared_data, &mut event.serialization);
+                            let result = isolation::call(block.next, self.shared_data);
+                            event.data_after = take_snapshot(self.shared_data, &mut event.serialization);
+                            event.duration = started.elapsed();
+                            self.history.events.push(event);
+                            if let Err(e) = result {
+                                break 'step Err(error::ExecutionError::CatchFailed {
+                                    node: node.id.clone(),
+                                    error: code_of(&self.codes, e.as_ref()),
+                                });
+                            }
+                            if let Some(violation) = check_invariants(&self.invariants, self.shared_data, &format!("{}.Catch{}", node.id, index)) {
+                                break 'step Err(violation);
+                            }
+                        },
+                        None => break 'step Err(failure),
+                    }
+                }
 
-            // break if the last node/step
-            if node.end.is_some() && node.end.unwrap() {
-                break
+                // break if the last node/step
+                if node.end.is_some() && node.end.unwrap() {
+                    break 'nodes
+                }
+                Ok(())
+            };
+            self.history.storm_alerts.append(&mut alerts.borrow_mut());
+
+            let closing = open.as_ref().map(|(group, _)| *group).filter(|group| groups[*group].1 == index);
+            let outcome = match (outcome, closing) {
+                (Ok(()), Some(group)) => self.transactions[group].commit(self.snapshot, self.shared_data, &mut self.history),
+                (outcome, _) => outcome,
+            };
+            match (outcome, open.take()) {
+                (Ok(()), open_group) if closing.is_none() => open = open_group,
+                (Ok(()), _) => (),
+                // a violated invariant is neither retried nor caught, it is not rolled back either
+                (Err(failure), Some((group, snapshot))) if !matches!(failure, error::ExecutionError::InvariantViolated { .. }) => {
+                    self.error_string = None;
+                    self.transactions[group].roll_back(&snapshot, failure, self.snapshot, self.shared_data, &mut self.history)?;
+                    skip_until = Some(groups[group].1);
+                },
+                (Err(failure), _) => return Err(failure),
             }
         }
+        // the end step breaks out of the loop
+        self.history.storm_alerts.append(&mut alerts.borrow_mut());
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+impl<'a, T: data::DeserializeStateData + Serialize> StateMachine<'a, T> {
+    /// Record json snapshots of the shared data before and after every step in the history,
+    /// see [`history::ExecutionHistory::data_at`]. The snapshots are json whatever the codec the
+    /// history is encoded with, see [`data::DataCodec`]
+    pub fn enable_snapshots(&mut self) {
+        self.snapshot = Some(|data: &T| Ok(serde_json::to_string(data)?));
+    }
+
+    /// Set the maximum size, in bytes, of the shared data serialized as json.
+    ///
+    /// The size is checked after every step. A step leaving larger data fails with the
+    /// `States.DataLimitExceeded` error, which is not retried but can be caught, for instance
+    /// to offload a large value to a [`PayloadStore`](crate::machine::payload::PayloadStore)
+    pub fn set_max_data_size(&mut self, bytes: usize) {
+        self.set_max_data_size_with::<data::Json>(bytes);
+    }
+
+    /// Set the maximum size, in bytes, of the shared data encoded with the given codec,
+    /// see [`StateMachine::set_max_data_size`]
+    pub fn set_max_data_size_with<C: data::DataCodec>(&mut self, bytes: usize) {
+        self.data_limit = Some((bytes, |data: &T| Ok(C::encode(data)?.len())));
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
+use crate::machine::data;
+use crate::machine::state::StateMachine;
+
+
+/// The gauges of the executions at a point in time, see [`ExecutorStats::stats`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StatsSnapshot {
+    /// the inputs of the batch runs waiting for a worker
+    pub queued: usize,
+    /// the running executions
+    pub running: usize,
+    /// the running executions, by id of state machine
+    pub running_by_machine: BTreeMap<String, usize>,
+    /// the steps being executed, keyed by `<machine>/<step>`, the steps without any execution
+    /// in flight are absent
+    pub in_flight: BTreeMap<String, usize>,
+    /// the workers of the batch runs
+    pub workers: usize,
+    /// the workers of the batch runs executing an input
+    pub busy_workers: usize,
+}
+
+impl StatsSnapshot {
+    /// The share of the workers executing an input, from 0 to 1, zero without any worker
+    pub fn utilization(&self) -> f64 {
+        if self.workers == 0 {
+            return 0.0;
+        }
+        self.busy_workers as f64 / self.workers as f64
+    }
+}
+
+/// Live gauges of the executions of the machines and batch runs sharing it, for capacity
+/// planning.
+///
+/// A machine reports its executions and steps once instrumented with
+/// [`StateMachine::set_executor_stats`], a batch run its queue and workers once given in
+/// [`BatchOptions::stats`](crate::machine::batch::BatchOptions::stats), which instruments the
+/// machines it builds as well
+#[derive(Debug, Default)]
+pub struct ExecutorStats {
+    gauges: Mutex<StatsSnapshot>,
+}
+
+// Decrements a gauge when dropped, whatever the way the execution or step ends
+pub(crate) struct Gauge<'s> {
+    stats: &'s ExecutorStats,
+    update: fn(&mut StatsSnapshot, &str, isize),
+    key: String,
+}
+
+impl Drop for Gauge<'_> {
+    fn drop(&mut self) {
+        let mut gauges = self.stats.gauges.lock().unwrap();
+        (self.update)(&mut gauges, &self.key, -1);
+    }
+}
+
+fn adjust(counts: &mut BTreeMap<String, usize>, key: &str, delta: isize) {
+    let count = counts.entry(key.to_string()).or_default();
+    *count = count.saturating_add_signed(delta);
+    if *count == 0 {
+        counts.remove(key);
+    }
+}
+
+impl ExecutorStats {
+    /// Create gauges which can be shared between the machines and threads
+    pub fn shared() -> Arc<ExecutorStats> {
+        Arc::new(ExecutorStats::default())
+    }
+
+    /// The current value of the gauges
+    pub fn stats(&self) -> StatsSnapshot {
+        self.gauges.lock().unwrap().clone()
+    }
+
+    fn gauge(&self, key: String, update: fn(&mut StatsSnapshot, &str, isize)) -> Gauge<'_> {
+        update(&mut self.gauges.lock().unwrap(), &key, 1);
+        Gauge { stats: self, update, key }
+    }
+
+    /// Count a running execution until the returned gauge is dropped
+    pub(crate) fn execution(&self, machine_id: &str) -> Gauge<'_> {
+        self.gauge(machine_id.to_string(), |gauges, machine_id, delta| {
+            gauges.running = gauges.running.saturating_add_signed(delta);
+            adjust(&mut gauges.running_by_machine, machine_id, delta);
+        })
+    }
+
+    /// Count a step in flight until the returned gauge is dropped
+    pub(crate) fn step(&self, machine_id: &str, node: &str) -> Gauge<'_> {
+        self.gauge(format!("{}/{}", machine_id, node), |gauges, key, delta| adjust(&mut gauges.in_flight, key, delta))
+    }
+
+    /// Count a busy worker until the returned gauge is dropped
+    pub(crate) fn busy_worker(&self) -> Gauge<'_> {
+        self.gauge(String::new(), |gauges, _, delta| gauges.busy_workers = gauges.busy_workers.saturating_add_signed(delta))
+    }
+
+    pub(crate) fn update(&self, update: impl FnOnce(&mut StatsSnapshot)) {
+        update(&mut self.gauges.lock().unwrap());
+    }
+}
+
+impl<'a, T: data::DeserializeStateData> StateMachine<'a, T> {
+    /// Report the running executions of the machine and their steps in flight to shared gauges,
+    /// see [`ExecutorStats`]
+    pub fn set_executor_stats(&mut self, stats: Arc<ExecutorStats>) {
+        self.stats = Some(stats);
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use crate::machine::data;
+use crate::machine::state::StateMachine;
+
+
+/// The configuration of a [`RetryStormGuard`]
+#[derive(Debug, Clone, Copy)]
+pub struct StormConfig {
+    /// the window over which the failure rate of a step is measured
+    pub window: Duration,
+    /// the minimum number of attempts of a step within the window before its failure rate is checked
+    pub min_attempts: u32,
+    /// the failure rate, from 0 to 1, above which the retries of the step are paused
+    pub failure_rate: f64,
+    /// the time during which the retries of the step stay paused
+    pub cool_down: Duration,
+    /// hook receiving the alert raised when the retries of a step are paused
+    pub on_alert: Option<fn(&StormAlert)>,
+}
+
+impl Default for StormConfig {
+    fn default() -> Self {
+        StormConfig {
+            window: Duration::from_secs(60),
+            min_attempts: 20,
+            failure_rate: 0.5,
+            cool_down: Duration::from_secs(30),
+            on_alert: None,
+        }
+    }
+}
+
+/// The alert raised when the retries of a step are paused, see [`StormConfig::on_alert`]. It is
+/// also recorded in the history of the execution which crossed the threshold
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StormAlert {
+    /// the id of the state machine
+    pub machine_id: String,
+    /// the id of the step
+    pub node: String,
+    /// the attempts of the step within the window
+    pub attempts: u32,
+    /// the failure rate of the step within the window
+    pub failure_rate: f64,
+    /// the time during which the retries of the step are paused
+    pub cool_down: Duration,
+}
+
+// the failure rate is a ratio of attempts, never NaN
+impl Eq for StormAlert {}
+
+#[derive(Debug)]
+struct NodeWindow {
+    started: Instant,
+    attempts: u32,
+    failures: u32,
+    paused_until: Option<Instant>,
+}
+
+/// Aggregates the failures of the steps across the executions of the machines sharing it, and
+/// pauses the retries of a step failing too often for a cool-down window, preventing the
+/// executions from retrying in lockstep against a struggling dependency.
+///
+/// While the retries of a step are paused, its failures are not retried: they are caught or
+/// fail the execution right away. See [`StateMachine::set_retry_storm_guard`]
+#[derive(Debug)]
+pub struct RetryStormGuard {
+    config: StormConfig,
+    nodes: Mutex<HashMap<(String, String), NodeWindow>>,
+}
+
+impl RetryStormGuard {
+    /// Create a guard which can be shared between the machines and threads
+    pub fn shared(config: StormConfig) -> Arc<RetryStormGuard> {
+        Arc::new(RetryStormGuard { config, nodes: Mutex::new(HashMap::new()) })
+    }
+
+    /// Whether the retries of a step are paused
+    pub fn is_paused(&self, machine_id: &str, node: &str) -> bool {
+        let nodes = self.nodes.lock().unwrap();
+        nodes.get(&(machine_id.to_string(), node.to_string()))
+            .and_then(|window| window.paused_until)
+            .is_some_and(|until| Instant::now() < until)
+    }
+
+    /// Record the outcome of an attempt of a step, pausing its retries when its failure rate
+    /// crosses the threshold, in which case the alert is returned
+    pub(crate) fn record(&self, machine_id: &str, node: &str, failed: bool) -> Option<StormAlert> {
+        let now = Instant::now();
+        let mut nodes = self.nodes.lock().unwrap();
+        let window = nodes.entry((machine_id.to_string(), node.to_string()))
+            .or_insert(NodeWindow { started: now, attempts: 0, failures: 0, paused_until: None });
+        if now.duration_since(window.started) > self.config.window {
+            *window = NodeWindow { started: now, attempts: 0, failures: 0, paused_until: window.paused_until };
+        }
+        window.attempts += 1;
+        window.failures += u32::from(failed);
+
+        let rate = f64::from(window.failures) / f64::from(window.attempts);
+        let paused = window.paused_until.is_some_and(|until| now < until);
+        if paused || window.attempts < self.config.min_attempts || rate < self.config.failure_rate {
+            return None;
+        }
+        let alert = StormAlert {
+            machine_id: machine_id.to_string(),
+            node: node.to_string(),
+            attempts: window.attempts,
+            failure_rate: rate,
+            cool_down: self.config.cool_down,
+        };
+        // the next window starts after the cool-down
+        *window = NodeWindow { started: now, attempts: 0, failures: 0, paused_until: Some(now + self.config.cool_down) };
+        drop(nodes);
+        if let Some(on_alert) = self.config.on_alert {
+            on_alert(&alert);
+        }
+        Some(alert)
+    }
+}
+
+impl<'a, T: data::DeserializeStateData> StateMachine<'a, T> {
+    /// Record the attempts of the steps in a guard shared with other machines, which pauses the
+    /// retries of the steps failing too often, see [`RetryStormGuard`].
+    ///
+    /// The steps are identified by the id of the machine and their own id, the machines running
+    /// the same workflow should share both the guard and their id
+    pub fn set_retry_storm_guard(&mut self, guard: Arc<RetryStormGuard>) {
+        self.storm = Some(guard);
+    }
+}
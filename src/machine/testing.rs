@@ -0,0 +1,141 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use crate::machine::data;
+use crate::machine::error::ExecutionError;
+use crate::machine::history::ExecutionHistory;
+use crate::machine::state::StateMachine;
+
+
+// Define the function signature for the mocked handlers
+type StateFunction<T> = fn(&mut T) -> Result<(), Box<dyn Error>>;
+
+/// Executes a state machine, usually defined with stubbed functions, and asserts on the
+/// visited steps, the retries, the outcome and the final data.
+///
+/// Every assertion panics with a descriptive message when it does not hold, and returns the
+/// execution so assertions can be chained
+#[derive(Debug)]
+pub struct ExecutionAssert<'m, 'a, T: data::DeserializeStateData> {
+    machine: &'m StateMachine<'a, T>,
+    result: Result<(), ExecutionError>,
+}
+
+impl<'m, 'a, T: data::DeserializeStateData> ExecutionAssert<'m, 'a, T> {
+    /// Execute the state machine
+    pub fn run(machine: &'m mut StateMachine<'a, T>) -> Self {
+        let result = machine.execute();
+        ExecutionAssert { machine, result }
+    }
+
+    /// The history of the execution
+    pub fn history(&self) -> &ExecutionHistory {
+        self.machine.history()
+    }
+
+    /// The result of the execution
+    pub fn result(&self) -> &Result<(), ExecutionError> {
+        &self.result
+    }
+
+    /// Assert the visited steps and catch blocks, see [`ExecutionHistory::path`]
+    pub fn assert_path(&self, expected: &[&str]) -> &Self {
+        let path = self.history().path();
+        assert!(path == expected, "unexpected execution path\n  expected: {:?}\n    actual: {:?}", expected, path);
+        self
+    }
+
+    /// Assert the number of retries of a step
+    pub fn assert_retries(&self, node: &str, retries: u32) -> &Self {
+        let actual = self.history().retries(node);
+        assert!(actual == retries, "expected {} retries for step {}, got {}", retries, node, actual);
+        self
+    }
+
+    /// Assert the number of retries of all the steps
+    pub fn assert_total_retries(&self, retries: u32) -> &Self {
+        let actual = self.history().total_retries();
+        assert!(actual == retries, "expected {} retries in total, got {}", retries, actual);
+        self
+    }
+
+    /// Assert the execution succeeded
+    pub fn assert_succeeded(&self) -> &Self {
+        if let Err(err) = &self.result {
+            panic!("expected the execution to succeed, it failed with: {}", err);
+        }
+        self
+    }
+
+    /// Assert the execution failed with the given error
+    pub fn assert_failed_with(&self, error: &str) -> &Self {
+        match &self.result {
+            Ok(_) => panic!("expected the execution to fail with: {}, it succeeded", error),
+            Err(err) => assert!(err.to_string() == error, "expected the execution to fail with: {}, it failed with: {}", error, err),
+        }
+        self
+    }
+
+    /// Run assertions on the final data
+    pub fn assert_data<F: FnOnce(&T)>(&self, check: F) -> &Self {
+        check(self.machine.data());
+        self
+    }
+}
+
+/// Replaces the functions of steps by their id with canned ones, e.g. to test a definition
+/// imported with [`StateMachine::import_asl`] without its side effects.
+///
+/// A registry can be applied to several machines, the steps they do not have are ignored. The
+/// canned functions are plain functions like any handler: [`StateMachine::okay`] succeeds and
+/// [`StateMachine::error`] fails, and the outcomes depending on the attempt, such as failing
+/// twice before succeeding, keep their count in the shared data
+#[derive(Debug)]
+pub struct MockHandlerRegistry<T> {
+    handlers: BTreeMap<String, StateFunction<T>>,
+}
+
+impl<T> Default for MockHandlerRegistry<T> {
+    fn default() -> Self {
+        MockHandlerRegistry { handlers: BTreeMap::new() }
+    }
+}
+
+impl<T: data::DeserializeStateData> MockHandlerRegistry<T> {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        MockHandlerRegistry::default()
+    }
+
+    /// Replace the function of the step with the given id by `handler`
+    pub fn mock(mut self, node: &str, handler: StateFunction<T>) -> Self {
+        self.handlers.insert(node.to_string(), handler);
+        self
+    }
+
+    /// Swap the functions of the mocked steps of a machine, and return the ids of the swapped
+    /// steps in their order in the machine. The next functions, catch blocks and feature flag
+    /// fallbacks of the steps are kept
+    pub fn apply(&self, machine: &mut StateMachine<'_, T>) -> Vec<String> {
+        let mut swapped = Vec::new();
+        for node in &mut machine.nodes {
+            if let Some(handler) = self.handlers.get(&node.id) {
+                node.state_function = *handler;
+                swapped.push(node.id.clone());
+            }
+        }
+        swapped
+    }
+}
+
+/// Assert the path of an execution, see [`ExecutionAssert::assert_path`]
+///
+/// ```text
+/// let execution = ExecutionAssert::run(&mut state_machine);
+/// assert_path!(execution, ["NodeA", "NodeB", "NodeB.Catch0", "NodeD"]);
+/// ```
+#[macro_export]
+macro_rules! assert_path {
+    ($execution:expr, [$($node:expr),* $(,)?]) => {
+        $execution.assert_path(&[$($node),*])
+    };
+}
@@ -0,0 +1,144 @@
+use std::error::Error;
+use std::time::Instant;
+use serde::Serialize;
+use crate::machine::{data, isolation};
+use crate::machine::error::{ExecutionError, StateMachineError};
+use crate::machine::history::{EventOutcome, ExecutionHistory, HistoryEvent};
+use crate::machine::state::{StateMachine, StateNode};
+
+
+// Define the function signature for the handlers of the transactions
+type StateFunction<T> = fn(&mut T) -> Result<(), Box<dyn Error>>;
+// Define the function signature serializing the shared data into the snapshot of a transaction
+type SnapshotFunction<T> = fn(&T) -> Result<String, Box<dyn Error>>;
+
+/// A group of contiguous steps which either all succeed or leave the shared data as it was
+/// before the group, see [`StateMachine::transaction`]
+#[derive(Debug)]
+pub(crate) struct Transaction<T> {
+    pub(crate) name: String,
+    pub(crate) first: String,
+    pub(crate) last: String,
+    on_failure: StateFunction<T>,
+    pub(crate) on_commit: Option<StateFunction<T>>,
+    pub(crate) snapshot: SnapshotFunction<T>,
+}
+
+/// The positions of the first and last steps of the transactions, in their order
+pub(crate) fn ranges<T: data::DeserializeStateData>(transactions: &[Transaction<T>], nodes: &[StateNode<'_, T>]) -> Vec<(usize, usize)> {
+    let position = |id: &str| nodes.iter().position(|node| node.id == id).unwrap_or(usize::MAX);
+    transactions.iter().map(|transaction| (position(&transaction.first), position(&transaction.last))).collect()
+}
+
+// A snapshot of the data for the history, when snapshots are enabled, see StateMachine::enable_snapshots
+fn take_snapshot<T>(snapshot: Option<SnapshotFunction<T>>, data: &T, event: &mut HistoryEvent) -> Option<String> {
+    let started = Instant::now();
+    let json = snapshot.and_then(|serialize| isolation::isolate(|| serialize(data)).ok()?.ok());
+    event.serialization += started.elapsed();
+    json
+}
+
+// Run a handler of the transaction, recorded as an event of the transaction. The data before the
+// handler is recorded by the caller, a rollback restoring the data beforehand
+fn run_handler<T>(mut event: HistoryEvent, handler: StateFunction<T>, snapshot: Option<SnapshotFunction<T>>, data: &mut T, history: &mut ExecutionHistory) -> Result<(), Box<dyn Error>> {
+    let started = Instant::now();
+    event.attempts = 1;
+    let result = isolation::call(handler, data);
+    if let Err(err) = &result {
+        event.outcome = EventOutcome::Failed(err.to_string());
+    }
+    event.data_after = take_snapshot(snapshot, data, &mut event);
+    event.duration = started.elapsed();
+    history.events.push(event);
+    result
+}
+
+impl<T: data::DeserializeStateData> Transaction<T> {
+    // An event of the transaction, see HistoryEvent::transaction
+    fn event(&self, suffix: &str) -> HistoryEvent {
+        let mut event = HistoryEvent::new(&format!("{}.{}", self.name, suffix), EventOutcome::Succeeded);
+        event.transaction = Some(self.name.clone());
+        event
+    }
+
+    /// Run the commit handler once the last step of the group succeeded, recorded as the
+    /// `<name>.Commit` event. Its failure rolls the group back
+    pub(crate) fn commit(&self, snapshot: Option<SnapshotFunction<T>>, data: &mut T, history: &mut ExecutionHistory) -> Result<(), ExecutionError> {
+        let on_commit = match self.on_commit {
+            Some(on_commit) => on_commit,
+            None => return Ok(()),
+        };
+        let mut event = self.event("Commit");
+        event.data_before = take_snapshot(snapshot, data, &mut event);
+        run_handler(event, on_commit, snapshot, data, history)
+            .map_err(|err| ExecutionError::NodeFailed { node: self.name.clone(), error: err.to_string() })
+    }
+
+    /// Restore the shared data from the snapshot taken before the group and run the failure
+    /// handler, recorded as the `<name>.Rollback` event with the error of the group as cause.
+    /// Its snapshots hold the data left by the failing step and the restored data.
+    ///
+    /// When the snapshot cannot be read back, the data is left as the failing step left it, the
+    /// failure handler does not run and the execution fails
+    pub(crate) fn roll_back(&self, restore_from: &str, failure: ExecutionError, snapshot: Option<SnapshotFunction<T>>, data: &mut T, history: &mut ExecutionHistory) -> Result<(), ExecutionError> {
+        let mut event = self.event("Rollback");
+        event.data_before = take_snapshot(snapshot, data, &mut event);
+        let restored = match isolation::isolate(|| T::from_json(restore_from)).unwrap_or_else(|panic| Err(Box::new(panic))) {
+            Ok(restored) => restored,
+            Err(err) => {
+                let error = format!("Transaction {} cannot be rolled back: {}", self.name, err);
+                event.outcome = EventOutcome::Failed(error.clone());
+                event.causes = vec![failure.to_string(), err.to_string()];
+                event.data_after = event.data_before.clone();
+                history.events.push(event);
+                return Err(ExecutionError::NodeFailed { node: self.name.clone(), error });
+            },
+        };
+        *data = restored;
+        event.causes.push(failure.to_string());
+        run_handler(event, self.on_failure, snapshot, data, history)
+            .map_err(|err| ExecutionError::CatchFailed { node: self.name.clone(), error: err.to_string() })
+    }
+}
+
+impl<'a, T: data::DeserializeStateData + Serialize> StateMachine<'a, T> {
+    /// Run the steps from `first` to `last` as a transaction.
+    ///
+    /// The shared data is snapshotted before the first step. When a step of the group fails
+    /// with an error it does not catch, the data is restored from the snapshot, the `on_failure`
+    /// handler runs, and the execution resumes after the group. Once the last step succeeds the
+    /// `on_commit` handler runs, e.g. to dispatch the side effects queued in an outbox held by
+    /// the shared data, which are discarded with the data of a rolled back group.
+    ///
+    /// The groups cannot overlap nor reach the step marked as the end, and a failure of
+    /// `on_failure` fails the execution. A violated invariant is not rolled back, it fails the
+    /// execution, see [`StateMachine::add_invariant`]
+    pub fn transaction(&mut self, name: &str, first: &str, last: &str, on_failure: StateFunction<T>, on_commit: Option<StateFunction<T>>) -> Result<(), StateMachineError> {
+        let position = |id: &str| self.nodes.iter().position(|node| node.id == id)
+            .ok_or_else(|| StateMachineError { message: format!("Node ID not found: {}", id) });
+        let range = (position(first)?, position(last)?);
+        if range.0 > range.1 {
+            return Err(StateMachineError { message: format!("Transaction {}: step {} comes after step {}", name, first, last) });
+        }
+        // the execution stops at the end step, the group would never commit nor roll back
+        if let Some(end) = self.nodes[..=range.1].iter().find(|node| node.end == Some(true)) {
+            return Err(StateMachineError { message: format!("Transaction {} reaches the end step {}", name, end.id) });
+        }
+        let overlapping = ranges(&self.transactions, &self.nodes).iter()
+            .position(|(start, end)| range.0 <= *end && *start <= range.1);
+        if let Some(index) = overlapping {
+            return Err(StateMachineError {
+                message: format!("Transaction {} overlaps transaction {}", name, self.transactions[index].name),
+            });
+        }
+        self.transactions.push(Transaction {
+            name: name.to_string(),
+            first: first.to_string(),
+            last: last.to_string(),
+            on_failure,
+            on_commit,
+            snapshot: |data: &T| Ok(serde_json::to_string(data)?),
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+
+
+/// An execution which made no progress for longer than the stall window,
+/// see [`StateMachine::set_stall_timeout`](crate::machine::state::StateMachine::set_stall_timeout)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StallEvent {
+    /// the id of the state machine
+    pub machine_id: String,
+    /// the step the execution is stuck in
+    pub node: String,
+    /// the time elapsed since the step started
+    pub stalled_for: Duration,
+    /// the retries of the execution before the step started
+    pub retries: u32,
+}
+
+/// The configuration of the stall detection
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct StallConfig {
+    pub(crate) window: Duration,
+    pub(crate) on_stall: Option<fn(&StallEvent)>,
+}
+
+#[derive(Debug)]
+struct Progress {
+    node: Option<String>,
+    since: Instant,
+    retries: u32,
+    // the step declares a wait, e.g. a sleep, it is not expected to progress
+    waiting: bool,
+    reported: bool,
+    finished: bool,
+    stalls: Vec<StallEvent>,
+}
+
+/// A thread watching the progress of an execution, it reports a stall at most once per step
+#[derive(Debug)]
+pub(crate) struct Watchdog {
+    progress: Arc<(Mutex<Progress>, Condvar)>,
+    handle: JoinHandle<()>,
+}
+
+impl Watchdog {
+    pub(crate) fn start(machine_id: &str, config: StallConfig) -> Self {
+        let progress = Arc::new((Mutex::new(Progress {
+            node: None,
+            since: Instant::now(),
+            retries: 0,
+            waiting: false,
+            reported: false,
+            finished: false,
+            stalls: Vec::new(),
+        }), Condvar::new()));
+        let watched = Arc::clone(&progress);
+        let machine_id = machine_id.to_string();
+        let handle = thread::spawn(move || {
+            let (lock, condvar) = &*watched;
+            let mut progress = lock.lock().unwrap();
+            while !progress.finished {
+                // nothing to watch until the next step starts, or the execution ends
+                if progress.reported || progress.waiting {
+                    progress = condvar.wait(progress).unwrap();
+                    continue;
+                }
+                let elapsed = progress.since.elapsed();
+                if elapsed < config.window {
+                    progress = condvar.wait_timeout(progress, config.window - elapsed).unwrap().0;
+                    continue;
+                }
+                progress.reported = true;
+                if let Some(node) = progress.node.clone() {
+                    let event = StallEvent { machine_id: machine_id.clone(), node, stalled_for: elapsed, retries: progress.retries };
+                    progress.stalls.push(event.clone());
+                    // the hook runs without the lock, the execution keeps entering the next steps
+                    if let Some(on_stall) = config.on_stall {
+                        drop(progress);
+                        on_stall(&event);
+                        progress = lock.lock().unwrap();
+                    }
+                }
+            }
+        });
+        Watchdog { progress, handle }
+    }
+
+    /// Record the start of a step
+    pub(crate) fn enter(&self, node: &str, waiting: bool, retries: u32) {
+        let (lock, condvar) = &*self.progress;
+        let mut progress = lock.lock().unwrap();
+        progress.node = Some(node.to_string());
+        progress.since = Instant::now();
+        progress.retries = retries;
+        progress.waiting = waiting;
+        progress.reported = false;
+        condvar.notify_one();
+    }
+
+    /// Stop watching, returns the reported stalls
+    pub(crate) fn stop(self) -> Vec<StallEvent> {
+        {
+            let (lock, condvar) = &*self.progress;
+            lock.lock().unwrap().finished = true;
+            condvar.notify_one();
+        }
+        let _ = self.handle.join();
+        let (lock, _) = &*self.progress;
+        std::mem::take(&mut lock.lock().unwrap().stalls)
+    }
+}
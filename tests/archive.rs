@@ -0,0 +1,106 @@
+use std::error::Error;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State, ExecutionOptions}, data::DeserializeStateData, archive::ExecutionArchive, history::Snapshot};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  counter: i16,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn add(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.counter += 1;
+    Ok(())
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = SharedData { counter: 0 };
+    let mut state_machine = StateMachine::new("MachineArchive".to_string(), &mut shared_data, 3);
+    state_machine.enable_snapshots();
+    state_machine.step("NodeA", State::Task, add, None, None, None, None);
+    state_machine.step("NodeB", State::Task, add, None, None, None, None);
+    state_machine.execute_with(ExecutionOptions::default().tag("ticket", "SUP-42")).unwrap();
+
+    let path = std::env::temp_dir().join("sfn-machine-archive.json");
+    state_machine.export_execution().write_to(&path).unwrap();
+    let archive = ExecutionArchive::read_from(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    // load the execution in another machine with the same definition
+    let mut other_data = SharedData { counter: 0 };
+    let mut other = StateMachine::new("MachineArchive".to_string(), &mut other_data, 3);
+    other.step("NodeA", State::Task, add, None, None, None, None);
+    other.step("NodeB", State::Task, add, None, None, None, None);
+    other.import_execution(archive).unwrap();
+
+    assert_eq!(other.history(), state_machine.history());
+    assert_eq!(other.history().tag("ticket"), Some("SUP-42"));
+    let data: SharedData = other.history().data_at("NodeB", Snapshot::After).unwrap();
+    assert_eq!(data.counter, 2);
+}
+
+#[test]
+pub fn rejected() {
+    let mut shared_data = SharedData { counter: 0 };
+    let mut state_machine = StateMachine::new("MachineArchive".to_string(), &mut shared_data, 3);
+    state_machine.step("NodeA", State::Task, add, None, None, None, None);
+    state_machine.execute().unwrap();
+    let archive = state_machine.export_execution();
+
+    let mut other_data = SharedData { counter: 0 };
+    let mut other = StateMachine::new("MachineArchive".to_string(), &mut other_data, 3);
+    other.step("NodeA", State::Task, add, None, None, None, None);
+    other.step("NodeB", State::Task, add, None, None, None, None);
+    let err = other.import_execution(archive.clone()).unwrap_err();
+    assert!(err.message.ends_with("+ node NodeB\n+ transition NodeA -> NodeB\n"), "{}", err.message);
+
+    let json = archive.to_json().replacen("\"version\":1", "\"version\":99", 1);
+    let err = ExecutionArchive::from_json(&json).unwrap_err();
+    assert_eq!(err.message, "Unsupported execution archive version: 99");
+}
+
+fn reject(_: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    Err("Rejected <quota>".into())
+}
+
+fn never() -> bool {
+    false
+}
+
+#[test]
+pub fn html_report() {
+    let mut shared_data = SharedData { counter: 0 };
+    let mut state_machine = StateMachine::new("MachineArchive".to_string(), &mut shared_data, 3);
+    state_machine.enable_snapshots();
+    state_machine.step("NodeA", State::Task, add, None, None, None, None);
+    state_machine.step("NodeB", State::Choice(never), add, None, None, None, None);
+    state_machine.step("NodeC", State::Task, reject, None, None, None, None);
+    state_machine.step("NodeD", State::Task, add, None, None, None, None);
+    assert!(state_machine.execute_with(ExecutionOptions::default().tag("ticket", "SUP-42")).is_err());
+
+    let html = state_machine.export_execution().to_html();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<title>Execution of MachineArchive</title>"));
+    assert!(html.contains("<p class=\"status failed\">Failed: Rejected &lt;quota&gt;</p>"));
+    assert!(html.contains("<tr><th>ticket</th><td>SUP-42</td></tr>"));
+
+    // the taken path is highlighted, the steps which were not reached are not
+    assert!(html.contains("<li class=\"taken\" title=\"NodeA\">NodeA<span class=\"state\">Task</span></li>"));
+    assert!(html.contains("<li class=\"skipped\" title=\"NodeB\">NodeB<span class=\"state\">Choice</span></li>"));
+    assert!(html.contains("<li class=\"failed\" title=\"NodeC\">"));
+    assert!(html.contains("<li class=\"\" title=\"NodeD\">"));
+
+    assert!(html.contains("<td>NodeC</td><td>Failed: Rejected &lt;quota&gt;</td><td>1</td>"));
+    assert!(html.contains("<details><summary>details</summary>"));
+    assert!(html.contains("<pre>{\n  &quot;counter&quot;: 1\n}</pre>"));
+}
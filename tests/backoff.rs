@@ -0,0 +1,80 @@
+
+use std::time::Duration;
+use std::sync::Mutex;
+use sfn_machine::machine::backoff::
+    {exponential_backoff, exponential_backoff_with, BackoffConfig, BackoffEvent, BackoffWarning};
+
+fn always_fail(data: &mut i32) -> Result<(), String> {
+    *data += 1;
+    Err(format!("attempt {}", data))
+}
+
+#[test]
+pub fn main() {
+    let mut data = 0;
+
+    let failure = exponential_backoff(always_fail, &mut data, Some(1)).unwrap_err();
+    // the operation is never executed more than the configured number of times
+    assert_eq!(data, 2);
+    assert_eq!(failure.attempts, 2);
+    assert_eq!(failure.error, "attempt 2");
+    assert_eq!(failure.total_delay, Duration::from_secs(1));
+}
+
+#[test]
+pub fn no_retries() {
+    let mut data = 0;
+
+    let failure = exponential_backoff(always_fail, &mut data, Some(0)).unwrap_err();
+    assert_eq!(data, 1);
+    assert_eq!(failure.attempts, 1);
+    assert_eq!(failure.total_delay, Duration::ZERO);
+}
+
+#[test]
+pub fn success() {
+    let mut data = 0;
+
+    let report = exponential_backoff(|x: &mut i32| { *x += 1; Ok::<(), String>(()) }, &mut data, Some(3)).unwrap();
+    assert_eq!(data, 1);
+    assert_eq!(report.attempts, 1);
+    assert_eq!(report.total_delay, Duration::ZERO);
+}
+
+static EVENTS: Mutex<Vec<BackoffEvent>> = Mutex::new(Vec::new());
+
+fn record(event: &BackoffEvent) {
+    EVENTS.lock().unwrap().push(*event);
+}
+
+#[test]
+pub fn configured() {
+    let mut data = 0;
+    let config = BackoffConfig {
+        max_retries: Some(2),
+        initial_delay: Duration::from_millis(1),
+        multiplier: 3,
+        on_event: Some(record),
+    };
+
+    let failure = exponential_backoff_with(always_fail, &mut data, Some(10), &config).unwrap_err();
+    assert_eq!(failure.attempts, 3);
+    assert_eq!(failure.total_delay, Duration::from_millis(4));
+    let warning = BackoffWarning::RetriesClamped { requested: 10, max_retries: 2 };
+    assert_eq!(failure.warning, Some(warning));
+    assert_eq!(*EVENTS.lock().unwrap(), vec![
+        BackoffEvent::Warning(warning),
+        BackoffEvent::Retrying { attempt: 1, delay: Duration::from_millis(1) },
+        BackoffEvent::Retrying { attempt: 2, delay: Duration::from_millis(3) },
+    ]);
+}
+
+#[test]
+pub fn uncapped() {
+    let mut data = 0;
+    let config = BackoffConfig { max_retries: None, initial_delay: Duration::ZERO, ..Default::default() };
+
+    let failure = exponential_backoff_with(always_fail, &mut data, Some(20), &config).unwrap_err();
+    assert_eq!(failure.attempts, 21);
+    assert_eq!(failure.warning, None);
+}
@@ -0,0 +1,143 @@
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake};
+use std::thread::{self, Thread};
+use std::time::Duration;
+use sfn_machine::machine::backoff::
+    {exponential_backoff_async, BackoffConfig, BackoffError, CancellationToken, ThreadTimer, Timer};
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+// Minimal executor driving a future to completion on the current thread
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = Box::pin(future);
+    let waker = Arc::new(ThreadWaker(thread::current())).into();
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+// Timer completing immediately, recording the requested delays
+#[derive(Default)]
+struct RecordingTimer {
+    delays: Mutex<Vec<Duration>>,
+    cancel_after: Option<(usize, CancellationToken)>,
+}
+
+impl Timer for RecordingTimer {
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        let mut delays = self.delays.lock().unwrap();
+        delays.push(duration);
+        if let Some((count, token)) = &self.cancel_after {
+            if delays.len() == *count {
+                token.cancel();
+            }
+        }
+        Box::pin(std::future::ready(()))
+    }
+}
+
+fn fail_until(data: &mut i32) -> Result<(), String> {
+    *data -= 1;
+    if *data > 0 { Err(format!("remaining {}", data)) } else { Ok(()) }
+}
+
+#[test]
+pub fn main() {
+    let timer = RecordingTimer::default();
+    let token = CancellationToken::new();
+    let mut data = 3;
+
+    let report = block_on(exponential_backoff_async(fail_until, &mut data, Some(5), &BackoffConfig::default(), &timer, &token)).unwrap();
+    assert_eq!(report.attempts, 3);
+    assert_eq!(report.total_delay, Duration::from_secs(3));
+    assert_eq!(data, 0);
+    assert_eq!(*timer.delays.lock().unwrap(), vec![Duration::from_secs(1), Duration::from_secs(2)]);
+}
+
+#[test]
+pub fn exhausted() {
+    let timer = RecordingTimer::default();
+    let token = CancellationToken::new();
+    let mut data = 100;
+
+    match block_on(exponential_backoff_async(fail_until, &mut data, Some(2), &BackoffConfig::default(), &timer, &token)) {
+        Err(BackoffError::Failed(failure)) => {
+            assert_eq!(failure.error, "remaining 97");
+            assert_eq!(failure.attempts, 3);
+            assert_eq!(failure.total_delay, Duration::from_secs(3));
+        },
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert_eq!(timer.delays.lock().unwrap().len(), 2);
+}
+
+#[test]
+pub fn cancelled() {
+    let token = CancellationToken::new();
+    let timer = RecordingTimer { cancel_after: Some((2, token.clone())), ..Default::default() };
+    let mut data = 100;
+
+    let result = block_on(exponential_backoff_async(fail_until, &mut data, Some(5), &BackoffConfig::default(), &timer, &token));
+    assert!(matches!(result, Err(BackoffError::Cancelled)));
+    assert_eq!(data, 98);
+}
+
+#[test]
+pub fn cancelled_while_sleeping() {
+    let token = CancellationToken::new();
+    let canceller = token.clone();
+    let mut data = 100;
+
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        canceller.cancel();
+    });
+    // the thread timer would otherwise wait for a second before the next attempt
+    let result = block_on(exponential_backoff_async(fail_until, &mut data, Some(5), &BackoffConfig::default(), &ThreadTimer, &token));
+    assert!(matches!(result, Err(BackoffError::Cancelled)));
+    assert_eq!(data, 99);
+}
+
+#[test]
+pub fn thread_timer() {
+    let token = CancellationToken::new();
+    let config = BackoffConfig { initial_delay: Duration::from_millis(10), ..Default::default() };
+
+    // the sleeps of concurrent retries are waited by the same background thread
+    let retries: Vec<_> = (0..4).map(|_| thread::spawn({
+        let token = token.clone();
+        move || {
+            let mut data = 3;
+            block_on(exponential_backoff_async(fail_until, &mut data, Some(5), &config, &ThreadTimer, &token)).unwrap()
+        }
+    })).collect();
+    for retry in retries {
+        let report = retry.join().unwrap();
+        assert_eq!((report.attempts, report.total_delay), (3, Duration::from_millis(30)));
+    }
+}
+
+#[test]
+pub fn saturating_delays() {
+    let timer = RecordingTimer::default();
+    let token = CancellationToken::new();
+    let config = BackoffConfig { initial_delay: Duration::from_secs(60), multiplier: u32::MAX, ..Default::default() };
+    let mut data = 5;
+
+    let report = block_on(exponential_backoff_async(fail_until, &mut data, Some(5), &config, &timer, &token)).unwrap();
+    assert_eq!((report.attempts, report.total_delay), (5, Duration::MAX));
+    assert_eq!(timer.delays.lock().unwrap()[3], Duration::MAX);
+}
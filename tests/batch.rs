@@ -0,0 +1,121 @@
+use std::error::Error;
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State}, data::DeserializeStateData, error::StateMachineError, batch::{run_batch, BatchOptions, BatchProgress, BatchStatus}, backoff::CancellationToken};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  counter: i16,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn double(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    if data.counter < 0 {
+        return Err(Box::new(StateMachineError { message: String::from("Negative") }));
+    }
+    data.counter *= 2;
+    Ok(())
+}
+
+fn machine(data: &mut SharedData) -> StateMachine<'_, SharedData> {
+    let mut state_machine = StateMachine::new("MachineBatch".to_string(), data, 3);
+    state_machine.step("Double", State::Task, double, None, None, None, None);
+    state_machine
+}
+
+#[test]
+pub fn main() {
+    let inputs: Vec<SharedData> = [1, 2, -3, 4, 5, 6].iter().map(|counter| SharedData { counter: *counter }).collect();
+    let report = run_batch(inputs, &BatchOptions { concurrency: 3, ..Default::default() }, machine).unwrap();
+
+    assert_eq!((report.succeeded(), report.failed(), report.skipped()), (5, 1, 0));
+    let counters: Vec<i16> = report.items.iter().map(|item| item.data.counter).collect();
+    assert_eq!(counters, vec![2, 4, -3, 8, 10, 12]);
+    assert_eq!(report.items[2].status, BatchStatus::Failed(String::from("Negative")));
+    assert_eq!(report.items[0].history.as_ref().unwrap().path(), vec!["Double"]);
+}
+
+#[test]
+pub fn stop_on_failure() {
+    let inputs: Vec<SharedData> = [1, -2, 3, 4].iter().map(|counter| SharedData { counter: *counter }).collect();
+    let report = run_batch(inputs, &BatchOptions { stop_on_failure: true, ..Default::default() }, machine).unwrap();
+
+    assert_eq!((report.succeeded(), report.failed(), report.skipped()), (1, 1, 2));
+    assert_eq!(report.items[2].status, BatchStatus::Skipped);
+    assert_eq!(report.items[2].data.counter, 3);
+    assert!(report.items[2].history.is_none());
+}
+
+static PROGRESS: Mutex<Vec<(usize, usize)>> = Mutex::new(Vec::new());
+
+fn record_progress(progress: &BatchProgress) {
+    PROGRESS.lock().unwrap().push((progress.completed, progress.total));
+}
+
+#[test]
+pub fn resume() {
+    let path = std::env::temp_dir().join("sfn-machine-batch-progress.txt");
+    let _ = std::fs::remove_file(&path);
+    let inputs = || -> Vec<SharedData> { [1, -2, 3, 4].iter().map(|counter| SharedData { counter: *counter }).collect() };
+    let options = BatchOptions {
+        stop_on_failure: true,
+        progress_file: Some(path.clone()),
+        on_progress: Some(record_progress),
+        ..Default::default()
+    };
+
+    let report = run_batch(inputs(), &options, machine).unwrap();
+    assert_eq!((report.succeeded(), report.failed(), report.skipped()), (1, 1, 2));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "0\n");
+    assert_eq!(*PROGRESS.lock().unwrap(), vec![(1, 4), (2, 4)]);
+
+    // the completed input is not executed again, the failed one is
+    let options = BatchOptions { stop_on_failure: false, ..options };
+    let report = run_batch(inputs(), &options, machine).unwrap();
+    assert_eq!((report.resumed(), report.succeeded(), report.failed()), (1, 2, 1));
+    assert_eq!(report.items[0].status, BatchStatus::Completed);
+    assert_eq!(report.items[0].data.counter, 1);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "0\n2\n3\n");
+    assert_eq!(PROGRESS.lock().unwrap()[2..], [(2, 4), (3, 4), (4, 4)]);
+    std::fs::remove_file(&path).unwrap();
+}
+
+static STALE: Mutex<Vec<(usize, usize)>> = Mutex::new(Vec::new());
+
+fn record_stale(progress: &BatchProgress) {
+    STALE.lock().unwrap().push((progress.completed, progress.total));
+}
+
+#[test]
+pub fn stale_progress() {
+    // the progress file of a run over more inputs
+    let path = std::env::temp_dir().join("sfn-machine-batch-stale-progress.txt");
+    std::fs::write(&path, "0\n5\n9\n").unwrap();
+    let inputs: Vec<SharedData> = [1, 2].iter().map(|counter| SharedData { counter: *counter }).collect();
+    let options = BatchOptions { progress_file: Some(path.clone()), on_progress: Some(record_stale), ..Default::default() };
+
+    let report = run_batch(inputs, &options, machine).unwrap();
+    assert_eq!((report.resumed(), report.succeeded()), (1, 1));
+    assert!(report.progress_error.is_none());
+    assert_eq!(*STALE.lock().unwrap(), vec![(2, 2)]);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+pub fn cancelled() {
+    let cancellation = CancellationToken::new();
+    cancellation.cancel();
+    let inputs: Vec<SharedData> = [1, 2].iter().map(|counter| SharedData { counter: *counter }).collect();
+    let options = BatchOptions { cancellation: Some(cancellation), ..Default::default() };
+    let report = run_batch(inputs, &options, machine).unwrap();
+    assert_eq!(report.skipped(), 2);
+}
@@ -0,0 +1,33 @@
+use std::time::Duration;
+use sfn_machine::bench::{self, BenchData, BenchResult};
+
+fn result(name: &str, mean: u64) -> BenchResult {
+    let mean = Duration::from_millis(mean);
+    BenchResult { name: name.to_string(), iterations: 1, mean, min: mean, max: mean }
+}
+
+#[test]
+pub fn main() {
+    let ids: Vec<String> = (0..10).map(|i| format!("Node{}", i)).collect();
+    let mut data = BenchData::default();
+    bench::linear(&ids, &mut data).execute().unwrap();
+    assert_eq!(data.counter, 10);
+
+    let mut data = BenchData { failures_left: 50, ..Default::default() };
+    bench::deep_retries(&mut data).execute().unwrap();
+    assert_eq!((data.counter, data.failures_left), (1, 0));
+
+    let measured = bench::measure("noop", 3, || {});
+    assert_eq!(measured.iterations, 3);
+    assert!(measured.min <= measured.mean && measured.mean <= measured.max);
+}
+
+#[test]
+pub fn regressions() {
+    let baseline = vec![result("a", 100), result("b", 100)];
+    let current = vec![result("a", 105), result("b", 150), result("c", 500)];
+    let regressions = bench::regressions(&baseline, &current, 10.0);
+    assert_eq!(regressions.len(), 1);
+    assert_eq!(regressions[0].name, "b");
+    assert!((regressions[0].slowdown - 50.0).abs() < 1e-9);
+}
@@ -0,0 +1,106 @@
+
+use std::error::Error;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State, StepDefinition, StepOptions, ErrorBlock}, data::DeserializeStateData};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  counter: i16,
+  id: String,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn increment(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.counter += 1;
+    Ok(())
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = SharedData { counter: 0, id: "some-id".to_string() };
+    let mut state_machine = StateMachine::new("MachineBulk".to_string(), &mut shared_data, 3);
+
+    state_machine.step("Node0", State::Task, increment, None, None, None, None);
+    let steps = (1..100).map(|i| StepDefinition::new(&format!("Node{}", i), State::Task, increment));
+    state_machine.steps(steps).expect("Failed to add steps");
+
+    assert_eq!(state_machine.get_node_ids().len(), 100);
+    state_machine.validate_node_ids();
+
+    if let Err(err) = state_machine.execute() {
+      panic!("State machine execution failed: {}", err);
+    }
+    assert_eq!(shared_data.counter, 100);
+}
+
+#[test]
+pub fn duplicates() {
+    let mut shared_data = SharedData { counter: 0, id: "some-id".to_string() };
+    let mut state_machine = StateMachine::new("MachineBulk".to_string(), &mut shared_data, 3);
+
+    state_machine.step("NodeA", State::Task, increment, None, None, None, None);
+    let steps = vec![
+        StepDefinition::new("NodeB", State::Task, increment),
+        StepDefinition::new("NodeA", State::Task, increment),
+        StepDefinition::new("NodeB", State::Task, increment),
+    ];
+
+    let err = state_machine.steps(steps).unwrap_err();
+    assert_eq!(err.to_string(), "Duplicate node IDs found: NodeA, NodeB");
+    // nothing is added when the validation fails
+    assert_eq!(state_machine.get_node_ids(), vec!["NodeA"]);
+}
+
+// Steps defined by another module, unaware of the machine they are merged into
+mod billing {
+    use sfn_machine::machine::state::{State, StepDefinition};
+    use super::{increment, SharedData};
+
+    pub fn steps<'a>() -> Vec<StepDefinition<'a, SharedData>> {
+        vec![
+            StepDefinition::new("Charge", State::Task, increment),
+            StepDefinition::new("Receipt", State::Task, increment),
+        ]
+    }
+}
+
+#[test]
+pub fn merge() {
+    let mut shared_data = SharedData { counter: 0, id: "some-id".to_string() };
+    let mut state_machine = StateMachine::new("MachineBulk".to_string(), &mut shared_data, 3);
+
+    state_machine.step("Receipt", State::Task, increment, None, None, None, None);
+    state_machine.merge("Billing", billing::steps()).unwrap();
+    state_machine.merge("Refund", billing::steps()).unwrap();
+    let err = state_machine.merge("Billing", billing::steps()).unwrap_err();
+    assert_eq!(err.to_string(), "Duplicate node IDs found: Billing.Charge, Billing.Receipt");
+
+    state_machine.execute().unwrap();
+    assert_eq!(state_machine.history().path(),
+        vec!["Receipt", "Billing.Charge", "Billing.Receipt", "Refund.Charge", "Refund.Receipt"]);
+}
+
+#[test]
+pub fn step_options() {
+    let mut shared_data = SharedData { counter: 0, id: "some-id".to_string() };
+    let mut state_machine = StateMachine::new("MachineBulk".to_string(), &mut shared_data, 3);
+
+    let catch = vec![ErrorBlock { error_equals: vec![String::from("STATE.FAILED")], next: increment }];
+    state_machine.step_with("Fail", State::Task, StateMachine::error, catch);
+    state_machine.step_with("Count", State::Task, increment, StepOptions::default());
+    state_machine.step_with("Stop", State::Task, increment, StepOptions { end: true, ..Default::default() });
+    state_machine.step_with("After", State::Task, increment, StepOptions::default());
+
+    state_machine.execute().unwrap();
+    assert_eq!(state_machine.history().path(), vec!["Fail", "Fail.Catch0", "Count"]);
+    assert_eq!(state_machine.data().counter, 2);
+}
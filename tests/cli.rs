@@ -0,0 +1,77 @@
+use std::error::Error;
+use std::path::PathBuf;
+use std::process::Command;
+use std::{env, fs};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sfn_machine::machine::
+    {state::{StateMachine, State}, data::DeserializeStateData, lint};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  counter: i16,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn increment(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.counter += 1;
+    Ok(())
+}
+
+fn write_definition(name: &str, contents: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("sfn-cli-{}-{}.json", name, std::process::id()));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+fn validate(path: &PathBuf, options: &[&str]) -> (Option<i32>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_sfn")).arg("validate").arg(path).args(options).output().unwrap();
+    (output.status.code(), String::from_utf8(output.stdout).unwrap())
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = SharedData { counter: 0 };
+    let mut machine = StateMachine::new("MachineCli".to_string(), &mut shared_data, 3);
+    machine.step("NodeA", State::Task, increment, None, None, Some(vec!["Timeout"]), None);
+    machine.step("NodeB", State::Sleep(600), StateMachine::okay, None, None, None, None);
+    machine.step("NodeC", State::Task, increment, None, None, Some(vec!["Timeout"]), None);
+    let path = write_definition("main", &serde_json::to_string(&machine.definition()).unwrap());
+
+    // the sleep is within the default maximum of a year, it is an error with a lower maximum
+    let (code, _) = validate(&path, &[]);
+    assert_eq!(code, Some(0));
+
+    let (code, stdout) = validate(&path, &["--format", "json", "--max-sleep-secs", "60"]);
+    assert_eq!(code, Some(1));
+    let output: Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(output["valid"], false);
+    assert_eq!(output["diagnostics"][0]["rule"], lint::SLEEP_TOO_LONG);
+    assert_eq!(output["diagnostics"][0]["severity"], "Error");
+    assert_eq!(output["diagnostics"][0]["node"], "NodeB");
+    assert_eq!(output["diagnostics"][0]["pointer"], "/nodes/1/state");
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+pub fn invalid_definition() {
+    let path = write_definition("invalid", "{\n  \"id\": \"MachineCli\",\n  \"nodes\": [\n}");
+    let (code, stdout) = validate(&path, &[]);
+    assert_eq!(code, Some(1));
+    assert!(stdout.contains(":4:1: Error [invalid-definition]"), "{}", stdout);
+
+    let (code, stdout) = validate(&path, &["--format", "json"]);
+    assert_eq!(code, Some(1));
+    let output: Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(output["diagnostics"][0]["span"]["line"], 4);
+    assert_eq!(output["diagnostics"][0]["span"]["column"], 1);
+    fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,88 @@
+use std::error::Error;
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use sfn_machine::machine::
+    {state::{StateMachine, State}, data::{DeserializeStateData, DataCodec, Json}, archive::ExecutionArchive, history::{ExecutionHistory, Snapshot}, error};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  name: String,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+// A codec writing json as hex, twice as large as json
+struct Hex;
+
+impl DataCodec for Hex {
+    const NAME: &'static str = "hex";
+
+    fn encode<V: Serialize>(value: &V) -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(serde_json::to_vec(value)?.iter().flat_map(|byte| format!("{:02x}", byte).into_bytes()).collect())
+    }
+
+    fn decode<V: DeserializeOwned>(bytes: &[u8]) -> Result<V, Box<dyn Error>> {
+        let json = bytes.chunks(2)
+            .map(|pair| u8::from_str_radix(std::str::from_utf8(pair)?, 16).map_err(|err| err.into()))
+            .collect::<Result<Vec<u8>, Box<dyn Error>>>()?;
+        Ok(serde_json::from_slice(&json)?)
+    }
+}
+
+fn rename(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.name = "x".repeat(40);
+    Ok(())
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = SharedData { name: String::new() };
+    let mut state_machine = StateMachine::new("MachineCodec".to_string(), &mut shared_data, 3);
+    state_machine.step("Rename", State::Task, rename, None, None, None, None);
+    state_machine.execute().unwrap();
+
+    let archive = state_machine.export_execution();
+    let bytes = archive.encode::<Hex>().unwrap();
+    assert_eq!(bytes.len(), 2 * archive.encode::<Json>().unwrap().len());
+    assert_eq!(ExecutionArchive::decode::<Hex>(&bytes).unwrap(), archive);
+    assert!(ExecutionArchive::decode::<Json>(&bytes).is_err());
+}
+
+#[test]
+pub fn data_limit() {
+    // {"name":"xxx..."} is 51 bytes as json, 102 as hex
+    let mut shared_data = SharedData { name: String::new() };
+    let mut state_machine = StateMachine::new("MachineCodec".to_string(), &mut shared_data, 3);
+    state_machine.set_max_data_size(64);
+    state_machine.step("Rename", State::Task, rename, None, None, None, None);
+    state_machine.execute().unwrap();
+
+    state_machine.set_max_data_size_with::<Hex>(64);
+    let err = state_machine.execute().unwrap_err();
+    assert_eq!(err.to_string(), error::DATA_LIMIT_EXCEEDED);
+    assert!(matches!(err, error::ExecutionError::DataLimitExceeded { .. }));
+}
+
+#[test]
+pub fn history() {
+    let mut shared_data = SharedData { name: String::new() };
+    let mut state_machine = StateMachine::new("MachineCodec".to_string(), &mut shared_data, 3);
+    state_machine.enable_snapshots();
+    state_machine.step("Rename", State::Task, rename, None, None, None, None);
+    state_machine.execute().unwrap();
+
+    // the history travels in the format of the codec, its snapshots stay json
+    let history = state_machine.history();
+    let bytes = history.encode::<Hex>().unwrap();
+    let decoded = ExecutionHistory::decode::<Hex>(&bytes).unwrap();
+    assert_eq!(&decoded, history);
+    assert_eq!(decoded.data_at::<SharedData>("Rename", Snapshot::After).unwrap().name, "x".repeat(40));
+    assert!(ExecutionHistory::decode::<Json>(&bytes).is_err());
+}
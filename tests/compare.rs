@@ -0,0 +1,70 @@
+use std::error::Error;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State, ErrorBlock}, data::DeserializeStateData, history::EventOutcome};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  amount: i16,
+  approved: bool,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn fees(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.amount += 10;
+    Ok(())
+}
+
+fn approve(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    if data.amount > 100 {
+        return Err("Approval.Rejected".into());
+    }
+    data.approved = true;
+    Ok(())
+}
+
+fn escalate(_data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    Err("Escalation.Unavailable".into())
+}
+
+fn run(amount: i16) -> sfn_machine::machine::history::ExecutionHistory {
+    let mut shared_data = SharedData { amount, approved: false };
+    let mut state_machine = StateMachine::new("MachineCompare".to_string(), &mut shared_data, 1);
+    state_machine.enable_snapshots();
+    let catch = vec![ErrorBlock { error_equals: vec![String::from("Approval.Rejected")], next: escalate }];
+    state_machine.step("Fees", State::Task, fees, None, None, None, None);
+    state_machine.step("Approve", State::Task, approve, None, Some(catch), None, None);
+    state_machine.step("Notify", State::Task, StateMachine::okay, None, None, None, None);
+    let _ = state_machine.execute();
+    state_machine.history().clone()
+}
+
+#[test]
+pub fn main() {
+    let (a, b) = (run(20), run(95));
+    assert!(a.compare(&run(20)).is_identical());
+
+    let diff = a.compare(&b);
+    assert!(!diff.is_identical());
+    assert_eq!(diff.divergence, Some(1));
+    assert_eq!(diff.left_error, None);
+    assert!(diff.right_error.is_some());
+
+    let labels: Vec<&str> = diff.steps.iter().map(|step| step.label.as_str()).collect();
+    assert_eq!(labels, vec!["Fees", "Approve", "Notify", "Approve.Catch0"]);
+    assert_eq!(diff.steps[0].differences[0].path, "$.amount");
+    assert_eq!(diff.steps[0].differences[0].recorded.as_deref(), Some("30"));
+    assert_eq!(diff.steps[0].differences[0].live.as_deref(), Some("105"));
+    assert_eq!(diff.steps[1].right, Some(EventOutcome::Failed(String::from("Approval.Rejected"))));
+    assert_eq!(diff.steps[2].right, None);
+    assert_eq!(diff.steps[3].left, None);
+    assert!(diff.to_string().contains("executions diverge at event 1"));
+}
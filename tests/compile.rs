@@ -0,0 +1,78 @@
+use std::error::Error;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State}, data::DeserializeStateData, error::StateMachineError};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  counter: i16,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn add(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.counter += 1;
+    Ok(())
+}
+
+fn double(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.counter *= 2;
+    Ok(())
+}
+
+fn fail(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    if data.counter > 5 {
+        return Err(Box::new(StateMachineError { message: String::from("TooLarge") }));
+    }
+    Ok(())
+}
+
+fn always() -> bool {
+    true
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = SharedData { counter: 0 };
+    let mut state_machine = StateMachine::new("MachineCompile".to_string(), &mut shared_data, 3);
+    state_machine.step("NodeA", State::Task, add, None, None, None, None);
+    state_machine.step("NodeB", State::Pass, add, Some(double), None, None, None);
+    state_machine.step("NodeC", State::Task, add, Some(double), None, None, None);
+    state_machine.step("NodeD", State::Task, fail, None, None, None, None);
+    state_machine.step("NodeE", State::Task, add, None, None, None, Some(true));
+    state_machine.execute().unwrap();
+    let executed = state_machine.data().counter;
+
+    let compiled = state_machine.compile().unwrap();
+    assert_eq!(compiled.id(), "MachineCompile");
+    assert_eq!(compiled.len(), 5);
+    let mut data = SharedData { counter: 0 };
+    compiled.run(&mut data).unwrap();
+    assert_eq!(data.counter, executed);
+
+    let mut data = SharedData { counter: 1 };
+    assert_eq!(compiled.run(&mut data).unwrap_err().to_string(), "TooLarge");
+}
+
+#[test]
+pub fn not_linear() {
+    let mut shared_data = SharedData { counter: 0 };
+    let mut state_machine = StateMachine::new("MachineCompile".to_string(), &mut shared_data, 3);
+    state_machine.step("NodeA", State::Task, add, None, None, Some(vec!["TooLarge"]), None);
+    let err = state_machine.compile().unwrap_err();
+    assert_eq!(err.message, "Step NodeA cannot be compiled: it retries errors");
+
+    let mut shared_data = SharedData { counter: 0 };
+    let mut state_machine = StateMachine::new("MachineCompile".to_string(), &mut shared_data, 3);
+    state_machine.step("NodeA", State::Task, add, None, None, None, None);
+    state_machine.step("NodeB", State::Choice(always), add, None, None, None, None);
+    let err = state_machine.compile().unwrap_err();
+    assert_eq!(err.message, "Step NodeB cannot be compiled: only task and pass steps are linear");
+}
@@ -0,0 +1,76 @@
+
+use std::error::Error;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State, ErrorBlock}, data::DeserializeStateData, coverage::Coverage, error::StateMachineError};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  fail: bool,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn maybe_fail(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    if data.fail {
+        return Err(Box::new(StateMachineError { message: String::from("Failed") }));
+    }
+    Ok(())
+}
+
+fn cond() -> bool {
+    true
+}
+
+fn define<'a>(machine: &mut StateMachine<'a, SharedData>) {
+    let catch = vec![
+        ErrorBlock { error_equals: vec![String::from("Failed")], next: StateMachine::okay },
+        ErrorBlock { error_equals: vec![String::from("Other")], next: StateMachine::okay },
+    ];
+    machine.step("NodeA", State::Task, maybe_fail, None, Some(catch), None, None);
+    machine.step("NodeB", State::Choice(cond), StateMachine::okay, None, None, None, None);
+    machine.step("NodeC", State::Pass, StateMachine::okay, None, None, None, Some(true));
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = SharedData { fail: false };
+    let mut machine = StateMachine::new("MachineCoverage".to_string(), &mut shared_data, 3);
+    define(&mut machine);
+    let coverage = Coverage::shared(machine.definition());
+    machine.instrument(coverage.clone());
+    machine.execute().unwrap();
+
+    // another test of the suite exercising the catch block
+    let mut shared_data = SharedData { fail: true };
+    let mut machine = StateMachine::new("MachineCoverage".to_string(), &mut shared_data, 3);
+    define(&mut machine);
+    machine.instrument(coverage.clone());
+    machine.execute().unwrap();
+
+    let report = coverage.lock().unwrap().report();
+    assert_eq!(report.nodes[0].hits, 2);
+    assert_eq!(report.uncovered(), vec!["NodeC", "NodeB -> NodeC", "NodeA.Catch1", "NodeB:skipped"]);
+    assert_eq!(report.to_string(), "\
+coverage of MachineCoverage: 55.6%
+  nodes            2/3
+  transitions      1/2
+  catch blocks     1/2
+  choice branches  1/2
+uncovered:
+  NodeC
+  NodeB -> NodeC
+  NodeA.Catch1
+  NodeB:skipped
+");
+    let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+    assert_eq!(json["catch_blocks"][0]["name"], "NodeA.Catch0");
+    assert_eq!(json["catch_blocks"][0]["hits"], 1);
+}
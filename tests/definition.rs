@@ -0,0 +1,66 @@
+
+use std::error::Error;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State, ErrorBlock}, data::DeserializeStateData, definition::{MachineDefinition, StateKind}};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  counter: i16,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn increment(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.counter += 1;
+    Ok(())
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = SharedData { counter: 0 };
+    let mut old = StateMachine::new("MachineDiff".to_string(), &mut shared_data, 3);
+    old.step("NodeA", State::Task, increment, None, None, None, None);
+    old.step("NodeB", State::Task, increment, None, None, None, None);
+    old.step("NodeC", State::Task, increment, None, None, None, None);
+    let old = old.definition();
+
+    let mut shared_data = SharedData { counter: 0 };
+    let mut new = StateMachine::new("MachineDiff".to_string(), &mut shared_data, 3);
+    let catch = vec![ErrorBlock { error_equals: vec![String::from("States.ALL")], next: increment }];
+    new.step("NodeA", State::Task, increment, None, None, None, None);
+    new.step("NodeB", State::Sleep(2), increment, None, Some(catch), Some(vec!["Timeout"]), None);
+    new.step("NodeD", State::Task, increment, None, None, None, None);
+    new.set_retry_budget(5);
+    let new = new.definition();
+
+    assert_eq!(new.node("NodeB").unwrap().state, StateKind::Sleep(2));
+    assert!(old.diff(&old).is_empty());
+
+    let diff = old.diff(&new);
+    assert_eq!(diff.added_nodes, vec!["NodeD"]);
+    assert_eq!(diff.removed_nodes, vec!["NodeC"]);
+    assert_eq!(diff.changed_nodes.len(), 1);
+    assert_eq!(diff.to_string(), "\
+~ machine retry_budget: null -> 5
++ node NodeD
+- node NodeC
+~ node NodeB state: \"Task\" -> {\"Sleep\":2}
+~ node NodeB retry: [] -> [\"Timeout\"]
+~ node NodeB catch: [] -> [[\"States.ALL\"]]
++ transition NodeB -> NodeD
+- transition NodeB -> NodeC
+");
+
+    // the definition survives a json round-trip
+    let json = serde_json::to_string(&new).unwrap();
+    let parsed: MachineDefinition = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, new);
+}
@@ -0,0 +1,73 @@
+use std::error::Error;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State}, data::DeserializeStateData, diagnostics, lint::{self, LintConfig}};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  counter: i16,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn increment(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.counter += 1;
+    Ok(())
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = SharedData { counter: 0 };
+    let mut machine = StateMachine::new("MachineDiagnostics".to_string(), &mut shared_data, 3);
+    machine.step("NodeA", State::Task, increment, None, None, Some(vec!["Timeout"]), None);
+    machine.step("NodeB", State::Task, increment, None, None, None, None);
+    machine.step("NodeC", State::Pass, StateMachine::okay, None, None, Some(vec!["Timeout"]), None);
+    let source = serde_json::to_string_pretty(&machine.definition()).unwrap();
+
+    let found = diagnostics::diagnose_json(&source, &LintConfig::default());
+    let pointers: Vec<(&str, &str)> = found.iter()
+        .map(|d| (d.diagnostic.rule.as_str(), d.pointer.as_deref().unwrap()))
+        .collect();
+    assert_eq!(pointers, vec![
+        (lint::TASK_WITHOUT_RETRY, "/nodes/1/retry"),
+        (lint::UNREACHABLE_RETRY, "/nodes/2/retry"),
+    ]);
+
+    // the span underlines the key in the text
+    let span = found[0].span.unwrap();
+    assert_eq!(&source[span.start..span.end], "\"retry\"");
+    let line = source.lines().nth(span.line - 1).unwrap();
+    assert_eq!(line.chars().nth(span.column - 1), Some('"'));
+    assert!(line.trim_start().starts_with("\"retry\""));
+    assert!(span.start > source.find("NodeB").unwrap());
+}
+
+#[test]
+pub fn locate() {
+    let source = "{\"a\": [1, {\"b\": \"x\\\"y\"}, [true, null]],\n \"c\": {}}";
+    let span = diagnostics::locate(source, "/a/1/b").unwrap();
+    assert_eq!(&source[span.start..span.end], "\"b\"");
+    let span = diagnostics::locate(source, "/a/2").unwrap();
+    assert_eq!(&source[span.start..span.end], "[true, null]");
+    let span = diagnostics::locate(source, "/c").unwrap();
+    assert_eq!((span.line, span.column), (2, 2));
+    assert!(diagnostics::locate(source, "/a/3").is_none());
+    assert!(diagnostics::locate(source, "/d").is_none());
+}
+
+#[test]
+pub fn invalid() {
+    let found = diagnostics::diagnose_json("{\n  \"id\": 3\n}", &LintConfig::default());
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].diagnostic.rule, lint::INVALID_DEFINITION);
+    let span = found[0].span.unwrap();
+    assert_eq!(span.line, 2);
+    assert_eq!(span.start, span.end);
+}
@@ -0,0 +1,102 @@
+
+use std::error::Error;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State, ErrorBlock, StepDefinition}, data::DeserializeStateData,
+     analysis::{ExecutionPath, PathOutcome, PathStep}};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  counter: i16,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn increment(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.counter += 1;
+    Ok(())
+}
+
+fn cond() -> bool {
+    true
+}
+
+fn catch_all() -> Vec<ErrorBlock<SharedData>> {
+    vec![ErrorBlock { error_equals: vec![String::from("States.ALL")], next: increment }]
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = SharedData { counter: 0 };
+    let mut machine = StateMachine::new("MachineA".to_string(), &mut shared_data, 3);
+    machine.step("NodeA", State::Choice(cond), increment, None, None, None, None);
+    machine.step("NodeB", State::Task, increment, None, Some(catch_all()), None, None);
+    machine.step("NodeC", State::Pass, increment, None, None, None, None);
+    let definition = machine.definition();
+
+    let exploration = definition.explore(100);
+    assert!(!exploration.truncated);
+    // NodeA skipped, executed or failed, and for both non-failing branches NodeB
+    // executed, caught then continued, or caught with a failing handler
+    assert_eq!(exploration.paths.len(), 7);
+    assert!(exploration.paths.contains(&ExecutionPath {
+        steps: vec![
+            PathStep::Skipped("NodeA".to_string()),
+            PathStep::Caught { node: "NodeB".to_string(), block: 0 },
+            PathStep::Executed("NodeC".to_string()),
+        ],
+        outcome: PathOutcome::Succeeded,
+    }));
+
+    // an unreachable step after the end does not change the behavior
+    let mut shared_data = SharedData { counter: 0 };
+    let mut refactored = StateMachine::new("MachineB".to_string(), &mut shared_data, 3);
+    let mut node_b = StepDefinition::new("NodeB", State::Task, increment);
+    node_b.catch = Some(catch_all());
+    let mut end = StepDefinition::new("NodeD", State::Task, increment);
+    end.end = Some(true);
+    refactored.steps(vec![
+        StepDefinition::new("NodeA", State::Choice(cond), increment),
+        node_b,
+        StepDefinition::new("NodeC", State::Pass, increment),
+        end,
+        StepDefinition::new("NodeE", State::Task, increment),
+    ]).unwrap();
+    let equivalence = definition.equivalent(&refactored.definition(), 100);
+    assert!(equivalence.is_equivalent());
+    assert!(!equivalence.truncated);
+}
+
+#[test]
+pub fn different() {
+    let mut shared_data = SharedData { counter: 0 };
+    let mut machine = StateMachine::new("MachineA".to_string(), &mut shared_data, 3);
+    machine.step("NodeA", State::Task, increment, None, None, None, None);
+    machine.step("NodeB", State::Task, increment, None, None, None, None);
+    let definition = machine.definition();
+
+    // catching the failures of NodeB removes a failing outcome and adds caught paths
+    let mut shared_data = SharedData { counter: 0 };
+    let mut changed = StateMachine::new("MachineA".to_string(), &mut shared_data, 3);
+    changed.step("NodeA", State::Task, increment, None, None, None, None);
+    changed.step("NodeB", State::Task, increment, None, Some(catch_all()), None, None);
+
+    let equivalence = definition.equivalent(&changed.definition(), 100);
+    assert!(!equivalence.is_equivalent());
+    assert_eq!(equivalence.only_in_left, vec![ExecutionPath {
+        steps: vec![PathStep::Executed("NodeA".to_string())],
+        outcome: PathOutcome::Failed("NodeB".to_string()),
+    }]);
+    assert_eq!(equivalence.only_in_right.len(), 2);
+
+    let exploration = definition.explore(2);
+    assert!(exploration.truncated);
+    assert_eq!(exploration.paths.len(), 2);
+}
@@ -0,0 +1,123 @@
+use std::error::Error;
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State, ErrorBlock}, data::DeserializeStateData, error::{ErrorCode, ExecutionError}};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  calls: i16,
+  recovered: bool,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+// An io failure, the source of the storage error
+#[derive(Debug)]
+struct DiskFull;
+
+impl fmt::Display for DiskFull {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no space left on device")
+    }
+}
+
+impl Error for DiskFull {}
+
+// A storage error, the way thiserror would derive it
+#[derive(Debug)]
+enum StorageError {
+    Throttled,
+    WriteFailed(DiskFull),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Throttled => write!(f, "the storage is throttling the writes"),
+            StorageError::WriteFailed(_) => write!(f, "cannot write the object"),
+        }
+    }
+}
+
+impl Error for StorageError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StorageError::WriteFailed(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl ErrorCode for StorageError {
+    fn code(&self) -> String {
+        match self {
+            StorageError::Throttled => String::from("Storage.Throttled"),
+            StorageError::WriteFailed(_) => String::from("Storage.WriteFailed"),
+        }
+    }
+}
+
+fn write(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.calls += 1;
+    if data.calls == 1 {
+        return Err(Box::new(StorageError::Throttled));
+    }
+    Err(Box::new(StorageError::WriteFailed(DiskFull)))
+}
+
+fn recover(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.recovered = true;
+    Ok(())
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = SharedData { calls: 0, recovered: false };
+    let mut state_machine = StateMachine::new("MachineErrorCodes".to_string(), &mut shared_data, 1);
+    state_machine.error_codes_of::<StorageError>();
+    let catch = vec![ErrorBlock { error_equals: vec![String::from("Storage.WriteFailed")], next: recover }];
+    state_machine.step("Write", State::Task, write, None, Some(catch), Some(vec!["Storage.Throttled"]), None);
+    assert!(state_machine.execute().is_ok());
+
+    // the throttling is retried by its code, the write failure caught by its code
+    let event = &state_machine.history().events[0];
+    assert_eq!(event.attempts, 2);
+    assert_eq!(event.causes, vec![String::from("no space left on device")]);
+    assert_eq!(shared_data.calls, 2);
+    assert!(shared_data.recovered);
+}
+
+#[test]
+pub fn unregistered() {
+    // without registration, the message of the error is its code
+    let mut shared_data = SharedData { calls: 1, recovered: false };
+    let mut state_machine = StateMachine::new("MachineErrorMessages".to_string(), &mut shared_data, 1);
+    state_machine.step("Write", State::Task, write, None, None, None, None);
+    let err = state_machine.execute().unwrap_err();
+    assert_eq!(err, ExecutionError::NodeFailed { node: String::from("Write"), error: String::from("cannot write the object") });
+    assert_eq!(state_machine.history().events[0].causes, vec![String::from("no space left on device")]);
+}
+
+fn throttled(_: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    Err(Box::new(StorageError::Throttled))
+}
+
+#[test]
+pub fn catch_failed() {
+    // the failure of the catch handler is reported by its code too
+    let mut shared_data = SharedData { calls: 1, recovered: false };
+    let mut state_machine = StateMachine::new("MachineErrorCodes".to_string(), &mut shared_data, 1);
+    state_machine.error_codes_of::<StorageError>();
+    let catch = vec![ErrorBlock { error_equals: vec![String::from("Storage.WriteFailed")], next: throttled }];
+    state_machine.step("Write", State::Task, write, None, Some(catch), None, None);
+    let err = state_machine.execute().unwrap_err();
+    assert_eq!(err, ExecutionError::CatchFailed { node: String::from("Write"), error: String::from("Storage.Throttled") });
+}
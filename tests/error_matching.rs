@@ -0,0 +1,61 @@
+
+use std::error::Error;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State, ErrorBlock}, data::DeserializeStateData, backoff::BackoffConfig, error::{self, StateMachineError}};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  calls: i16,
+  caught: bool,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn unavailable(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.calls += 1;
+    Err(Box::new(StateMachineError { message: String::from("Http.503") }))
+}
+
+fn recover(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.caught = true;
+    Ok(())
+}
+
+#[test]
+pub fn main() {
+    assert!(error::matches("States.ALL", "anything"));
+    assert!(error::matches("Http.5*", "Http.500"));
+    assert!(error::matches("Http.5*", "Http.5"));
+    assert!(error::matches("*.Timeout", "Lambda.Timeout"));
+    assert!(error::matches("Http.*.retry*", "Http.502.retry-later"));
+    assert!(error::matches("STATE.FAILED", "STATE.FAILED"));
+    assert!(!error::matches("Http.5*", "Http.404"));
+    assert!(!error::matches("Http.5", "Http.500"));
+    assert!(!error::matches("STATE.FAILED", "STATE.FAILED.OTHER"));
+    assert!(error::matches_any(&["Timeout", "Http.5*"], "Http.504"));
+}
+
+#[test]
+pub fn machine() {
+    let mut shared_data = SharedData { calls: 0, caught: false };
+    let mut state_machine = StateMachine::new("MachineMatching".to_string(), &mut shared_data, 2);
+    state_machine.set_backoff_config(BackoffConfig { initial_delay: Duration::ZERO, ..Default::default() });
+
+    let catch = vec![ErrorBlock {
+        error_equals: vec![String::from("States.ALL")], next: recover
+    }];
+    state_machine.step("NodeA", State::Task, unavailable, None, Some(catch), Some(vec!["Http.5*"]), None);
+
+    state_machine.execute().expect("the error is caught");
+    assert_eq!(shared_data.calls, 3);
+    assert!(shared_data.caught);
+}
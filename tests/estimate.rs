@@ -0,0 +1,106 @@
+use std::error::Error;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State, RetryBlock}, data::DeserializeStateData, estimate::{PathProbabilities, StepBudget},
+    backoff::BackoffConfig};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  counter: i16,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn increment(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.counter += 1;
+    Ok(())
+}
+
+fn millis(millis: u64) -> Duration {
+    Duration::from_millis(millis)
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = SharedData { counter: 0 };
+    let mut machine = StateMachine::new("MachineEstimate".to_string(), &mut shared_data, 2);
+    machine.step("Validate", State::Task, increment, None, None, Some(vec!["Timeout"]), None);
+    machine.step("Fraud", State::Route(0, 25), increment, None, None, None, None);
+    machine.step("Wait", State::Sleep(1), StateMachine::okay, None, None, None, None);
+    machine.step("Notify", State::Task, increment, None, None, None, None);
+    machine.set_budget("Validate", StepBudget { latency: millis(100), cost: 5 }).unwrap();
+    machine.set_budget("Fraud", StepBudget { latency: millis(400), cost: 20 }).unwrap();
+    assert!(machine.set_budget("Missing", StepBudget::default()).is_err());
+    let definition = machine.definition();
+
+    let estimate = definition.estimate(&PathProbabilities::default());
+    assert_eq!(estimate.expected_latency, millis(1200));
+    assert!((estimate.expected_cost - 10.0).abs() < 1e-9);
+    assert_eq!(estimate.unannotated, vec![String::from("Notify")]);
+    assert!(!estimate.truncated);
+
+    // the worst branch takes the route and retries the validation twice, after the 1s and 2s
+    // delays of the default backoff
+    assert_eq!(estimate.branches.len(), 2);
+    let worst = estimate.worst().unwrap();
+    assert_eq!(worst.steps, vec!["Validate", "Fraud", "Wait", "Notify"]);
+    assert!((worst.probability - 0.25).abs() < 1e-9);
+    assert_eq!(worst.latency, millis(1500));
+    assert_eq!(worst.worst_latency, millis(4700));
+    assert_eq!((worst.cost, worst.worst_cost), (25, 35));
+
+    // the probabilities override the defaults
+    let estimate = definition.estimate(&PathProbabilities::default().step("Fraud", 1.0));
+    assert_eq!(estimate.branches.len(), 1);
+    assert_eq!(estimate.expected_latency, millis(1500));
+}
+
+#[test]
+pub fn saturates() {
+    let mut shared_data = SharedData { counter: 0 };
+    let mut machine = StateMachine::new("MachineEstimate".to_string(), &mut shared_data, 2);
+    machine.step("Poll", State::Task, increment, None, None, None, None);
+    machine.step("Notify", State::Task, increment, None, None, None, None);
+    // an exponential policy without a cap, e.g. imported with MaxAttempts 100 and BackoffRate 2
+    let backoff = BackoffConfig { max_retries: None, initial_delay: Duration::from_secs(1), multiplier: 2, on_event: None };
+    let retry_blocks = vec![RetryBlock { error_equals: vec![String::from("Pending")], max_retries: 100, backoff }];
+    machine.set_retry_blocks("Poll", retry_blocks).unwrap();
+    machine.set_budget("Poll", StepBudget { latency: millis(100), cost: 5 }).unwrap();
+    machine.set_budget("Notify", StepBudget { latency: millis(100), cost: 1 }).unwrap();
+
+    let estimate = machine.definition().estimate(&PathProbabilities::default());
+    let worst = estimate.worst().unwrap();
+    assert_eq!((worst.latency, worst.worst_latency), (millis(200), Duration::MAX));
+    assert_eq!((worst.cost, worst.worst_cost), (6, 506));
+
+    // the budgets themselves saturate the totals of the branches
+    machine.set_budget("Notify", StepBudget { latency: Duration::MAX, cost: u64::MAX }).unwrap();
+    let estimate = machine.definition().estimate(&PathProbabilities::default());
+    let worst = estimate.worst().unwrap();
+    assert_eq!((worst.latency, worst.cost), (Duration::MAX, u64::MAX));
+    assert_eq!(estimate.expected_latency, Duration::MAX);
+}
+
+#[test]
+pub fn capped_retries() {
+    let mut shared_data = SharedData { counter: 0 };
+    let mut machine = StateMachine::new("MachineEstimate".to_string(), &mut shared_data, 10);
+    machine.step("Validate", State::Task, increment, None, None, Some(vec!["Timeout"]), None);
+    machine.set_budget("Validate", StepBudget { latency: millis(100), cost: 5 }).unwrap();
+    machine.set_backoff_config(BackoffConfig { max_retries: Some(2), initial_delay: millis(10), multiplier: 3, on_event: None });
+    let definition = machine.definition();
+    assert_eq!(definition.backoff.max_retries, Some(2));
+
+    // the 10 retries are clamped to the cap of the backoff, as they are when executing
+    let worst = definition.estimate(&PathProbabilities::default()).worst().unwrap().clone();
+    assert_eq!(worst.worst_latency, millis(340));
+    assert_eq!(worst.worst_cost, 15);
+}
@@ -0,0 +1,156 @@
+
+use std::error::Error;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use sfn_machine::assert_path;
+use sfn_machine::machine::
+    {state::{StateMachine, State, ErrorBlock}, data::DeserializeStateData, backoff::BackoffConfig,
+     error::StateMachineError, history::EventOutcome, testing::{ExecutionAssert, MockHandlerRegistry}};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  counter: i16,
+  recovered: bool,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+// Stubbed handlers
+fn increment(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.counter += 1;
+    Ok(())
+}
+
+fn timeout(_: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    Err(Box::new(StateMachineError { message: String::from("Timeout") }))
+}
+
+fn recover(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.recovered = true;
+    Ok(())
+}
+
+fn never() -> bool {
+    false
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = SharedData { counter: 0, recovered: false };
+    let mut state_machine = StateMachine::new("MachineAssert".to_string(), &mut shared_data, 2);
+    state_machine.set_backoff_config(BackoffConfig { initial_delay: Duration::ZERO, ..Default::default() });
+
+    let catch = vec![ErrorBlock { error_equals: vec![String::from("Timeout")], next: recover }];
+    state_machine.step("NodeA", State::Task, increment, None, None, None, None);
+    state_machine.step("NodeB", State::Task, timeout, None, Some(catch), Some(vec!["Timeout"]), None);
+    state_machine.step("NodeC", State::Choice(never), increment, None, None, None, None);
+    state_machine.step("NodeD", State::Task, increment, None, None, None, None);
+
+    let execution = ExecutionAssert::run(&mut state_machine);
+    assert_path!(execution, ["NodeA", "NodeB", "NodeB.Catch0", "NodeC", "NodeD"])
+        .assert_succeeded()
+        .assert_retries("NodeB", 2)
+        .assert_total_retries(2)
+        .assert_data(|data| {
+            assert_eq!(data.counter, 2);
+            assert!(data.recovered);
+        });
+
+    let events = &execution.history().events;
+    assert_eq!(events[1].outcome, EventOutcome::Failed("Timeout".to_string()));
+    assert_eq!(events[1].attempts, 3);
+    assert_eq!(events[3].outcome, EventOutcome::Skipped);
+}
+
+#[test]
+pub fn failure() {
+    let mut shared_data = SharedData { counter: 0, recovered: false };
+    let mut state_machine = StateMachine::new("MachineAssert".to_string(), &mut shared_data, 2);
+    state_machine.step("NodeA", State::Task, increment, None, None, None, None);
+    state_machine.step("NodeB", State::Task, timeout, None, None, None, None);
+    state_machine.step("NodeC", State::Task, increment, None, None, None, None);
+
+    let execution = ExecutionAssert::run(&mut state_machine);
+    assert_path!(execution, ["NodeA", "NodeB"])
+        .assert_failed_with("Timeout")
+        .assert_total_retries(0);
+    assert_eq!(execution.history().error.as_deref(), Some("Timeout"));
+}
+
+#[test]
+#[should_panic(expected = "unexpected execution path")]
+pub fn unexpected_path() {
+    let mut shared_data = SharedData { counter: 0, recovered: false };
+    let mut state_machine = StateMachine::new("MachineAssert".to_string(), &mut shared_data, 2);
+    state_machine.step("NodeA", State::Task, increment, None, None, None, None);
+
+    let execution = ExecutionAssert::run(&mut state_machine);
+    assert_path!(execution, ["NodeB"]);
+}
+
+#[test]
+pub fn junit() {
+    let mut shared_data = SharedData { counter: 0, recovered: false };
+    let mut state_machine = StateMachine::new("MachineAssert".to_string(), &mut shared_data, 0);
+    let catch = vec![ErrorBlock { error_equals: vec![String::from("Timeout")], next: recover }];
+    state_machine.step("NodeA", State::Task, timeout, None, Some(catch), None, None);
+    state_machine.step("NodeB", State::Choice(never), increment, None, None, None, None);
+    state_machine.step("NodeC", State::Task, timeout, None, None, None, None);
+
+    let execution = ExecutionAssert::run(&mut state_machine);
+    let xml = execution.history().to_junit();
+    assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuite name=\"MachineAssert\" tests=\"4\" failures=\"1\" skipped=\"1\" time=\""));
+    assert!(xml.ends_with("</testsuite>\n"));
+
+    // the caught failure passes, the catch block being a test case of its own
+    assert!(xml.contains("name=\"NodeA\" time=\"0.000\">\n    <system-out>caught: Timeout</system-out>\n  </testcase>"));
+    assert!(xml.contains("name=\"NodeA.Catch0\" time=\"0.000\"/>"));
+    assert!(xml.contains("name=\"NodeB\" time=\"0.000\">\n    <skipped/>\n  </testcase>"));
+    assert!(xml.contains("name=\"NodeC\" time=\"0.000\">\n    <failure message=\"Timeout\" type=\"Timeout\">"));
+}
+
+// Fails with a timeout until the counter reaches 2
+fn flaky(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.counter += 1;
+    match data.counter {
+        0..=1 => timeout(data),
+        _ => Ok(()),
+    }
+}
+
+#[test]
+pub fn mock_handlers() {
+    let mocks = MockHandlerRegistry::new()
+        .mock("NodeB", flaky)
+        .mock("NodeD", StateMachine::okay);
+
+    let mut shared_data = SharedData { counter: 0, recovered: false };
+    let mut state_machine = StateMachine::new("MachineMocked".to_string(), &mut shared_data, 2);
+    state_machine.set_backoff_config(BackoffConfig { initial_delay: Duration::ZERO, ..Default::default() });
+    let catch = vec![ErrorBlock { error_equals: vec![String::from("Timeout")], next: recover }];
+    state_machine.step("NodeB", State::Task, timeout, None, Some(catch), Some(vec!["Timeout"]), None);
+    assert_eq!(mocks.apply(&mut state_machine), vec!["NodeB"]);
+
+    // the mocked step succeeds at its second attempt, its catch block is kept
+    ExecutionAssert::run(&mut state_machine)
+        .assert_path(&["NodeB"])
+        .assert_retries("NodeB", 1)
+        .assert_data(|data| assert_eq!((data.counter, data.recovered), (2, false)));
+
+    // the registry serves another machine
+    let mut shared_data = SharedData { counter: 0, recovered: false };
+    let mut other = StateMachine::new("MachineMockedToo".to_string(), &mut shared_data, 0);
+    other.step("NodeA", State::Task, increment, None, None, None, None);
+    other.step("NodeD", State::Task, timeout, None, None, None, None);
+    assert_eq!(mocks.apply(&mut other), vec!["NodeD"]);
+    ExecutionAssert::run(&mut other)
+        .assert_succeeded()
+        .assert_data(|data| assert_eq!(data.counter, 1));
+}
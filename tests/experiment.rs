@@ -0,0 +1,71 @@
+use std::error::Error;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, ExecutionOptions}, data::DeserializeStateData, experiment::Experiment};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  counter: i16,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+#[test]
+pub fn main() {
+    let experiment = Experiment::new("checkout", &[("control", 70), ("treatment", 30)]).unwrap();
+    let mut shared_data = SharedData { counter: 0 };
+    let mut state_machine = StateMachine::new("MachineExperiment".to_string(), &mut shared_data, 3);
+    state_machine.step("Control", experiment.route("control").unwrap(), StateMachine::okay, None, None, None, None);
+    state_machine.step("Treatment", experiment.route("treatment").unwrap(), StateMachine::error, None, None, None, None);
+    state_machine.set_experiment(experiment.clone());
+
+    let mut histories = Vec::new();
+    for execution in 0..500 {
+        let options = ExecutionOptions::default().routing_key(&format!("execution-{}", execution));
+        let _ = state_machine.execute_with(options);
+        let variant = state_machine.history().tag("checkout").unwrap();
+        let expected = experiment.variant(state_machine.history().routing_bucket).unwrap();
+        assert_eq!(variant, expected);
+        histories.push(state_machine.history().clone());
+    }
+
+    let summaries = experiment.summarize(&histories);
+    assert_eq!(summaries.len(), 2);
+    let (control, treatment) = (&summaries[0], &summaries[1]);
+    assert_eq!((control.variant.as_str(), treatment.variant.as_str()), ("control", "treatment"));
+    assert_eq!(control.executions + treatment.executions, 500);
+    assert!((100..200).contains(&treatment.executions), "{} treatment executions", treatment.executions);
+    assert_eq!(control.success_rate(), 100.0);
+    assert_eq!(treatment.success_rate(), 0.0);
+}
+
+#[test]
+pub fn invalid_weights() {
+    let err = Experiment::new("checkout", &[("control", 70), ("treatment", 20)]).unwrap_err();
+    assert_eq!(err.message, "The weights of experiment checkout add up to 90 instead of 100");
+
+    let experiment = Experiment::new("checkout", &[("control", 100)]).unwrap();
+    assert_eq!(experiment.variant(99), Some("control"));
+    assert!(experiment.route("treatment").is_none());
+}
+
+#[test]
+pub fn deserialized_weights() {
+    // the weights of a deserialized experiment are checked like those of a new one
+    let experiment = Experiment::new("checkout", &[("control", 70), ("treatment", 30)]).unwrap();
+    let json = serde_json::to_string(&experiment).unwrap();
+    let deserialized: Experiment = serde_json::from_str(&json).unwrap();
+    assert_eq!(deserialized, experiment);
+    assert_eq!((deserialized.name(), deserialized.variants().len()), ("checkout", 2));
+
+    let json = r#"{"name": "checkout", "variants": [{"name": "control", "weight": 70}, {"name": "treatment", "weight": 70}]}"#;
+    let err = serde_json::from_str::<Experiment>(json).unwrap_err();
+    assert_eq!(err.to_string(), "The weights of experiment checkout add up to 140 instead of 100");
+}
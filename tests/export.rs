@@ -0,0 +1,92 @@
+use std::error::Error;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sfn_machine::machine::
+    {state::{StateMachine, State, ErrorBlock, RetryBlock}, data::DeserializeStateData, backoff::BackoffConfig};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  counter: i16,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn add(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.counter += 1;
+    Ok(())
+}
+
+fn build(shared_data: &mut SharedData) -> StateMachine<'_, SharedData> {
+    let mut state_machine = StateMachine::new("Orders".to_string(), shared_data, 3);
+    let catch = vec![ErrorBlock { error_equals: vec![String::from("States.ALL")], next: add }];
+    state_machine.step("Reserve", State::Task, add, None, None, Some(vec!["Throttled"]), None);
+    state_machine.step("Charge", State::Task, add, None, Some(catch), None, None);
+    state_machine.step("Cool", State::Sleep(5), add, None, None, None, None);
+    state_machine.step("Ship", State::Task, add, None, None, None, Some(true));
+    state_machine.step("Unreachable", State::Task, add, None, None, None, None);
+    let backoff = BackoffConfig { max_retries: None, initial_delay: Duration::from_millis(1500), ..Default::default() };
+    state_machine.set_retry_blocks("Charge", vec![RetryBlock { error_equals: vec![String::from("Timeout")], max_retries: 2, backoff }]).unwrap();
+    state_machine
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = SharedData { counter: 0 };
+    let state_machine = build(&mut shared_data);
+    let asl = state_machine.definition().to_asl(|state| format!("arn:aws:lambda:eu-west-1:123:function:{}", state)).unwrap();
+
+    assert_eq!(asl["StartAt"], "Reserve");
+    assert_eq!(asl["States"], json!({
+        "Reserve": {
+            "Type": "Task",
+            "Resource": "arn:aws:lambda:eu-west-1:123:function:Reserve",
+            "Retry": [{ "ErrorEquals": ["Throttled"], "MaxAttempts": 3 }],
+            "Next": "Charge",
+        },
+        "Charge": {
+            "Type": "Task",
+            "Resource": "arn:aws:lambda:eu-west-1:123:function:Charge",
+            "Retry": [{ "ErrorEquals": ["Timeout"], "MaxAttempts": 2, "IntervalSeconds": 2, "BackoffRate": 2 }],
+            "Catch": [{ "ErrorEquals": ["States.ALL"], "Next": "Charge.Catch0" }],
+            "Next": "Cool",
+        },
+        "Charge.Catch0": {
+            "Type": "Task",
+            "Resource": "arn:aws:lambda:eu-west-1:123:function:Charge.Catch0",
+            "Next": "Cool",
+        },
+        // the end step is not run by the local engine, it is not exported
+        "Cool": { "Type": "Wait", "Seconds": 5, "End": true },
+    }));
+}
+
+#[test]
+pub fn templates() {
+    let mut shared_data = SharedData { counter: 0 };
+    let state_machine = build(&mut shared_data);
+    let definition = state_machine.definition();
+
+    let template: serde_json::Value = serde_json::from_str(&definition.to_cloudformation().unwrap()).unwrap();
+    let properties = &template["Resources"]["Orders"]["Properties"];
+    assert_eq!(template["Resources"]["Orders"]["Type"], "AWS::StepFunctions::StateMachine");
+    assert_eq!(properties["Definition"]["States"]["Charge.Catch0"]["Resource"], "${ChargeCatch0}");
+    assert_eq!(properties["DefinitionSubstitutions"]["ChargeCatch0"]["Fn::Sub"],
+        "arn:aws:lambda:${AWS::Region}:${AWS::AccountId}:function:Orders-ChargeCatch0");
+    assert_eq!(properties["DefinitionSubstitutions"].as_object().unwrap().len(), 3);
+
+    let terraform = definition.to_terraform().unwrap();
+    assert!(terraform.starts_with("resource \"aws_sfn_state_machine\" \"Orders\" {\n  name       = \"Orders\"\n"), "{}", terraform);
+    assert!(terraform.contains("\"Resource\": \"${var.lambda_arns.Reserve}\""), "{}", terraform);
+
+    let mut empty_data = SharedData { counter: 0 };
+    let empty = StateMachine::new("Empty".to_string(), &mut empty_data, 3);
+    assert_eq!(empty.definition().to_asl(|state| state.to_string()).unwrap_err().message, "State machine Empty has no steps to export");
+}
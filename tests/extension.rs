@@ -0,0 +1,92 @@
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State, RetryBlock, StepDefinition}, data::DeserializeStateData, backoff::BackoffConfig, error::ErrorCode, extension::StateExtension};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  appends: i16,
+  flushed: bool,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+// The error of the ledger, as a third-party crate would define it
+#[derive(Debug)]
+struct LedgerBusy;
+
+impl fmt::Display for LedgerBusy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the ledger is locked by another writer")
+    }
+}
+
+impl Error for LedgerBusy {}
+
+impl ErrorCode for LedgerBusy {
+    fn code(&self) -> String {
+        String::from("Ledger.Busy")
+    }
+}
+
+fn append(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.appends += 1;
+    if data.appends < 3 {
+        return Err(Box::new(LedgerBusy));
+    }
+    Ok(())
+}
+
+fn flush(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.flushed = true;
+    Ok(())
+}
+
+// The reusable steps of the ledger
+struct Ledger;
+
+impl<'a> StateExtension<'a, SharedData> for Ledger {
+    fn name(&self) -> &str {
+        "Ledger"
+    }
+
+    fn steps(&self) -> Vec<StepDefinition<'a, SharedData>> {
+        vec![
+            StepDefinition::new("Append", State::Task, append),
+            StepDefinition::new("Flush", State::Task, flush),
+        ]
+    }
+
+    fn retry_blocks(&self) -> Vec<(String, Vec<RetryBlock>)> {
+        let backoff = BackoffConfig { max_retries: None, initial_delay: Duration::ZERO, ..Default::default() };
+        vec![(String::from("Append"), vec![RetryBlock { error_equals: vec![String::from("Ledger.Busy")], max_retries: 3, backoff }])]
+    }
+
+    fn configure(&self, machine: &mut StateMachine<'a, SharedData>) {
+        machine.error_codes_of::<LedgerBusy>();
+    }
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = SharedData { appends: 0, flushed: false };
+    let mut state_machine = StateMachine::new("MachineExtension".to_string(), &mut shared_data, 0);
+    state_machine.extend(&Ledger).unwrap();
+    let err = state_machine.extend(&Ledger).unwrap_err();
+    assert_eq!(err.to_string(), "Duplicate node IDs found: Ledger.Append, Ledger.Flush");
+
+    state_machine.execute().unwrap();
+    let history = state_machine.history();
+    assert_eq!(history.path(), vec!["Ledger.Append", "Ledger.Flush"]);
+    assert_eq!(history.events[0].attempts, 3);
+    assert!(state_machine.data().flushed);
+}
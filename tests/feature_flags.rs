@@ -0,0 +1,59 @@
+use std::error::Error;
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State}, data::DeserializeStateData, flags::InMemoryFlags, history::EventOutcome};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  counter: i16,
+  fallback: bool,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn add(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.counter += 1;
+    Ok(())
+}
+
+fn fallback(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.fallback = true;
+    Ok(())
+}
+
+#[test]
+pub fn main() {
+    let flags = Arc::new(InMemoryFlags::new());
+    let mut shared_data = SharedData { counter: 0, fallback: false };
+    let mut state_machine = StateMachine::new("MachineFlags".to_string(), &mut shared_data, 3);
+    state_machine.step("NodeA", State::Task, add, Some(add), None, None, None);
+    state_machine.step("NodeB", State::Task, add, None, None, None, None);
+    state_machine.set_feature_flag("NodeA", "node-a", None).unwrap();
+    state_machine.set_feature_flag("NodeB", "node-b", Some(fallback)).unwrap();
+    state_machine.set_feature_flags(flags.clone());
+    assert_eq!(state_machine.definition().nodes[0].flag.as_deref(), Some("node-a"));
+
+    // unknown flags are on
+    state_machine.execute().unwrap();
+    assert_eq!(state_machine.data().counter, 3);
+
+    // the flags are switched off between two executions
+    flags.set("node-a", false);
+    flags.set("node-b", false);
+    state_machine.execute().unwrap();
+    assert_eq!(state_machine.data().counter, 3);
+    assert!(state_machine.data().fallback);
+    let outcomes: Vec<&EventOutcome> = state_machine.history().events.iter().map(|event| &event.outcome).collect();
+    assert_eq!(outcomes, vec![&EventOutcome::Skipped, &EventOutcome::Succeeded]);
+
+    let err = state_machine.set_feature_flag("NodeC", "node-c", None).unwrap_err();
+    assert_eq!(err.message, "Node ID not found: NodeC");
+}
@@ -0,0 +1,81 @@
+use std::error::Error;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State}, data::DeserializeStateData, error::ExecutionError, handler::Handler};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  name: String,
+  greeting: Option<String>,
+  visits: u32,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+// The event and result of an existing Lambda function
+#[derive(Deserialize)]
+struct Request {
+    name: String,
+}
+
+#[derive(Serialize)]
+struct Response {
+    greeting: String,
+}
+
+#[derive(Default)]
+struct Greeter;
+
+impl Handler<Request, Response> for Greeter {
+    fn call(&self, event: Request) -> Result<Response, Box<dyn Error>> {
+        if event.name.is_empty() {
+            return Err("Anonymous".into());
+        }
+        Ok(Response { greeting: format!("Hello {}", event.name) })
+    }
+}
+
+// A handler returning a bare value rather than an object
+#[derive(Default)]
+struct Counter;
+
+impl Handler<Request, u32> for Counter {
+    fn call(&self, _: Request) -> Result<u32, Box<dyn Error>> {
+        Ok(1)
+    }
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = SharedData { name: String::from("Ada"), greeting: None, visits: 3 };
+    let mut state_machine = StateMachine::new("MachineHandler".to_string(), &mut shared_data, 1);
+    state_machine.step("Greet", State::Task, StateMachine::handler::<Greeter, _, _>, None, None, None, None);
+    state_machine.execute().unwrap();
+
+    // the result is merged into the shared data, the other fields are kept
+    assert_eq!(shared_data.greeting.as_deref(), Some("Hello Ada"));
+    assert_eq!((shared_data.name.as_str(), shared_data.visits), ("Ada", 3));
+}
+
+#[test]
+pub fn failures() {
+    let mut shared_data = SharedData { name: String::new(), greeting: None, visits: 0 };
+    let mut state_machine = StateMachine::new("MachineHandler".to_string(), &mut shared_data, 1);
+    state_machine.step("Greet", State::Task, StateMachine::handler::<Greeter, _, _>, None, None, None, None);
+    let err = state_machine.execute().unwrap_err();
+    assert_eq!(err, ExecutionError::NodeFailed { node: String::from("Greet"), error: String::from("Anonymous") });
+    assert!(state_machine.data().greeting.is_none());
+
+    let mut shared_data = SharedData { name: String::from("Ada"), greeting: None, visits: 0 };
+    let mut state_machine = StateMachine::new("MachineHandler".to_string(), &mut shared_data, 1);
+    state_machine.step("Count", State::Task, StateMachine::handler::<Counter, _, _>, None, None, None, None);
+    let err = state_machine.execute().unwrap_err();
+    assert!(err.to_string().contains("must be a json object, got 1"), "{}", err);
+}
@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::error::Error;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sfn_machine::machine::
+    {state::{StateMachine, State, ErrorBlock}, data::DeserializeStateData, error::StateMachineError};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  counter: i16,
+  refunded: bool,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn add(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.counter += 1;
+    Ok(())
+}
+
+fn decline(_: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    Err(Box::new(StateMachineError { message: String::from("Declined") }))
+}
+
+fn refund(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.refunded = true;
+    Ok(())
+}
+
+type Handler = fn(&mut SharedData) -> Result<(), Box<dyn Error>>;
+
+fn handlers() -> HashMap<&'static str, Handler> {
+    HashMap::from([
+        ("arn:aws:lambda:eu-west-1:123:function:reserve", add as Handler),
+        ("arn:aws:lambda:eu-west-1:123:function:charge", decline),
+        ("arn:aws:lambda:eu-west-1:123:function:refund", refund),
+    ])
+}
+
+#[test]
+pub fn main() {
+    let asl = json!({
+        "StartAt": "Reserve",
+        "States": {
+            "Reserve": {
+                "Type": "Task",
+                "Resource": "arn:aws:lambda:eu-west-1:123:function:reserve",
+                "Retry": [{ "ErrorEquals": ["Throttled"], "MaxAttempts": 2 }],
+                "Next": "Charge",
+            },
+            "Charge": {
+                "Type": "Task",
+                "Resource": "arn:aws:lambda:eu-west-1:123:function:charge",
+                "Catch": [{ "ErrorEquals": ["Declined"], "Next": "Refund" }],
+                "Next": "Wait",
+            },
+            "Refund": { "Type": "Task", "Resource": "arn:aws:lambda:eu-west-1:123:function:refund", "Next": "Wait" },
+            "Wait": { "Type": "Wait", "Seconds": 0, "Next": "Done" },
+            "Done": { "Type": "Succeed" },
+        },
+    });
+    let mut shared_data = SharedData { counter: 0, refunded: false };
+    let mut state_machine = StateMachine::new("MachineImport".to_string(), &mut shared_data, 3);
+    state_machine.import_asl(&asl, &handlers()).unwrap();
+
+    let definition = state_machine.definition();
+    assert_eq!(definition.nodes.iter().map(|node| node.id.as_str()).collect::<Vec<_>>(), vec!["Reserve", "Charge", "Wait"]);
+    assert_eq!(definition.nodes[0].retry_blocks[0].max_retries, 2);
+    assert_eq!(definition.nodes[0].retry_blocks[0].max_retries_cap, Some(2));
+    assert_eq!(definition.nodes[1].catch, vec![vec![String::from("Declined")]]);
+
+    state_machine.execute().unwrap();
+    assert_eq!(state_machine.history().path(), vec!["Reserve", "Charge", "Charge.Catch0", "Wait"]);
+    assert_eq!((shared_data.counter, shared_data.refunded), (1, true));
+}
+
+#[test]
+pub fn round_trip() {
+    let mut shared_data = SharedData { counter: 0, refunded: false };
+    let mut state_machine = StateMachine::new("MachineImport".to_string(), &mut shared_data, 3);
+    let catch = vec![ErrorBlock { error_equals: vec![String::from("Declined")], next: refund }];
+    state_machine.step("Reserve", State::Task, add, None, None, None, None);
+    state_machine.step("Charge", State::Task, decline, None, Some(catch), None, None);
+    let asl = state_machine.definition().to_asl(|state| state.to_string()).unwrap();
+
+    let handlers = HashMap::from([
+        ("Reserve", add as Handler),
+        ("Charge", decline),
+        ("Charge.Catch0", refund),
+    ]);
+    let mut imported_data = SharedData { counter: 0, refunded: false };
+    let mut imported = StateMachine::new("MachineImport".to_string(), &mut imported_data, 3);
+    imported.import_asl(&asl, &handlers).unwrap();
+    assert!(imported.definition().diff(&state_machine.definition()).is_empty());
+}
+
+#[test]
+pub fn round_trip_end() {
+    let mut shared_data = SharedData { counter: 0, refunded: false };
+    let mut state_machine = StateMachine::new("MachineImport".to_string(), &mut shared_data, 3);
+    state_machine.step("Reserve", State::Task, add, None, None, None, None);
+    state_machine.step("Charge", State::Task, add, None, None, None, None);
+    state_machine.step("Ship", State::Task, add, None, None, None, Some(true));
+    state_machine.step("Unreachable", State::Task, add, None, None, None, None);
+    let asl = state_machine.definition().to_asl(|state| state.to_string()).unwrap();
+    state_machine.execute().unwrap();
+
+    let handlers = HashMap::from([("Reserve", add as Handler), ("Charge", add), ("Ship", add)]);
+    let mut imported_data = SharedData { counter: 0, refunded: false };
+    let mut imported = StateMachine::new("MachineImport".to_string(), &mut imported_data, 3);
+    imported.import_asl(&asl, &handlers).unwrap();
+    imported.execute().unwrap();
+
+    // the exported machine runs the same handlers as the local one
+    assert_eq!(imported.history().path(), state_machine.history().path());
+    assert_eq!(imported.history().path(), vec!["Reserve", "Charge"]);
+    assert_eq!(imported.data().counter, state_machine.data().counter);
+}
+
+#[test]
+pub fn rejected() {
+    let asl = json!({
+        "StartAt": "Reserve",
+        "States": {
+            "Reserve": { "Type": "Task", "Resource": "arn:aws:lambda:eu-west-1:123:function:unknown", "Next": "Route" },
+            "Route": { "Type": "Choice", "Choices": [], "Default": "Done" },
+            "Done": { "Type": "Succeed" },
+        },
+    });
+    let mut shared_data = SharedData { counter: 0, refunded: false };
+    let mut state_machine = StateMachine::new("MachineImport".to_string(), &mut shared_data, 3);
+    let err = state_machine.import_asl(&asl, &handlers()).unwrap_err();
+    assert_eq!(err.message, "Cannot import the ASL definition:\n- Choice state Route is not supported\n- resource arn:aws:lambda:eu-west-1:123:function:unknown is not mapped to a function");
+    assert!(state_machine.definition().nodes.is_empty());
+}
+
+#[test]
+pub fn rejected_retries() {
+    let asl = json!({
+        "StartAt": "Reserve",
+        "States": {
+            "Reserve": {
+                "Type": "Task",
+                "Resource": "arn:aws:lambda:eu-west-1:123:function:reserve",
+                "Retry": [{ "ErrorEquals": ["Throttled"], "BackoffRate": 1.5 }],
+                "Catch": [{ "ErrorEquals": ["Declined"], "Next": "Refund" }],
+                "Next": "Done",
+            },
+            "Refund": {
+                "Type": "Task",
+                "Resource": "arn:aws:lambda:eu-west-1:123:function:refund",
+                "Retry": [{ "ErrorEquals": ["States.ALL"] }],
+                "Next": "Done",
+            },
+            "Done": { "Type": "Succeed" },
+        },
+    });
+    let mut shared_data = SharedData { counter: 0, refunded: false };
+    let mut state_machine = StateMachine::new("MachineImport".to_string(), &mut shared_data, 3);
+    let err = state_machine.import_asl(&asl, &handlers()).unwrap_err();
+    assert_eq!(err.message, "Cannot import the ASL definition:\n\
+        - the catcher Refund of state Reserve cannot retry, catch blocks are not retried\n\
+        - the BackoffRate 1.5 of state Reserve is not supported, it must be a whole number");
+    assert!(state_machine.definition().nodes.is_empty());
+}
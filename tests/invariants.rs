@@ -0,0 +1,71 @@
+use std::error::Error;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State, ErrorBlock}, data::DeserializeStateData, error::{self, ExecutionError, StateMachineError}, history::EventOutcome};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  balance: i32,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn deposit(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.balance += 50;
+    Ok(())
+}
+
+fn withdraw(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.balance -= 100;
+    Ok(())
+}
+
+fn fail(_: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    Err(Box::new(StateMachineError { message: String::from("Failed") }))
+}
+
+fn non_negative(data: &SharedData) -> Result<(), String> {
+    if data.balance < 0 {
+        return Err(format!("the balance is {}", data.balance));
+    }
+    Ok(())
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = SharedData { balance: 20 };
+    let mut state_machine = StateMachine::new("MachineInvariants".to_string(), &mut shared_data, 3);
+    state_machine.add_invariant("non-negative balance", non_negative);
+    state_machine.step("Deposit", State::Task, deposit, None, None, None, None);
+    state_machine.step("Withdraw", State::Task, withdraw, None, None, None, None);
+    state_machine.step("Deposit2", State::Task, deposit, None, None, None, None);
+
+    let err = state_machine.execute().unwrap_err();
+    assert_eq!(err, ExecutionError::InvariantViolated {
+        invariant: String::from("non-negative balance"),
+        node: String::from("Withdraw"),
+        message: String::from("the balance is -30"),
+    });
+    assert_eq!(err.to_string(), "States.InvariantViolated: invariant non-negative balance violated by Withdraw: the balance is -30");
+    assert_eq!(state_machine.history().path(), vec!["Deposit", "Withdraw"]);
+    assert_eq!(state_machine.history().events[1].outcome, EventOutcome::Failed(error::INVARIANT_VIOLATED.to_string()));
+}
+
+#[test]
+pub fn catch_block() {
+    let mut shared_data = SharedData { balance: 20 };
+    let mut state_machine = StateMachine::new("MachineInvariants".to_string(), &mut shared_data, 3);
+    state_machine.add_invariant("non-negative balance", non_negative);
+    let catch = vec![ErrorBlock { error_equals: vec![String::from("Failed")], next: withdraw }];
+    state_machine.step("Charge", State::Task, fail, None, Some(catch), None, None);
+
+    let err = state_machine.execute().unwrap_err();
+    assert_eq!(err.node(), Some("Charge.Catch0"));
+}
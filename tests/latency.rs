@@ -0,0 +1,130 @@
+use std::error::Error;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State}, data::DeserializeStateData, history::{EventOutcome, ExecutionHistory}, latency::LatencyReport,
+    definition::RetryDefinition};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  counter: i16,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn increment(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.counter += 1;
+    Ok(())
+}
+
+fn never() -> bool {
+    false
+}
+
+// The history of an execution whose steps lasted the given number of milliseconds
+fn history(millis: u64, failed: bool) -> ExecutionHistory {
+    let mut shared_data = SharedData { counter: 0 };
+    let mut state_machine = StateMachine::new("MachineLatency".to_string(), &mut shared_data, 1);
+    state_machine.step("Fetch", State::Task, increment, None, None, None, None);
+    state_machine.step("Skip", State::Choice(never), increment, None, None, None, None);
+    state_machine.execute().unwrap();
+    let mut history = state_machine.history().clone();
+    history.events[0].duration = Duration::from_millis(millis);
+    if failed {
+        history.events[0].outcome = EventOutcome::Failed(String::from("Timeout"));
+    }
+    history
+}
+
+#[test]
+pub fn main() {
+    let histories: Vec<ExecutionHistory> = (1..=100).map(|millis| history(millis, millis % 10 == 0)).collect();
+    let report = LatencyReport::aggregate(&histories);
+    assert_eq!(report.executions, 100);
+
+    // skipped steps are not aggregated
+    assert_eq!(report.nodes.len(), 1);
+    assert!(report.node("Skip").is_none());
+
+    let fetch = report.node("Fetch").unwrap();
+    assert_eq!(fetch.executions, 100);
+    assert_eq!(fetch.p50, Duration::from_millis(50));
+    assert_eq!(fetch.p95, Duration::from_millis(95));
+    assert_eq!(fetch.p99, Duration::from_millis(99));
+    assert_eq!(fetch.max, Duration::from_millis(100));
+    assert!((fetch.failure_rate() - 0.1).abs() < 1e-9);
+
+    let markdown = report.to_markdown();
+    assert!(markdown.starts_with("| step | executions | p50 | p95 | p99 | max | failure rate |"));
+    assert!(markdown.contains("| Fetch | 100 | 50ms | 95ms | 99ms | 100ms | 10.0% |"));
+}
+
+#[test]
+pub fn tune_from_history() {
+    let histories: Vec<ExecutionHistory> = (1..=100).map(|millis| history(millis, false)).collect();
+    let report = LatencyReport::aggregate(&histories);
+
+    let mut shared_data = SharedData { counter: 0 };
+    let mut state_machine = StateMachine::new("MachineLatency".to_string(), &mut shared_data, 1);
+    state_machine.step("Fetch", State::Task, increment, None, None, None, None);
+    state_machine.step("Skip", State::Choice(never), increment, None, None, None, None);
+    let mut definition = state_machine.definition();
+    definition.nodes[0].retry_blocks.push(RetryDefinition {
+        error_equals: vec![String::from("Timeout")],
+        max_retries: 3,
+        max_retries_cap: None,
+        initial_delay_ms: 1000,
+        multiplier: 2,
+    });
+
+    let tuning = definition.tune(&report, 3.0);
+    assert_eq!(tuning.suggestions.len(), 1);
+    let fetch = &tuning.suggestions[0];
+    assert_eq!(fetch.node, "Fetch");
+    assert_eq!(fetch.timeout, Duration::from_millis(297));
+    assert_eq!(fetch.retry_interval, Duration::from_millis(99));
+    assert_eq!(fetch.expected_latency, Duration::from_millis(50));
+    assert_eq!(tuning.stall_timeout, Some(Duration::from_millis(297)));
+
+    // the patch only touches the aggregated step
+    assert_eq!(tuning.definition.nodes[0].retry_blocks[0].initial_delay_ms, 99);
+    assert_eq!(tuning.definition.nodes[0].budget.unwrap().latency, Duration::from_millis(50));
+    assert_eq!(tuning.definition.nodes[1], definition.nodes[1]);
+    assert_eq!(tuning.patch.changed_nodes.len(), 1);
+    assert_eq!(tuning.patch.changed_nodes[0].id, "Fetch");
+    assert_eq!(definition.diff(&tuning.definition), tuning.patch);
+}
+
+#[test]
+pub fn tune_without_retry_delays() {
+    // the retries of the step waited 900ms of the 1s it lasted
+    let histories: Vec<ExecutionHistory> = (1..=100).map(|_| {
+        let mut history = history(1000, false);
+        history.events[0].retry_delay = Duration::from_millis(900);
+        history
+    }).collect();
+    let report = LatencyReport::aggregate(&histories);
+    assert_eq!(report.node("Fetch").unwrap().p99, Duration::from_millis(1000));
+    assert_eq!(report.node("Fetch").unwrap().p99_running, Duration::from_millis(100));
+
+    let mut shared_data = SharedData { counter: 0 };
+    let mut state_machine = StateMachine::new("MachineLatency".to_string(), &mut shared_data, 1);
+    state_machine.step("Fetch", State::Task, increment, None, None, None, None);
+    let definition = state_machine.definition();
+    let tuning = definition.tune(&report, 2.0);
+    assert_eq!(tuning.suggestions[0].retry_interval, Duration::from_millis(100));
+    assert_eq!(tuning.suggestions[0].timeout, Duration::from_millis(2000));
+
+    // the factor saturates instead of panicking
+    assert_eq!(definition.tune(&report, f64::INFINITY).suggestions[0].timeout, Duration::MAX);
+    assert_eq!(definition.tune(&report, 1e300).suggestions[0].timeout, Duration::MAX);
+    assert_eq!(definition.tune(&report, f64::NAN).suggestions[0].timeout, Duration::from_millis(1000));
+    assert_eq!(definition.tune(&report, -1.0).suggestions[0].timeout, Duration::from_millis(1000));
+}
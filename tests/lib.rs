@@ -1,3 +1,52 @@
+// the original assertions of the basic test are kept as they were written
+#[allow(clippy::ptr_arg, clippy::needless_range_loop, clippy::bool_assert_comparison)]
 pub mod basic;
 pub mod custom;
-pub mod propagate_error;
\ No newline at end of file
+pub mod propagate_error;
+pub mod bulk;
+pub mod backoff;
+pub mod backoff_async;
+pub mod retry_budget;
+pub mod retry_blocks;
+pub mod error_matching;
+pub mod definition;
+pub mod equivalence;
+pub mod lint;
+pub mod execution_assert;
+pub mod coverage;
+pub mod snapshots;
+pub mod replay;
+pub mod tags;
+pub mod payload;
+pub mod archive;
+pub mod routing;
+pub mod experiment;
+pub mod batch;
+pub mod feature_flags;
+pub mod model_check;
+pub mod stall;
+pub mod profile;
+pub mod codec;
+pub mod compile;
+pub mod export;
+pub mod import;
+pub mod lineage;
+pub mod invariants;
+pub mod retryable;
+pub mod error_codes;
+pub mod panics;
+pub mod cli;
+pub mod projection;
+pub mod shadow;
+pub mod compare;
+pub mod storm;
+pub mod stats;
+pub mod estimate;
+pub mod latency;
+pub mod sampling;
+pub mod transactions;
+pub mod extension;
+pub mod diagnostics;
+pub mod handler;
+#[cfg(feature = "bench")]
+pub mod bench_harness;
@@ -0,0 +1,57 @@
+use std::error::Error;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State, ErrorBlock}, data::DeserializeStateData, error::StateMachineError};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  counter: i16,
+  status: String,
+  owner: String,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn add(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.counter += 10;
+    Ok(())
+}
+
+fn corrupt(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.status = String::from("wrong");
+    Err(Box::new(StateMachineError { message: String::from("Failed") }))
+}
+
+fn recover(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.status = String::from("recovered");
+    data.counter *= 2;
+    Ok(())
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = SharedData { counter: 1, status: String::from("new"), owner: String::from("ops") };
+    let mut state_machine = StateMachine::new("MachineLineage".to_string(), &mut shared_data, 3);
+    state_machine.enable_snapshots();
+
+    let catch = vec![ErrorBlock { error_equals: vec![String::from("Failed")], next: recover }];
+    state_machine.step("NodeA", State::Task, add, None, None, None, None);
+    state_machine.step("NodeB", State::Task, corrupt, None, Some(catch), None, None);
+    state_machine.step("NodeC", State::Task, add, None, None, None, None);
+    state_machine.execute().unwrap();
+
+    let lineage = state_machine.history().lineage();
+    assert_eq!(lineage.writers("counter"), ["NodeA", "NodeB.Catch0", "NodeC"]);
+    assert_eq!(lineage.last_writer("status"), Some("NodeB.Catch0"));
+    assert_eq!(lineage.writers("status"), ["NodeB", "NodeB.Catch0"]);
+    assert_eq!(lineage.last_writer("owner"), None);
+    assert_eq!(lineage.written_by("NodeB.Catch0"), vec!["counter", "status"]);
+    assert_eq!(lineage.to_string(), "lineage of MachineLineage\n  counter <- NodeA, NodeB.Catch0, NodeC\n  status <- NodeB, NodeB.Catch0\n");
+}
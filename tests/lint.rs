@@ -0,0 +1,84 @@
+
+use std::error::Error;
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State, ErrorBlock}, data::DeserializeStateData, lint::{self, LintConfig, Severity}};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  counter: i16,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn increment(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.counter += 1;
+    Ok(())
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = SharedData { counter: 0 };
+    let mut machine = StateMachine::new("MachineLint".to_string(), &mut shared_data, 3);
+    let catch = vec![
+        ErrorBlock { error_equals: vec![String::from("States.ALL")], next: increment },
+        ErrorBlock { error_equals: vec![String::from("Timeout")], next: increment },
+    ];
+    machine.step("NodeA", State::Task, increment, None, Some(catch), Some(vec!["Timeout"]), None);
+    machine.step("NodeB", State::Task, increment, None, None, None, None);
+    machine.step("NodeC", State::Sleep(600), StateMachine::okay, None, None, Some(vec!["Timeout"]), None);
+    machine.step("NodeD", State::Task, increment, None, None, None, Some(true));
+    machine.step("NodeE", State::Task, increment, None, None, None, None);
+    let definition = machine.definition();
+
+    let config = LintConfig { max_sleep_secs: Some(60), ..Default::default() };
+    let report = definition.lint(&config);
+    let rules: Vec<(&str, &str)> = report.diagnostics.iter()
+        .map(|d| (d.rule.as_str(), d.node.as_deref().unwrap()))
+        .collect();
+    assert_eq!(rules, vec![
+        (lint::UNREACHABLE_CATCH, "NodeA"),
+        (lint::TASK_WITHOUT_RETRY, "NodeB"),
+        (lint::UNREACHABLE_RETRY, "NodeC"),
+        (lint::SLEEP_TOO_LONG, "NodeC"),
+        (lint::END_STEP_NOT_EXECUTED, "NodeD"),
+        (lint::UNREACHABLE_STEP, "NodeE"),
+    ]);
+    assert!(report.has_errors());
+    assert_eq!(report.at_least(Severity::Warning).len(), 5);
+    assert_eq!(report.diagnostics[3].to_string(),
+        "Error [sleep-too-long] NodeC: the step sleeps for 600s, more than the maximum of 60s");
+
+    // rules can be disabled or have their severity changed
+    let config = LintConfig {
+        disabled: vec![lint::SLEEP_TOO_LONG.to_string(), lint::UNREACHABLE_STEP.to_string()],
+        severities: HashMap::from([(lint::TASK_WITHOUT_RETRY.to_string(), Severity::Error)]),
+        max_sleep_secs: Some(60),
+    };
+    let report = definition.lint(&config);
+    assert_eq!(report.diagnostics.len(), 4);
+    let errors = report.at_least(Severity::Error);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].rule, lint::TASK_WITHOUT_RETRY);
+}
+
+#[test]
+pub fn default_max_sleep() {
+    let mut shared_data = SharedData { counter: 0 };
+    let mut machine = StateMachine::new("MachineLint".to_string(), &mut shared_data, 3);
+    machine.step("Week", State::Sleep(7 * 24 * 60 * 60), StateMachine::okay, None, None, None, None);
+    machine.step("Decade", State::Sleep(10 * lint::DEFAULT_MAX_SLEEP_SECS), StateMachine::okay, None, None, None, None);
+
+    // the sleeps are checked without any configuration
+    let report = machine.definition().lint(&LintConfig::default());
+    assert_eq!(report.diagnostics.len(), 1);
+    assert_eq!((report.diagnostics[0].rule.as_str(), report.diagnostics[0].node.as_deref()), (lint::SLEEP_TOO_LONG, Some("Decade")));
+}
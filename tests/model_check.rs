@@ -0,0 +1,72 @@
+use std::error::Error;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State, ErrorBlock}, data::DeserializeStateData,
+     analysis::{ExecutionPath, PathOutcome, PathStep}, check::Invariant};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  counter: i16,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn increment(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.counter += 1;
+    Ok(())
+}
+
+fn catch_all() -> Vec<ErrorBlock<SharedData>> {
+    vec![ErrorBlock { error_equals: vec![String::from("States.ALL")], next: increment }]
+}
+
+fn short(path: &ExecutionPath) -> bool {
+    path.steps.len() <= 3
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = SharedData { counter: 0 };
+    let mut machine = StateMachine::new("MachineCheck".to_string(), &mut shared_data, 3);
+    machine.step("Payment", State::Task, increment, None, None, None, None);
+    machine.step("Shipping", State::Task, increment, None, Some(catch_all()), None, None);
+    machine.step("Compensation", State::Task, increment, None, None, None, None);
+    let definition = machine.definition();
+
+    let invariants = [
+        Invariant::Precedence { required: String::from("Payment"), node: String::from("Shipping") },
+        Invariant::Response { trigger: String::from("Payment"), response: String::from("Compensation") },
+        Invariant::Custom { name: String::from("at most three steps"), holds: short },
+    ];
+    let check = definition.check(&invariants, 100);
+    assert!(!check.truncated);
+    assert!(!check.holds());
+
+    // the compensation is missed when a step fails after the payment
+    assert!(check.violations.iter().all(|v| v.invariant == "Payment is always followed by Compensation"));
+    assert_eq!(check.violations[0].path, ExecutionPath {
+        steps: vec![PathStep::Executed(String::from("Payment")), PathStep::Executed(String::from("Shipping"))],
+        outcome: PathOutcome::Failed(String::from("Compensation")),
+    });
+    assert!(check.to_string().contains("  Payment is always followed by Compensation: [Payment, Shipping.Catch0] failed at Shipping\n"));
+}
+
+#[test]
+pub fn absence() {
+    let mut shared_data = SharedData { counter: 0 };
+    let mut machine = StateMachine::new("MachineCheck".to_string(), &mut shared_data, 3);
+    machine.step("NodeA", State::Pass, increment, None, None, None, None);
+    machine.step("NodeB", State::Pass, increment, None, None, None, Some(true));
+    machine.step("NodeC", State::Task, increment, None, None, None, None);
+
+    let check = machine.definition().check(&[Invariant::Absence(String::from("NodeC")), Invariant::NeverFails], 100);
+    assert!(check.holds(), "{}", check);
+    assert_eq!(check.paths, 1);
+}
@@ -0,0 +1,104 @@
+use std::error::Error;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State, ErrorBlock}, data::DeserializeStateData, backoff::BackoffConfig, error::{self, ExecutionError}, history::EventOutcome};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  calls: i16,
+  recovered: bool,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+// Panics on the first call only
+fn flaky(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.calls += 1;
+    if data.calls == 1 {
+        panic!("index out of bounds: the len is 0 but the index is 0");
+    }
+    Ok(())
+}
+
+fn broken(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    assert!(data.recovered, "the data is not recovered");
+    Ok(())
+}
+
+fn recover(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.recovered = true;
+    Ok(())
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = SharedData { calls: 0, recovered: false };
+    let mut state_machine = StateMachine::new("MachinePanics".to_string(), &mut shared_data, 2);
+    state_machine.set_backoff_config(BackoffConfig { initial_delay: Duration::from_millis(1), ..Default::default() });
+    state_machine.step("Flaky", State::Task, flaky, None, None, Some(vec![error::RUNTIME]), None);
+    let catch = vec![ErrorBlock { error_equals: vec![String::from(error::RUNTIME)], next: recover }];
+    state_machine.step("Broken", State::Task, broken, None, Some(catch), None, None);
+    assert!(state_machine.execute().is_ok());
+
+    // the panic is retried, then caught, and its message recorded
+    let events = &state_machine.history().events;
+    assert_eq!(events[0].attempts, 2);
+    assert_eq!(events[0].outcome, EventOutcome::Succeeded);
+    assert_eq!(events[1].outcome, EventOutcome::Failed(String::from(error::RUNTIME)));
+    assert_eq!(events[1].causes, vec![String::from("the data is not recovered")]);
+    assert_eq!(events[2].outcome, EventOutcome::Caught { block: 0, error: String::from(error::RUNTIME) });
+    assert_eq!(shared_data.calls, 2);
+    assert!(shared_data.recovered);
+}
+
+#[test]
+pub fn uncaught() {
+    // a panic fails the execution instead of the process
+    let mut shared_data = SharedData { calls: 0, recovered: false };
+    let mut state_machine = StateMachine::new("MachineUncaughtPanic".to_string(), &mut shared_data, 2);
+    state_machine.step("Flaky", State::Task, flaky, None, None, None, None);
+    let err = state_machine.execute().unwrap_err();
+    assert_eq!(err, ExecutionError::NodeFailed { node: String::from("Flaky"), error: String::from(error::RUNTIME) });
+    assert_eq!(state_machine.history().events[0].causes, vec![String::from("index out of bounds: the len is 0 but the index is 0")]);
+}
+
+fn unknown_condition() -> bool {
+    panic!("the condition cannot be evaluated")
+}
+
+fn recovered(data: &SharedData) -> Result<(), String> {
+    assert!(data.calls < 2, "too many calls");
+    Ok(())
+}
+
+#[test]
+pub fn callbacks() {
+    // a panicking choice condition fails its step, and can be caught
+    let mut shared_data = SharedData { calls: 0, recovered: false };
+    let mut state_machine = StateMachine::new("MachineCallbackPanic".to_string(), &mut shared_data, 2);
+    let catch = vec![ErrorBlock { error_equals: vec![String::from(error::RUNTIME)], next: recover }];
+    state_machine.step("Choose", State::Choice(unknown_condition), flaky, None, Some(catch), None, None);
+    assert!(state_machine.execute().is_ok());
+    assert_eq!(state_machine.history().events[0].causes, vec![String::from("the condition cannot be evaluated")]);
+    assert!(shared_data.recovered);
+
+    // a panicking invariant is violated
+    let mut shared_data = SharedData { calls: 1, recovered: false };
+    let mut state_machine = StateMachine::new("MachineCallbackPanic".to_string(), &mut shared_data, 2);
+    state_machine.add_invariant("recovered", recovered);
+    state_machine.step("Flaky", State::Task, flaky, None, None, None, None);
+    let err = state_machine.execute().unwrap_err();
+    assert_eq!(err, ExecutionError::InvariantViolated {
+        invariant: String::from("recovered"),
+        node: String::from("Flaky"),
+        message: String::from("too many calls"),
+    });
+}
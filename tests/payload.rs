@@ -0,0 +1,151 @@
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State, ErrorBlock}, data::DeserializeStateData, error, payload::{Payload, PayloadRef, PayloadStore, MemoryPayloadStore, ReplicatedPayloadStore}};
+
+static STORE: OnceLock<MemoryPayloadStore> = OnceLock::new();
+
+fn store() -> &'static MemoryPayloadStore {
+    STORE.get_or_init(MemoryPayloadStore::new)
+}
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  report: Payload<String>,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn generate(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.report = Payload::Inline("x".repeat(1000));
+    Ok(())
+}
+
+fn offload(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.report.offload(store())
+}
+
+fn read(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    assert_eq!(data.report.load(store())?.len(), 1000);
+    Ok(())
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = SharedData { report: Payload::Inline(String::new()) };
+    let mut state_machine = StateMachine::new("MachinePayload".to_string(), &mut shared_data, 3);
+    state_machine.set_max_data_size(200);
+    state_machine.enable_snapshots();
+
+    let catch = vec![ErrorBlock { error_equals: vec![error::DATA_LIMIT_EXCEEDED.to_string()], next: offload }];
+    state_machine.step("Generate", State::Task, generate, None, Some(catch), None, None);
+    state_machine.step("Read", State::Task, read, None, None, None, None);
+    state_machine.execute().unwrap();
+
+    assert_eq!(state_machine.history().path(), vec!["Generate", "Generate.Catch0", "Read"]);
+    assert!(state_machine.data().report.is_offloaded());
+    let snapshot = state_machine.history().events[2].data_after.as_ref().unwrap();
+    assert!(snapshot.len() < 200);
+}
+
+#[test]
+pub fn limit_exceeded() {
+    let mut shared_data = SharedData { report: Payload::Inline(String::new()) };
+    let mut state_machine = StateMachine::new("MachinePayload".to_string(), &mut shared_data, 3);
+    state_machine.set_max_data_size(200);
+    state_machine.step("Generate", State::Task, generate, None, None, None, None);
+
+    let err = state_machine.execute().unwrap_err();
+    assert_eq!(err.to_string(), error::DATA_LIMIT_EXCEEDED);
+    assert!(matches!(err, error::ExecutionError::DataLimitExceeded { .. }));
+    // the size of the data is recorded as the cause of the failure
+    let causes = &state_machine.history().events[0].causes;
+    assert_eq!(causes.len(), 1);
+    assert!(causes[0].ends_with("over the limit of 200 bytes"), "{}", causes[0]);
+}
+
+#[test]
+pub fn offload_above() {
+    let store = MemoryPayloadStore::new();
+    let mut small = Payload::Inline(String::from("small"));
+    small.offload_above(100, &store).unwrap();
+    assert!(!small.is_offloaded());
+
+    let mut large = Payload::Inline("y".repeat(500));
+    large.offload_above(100, &store).unwrap();
+    match &large {
+        Payload::Offloaded(reference) => assert_eq!(reference.size, 502),
+        Payload::Inline(_) => panic!("the payload was not offloaded"),
+    }
+    assert_eq!(large.load(&store).unwrap(), "y".repeat(500));
+    assert!(store.get(&sfn_machine::machine::payload::PayloadRef { key: String::from("missing"), size: 0 }).is_err());
+}
+
+// A store which can be taken down, failing every read
+#[derive(Debug, Default)]
+struct FlakyStore {
+    store: MemoryPayloadStore,
+    down: Arc<AtomicBool>,
+}
+
+impl PayloadStore for FlakyStore {
+    fn put(&self, payload: &str) -> Result<PayloadRef, Box<dyn Error>> {
+        self.store.put(payload)
+    }
+
+    fn get(&self, reference: &PayloadRef) -> Result<String, Box<dyn Error>> {
+        if self.down.load(Ordering::SeqCst) {
+            return Err("the store is down".into());
+        }
+        self.store.get(reference)
+    }
+}
+
+#[test]
+pub fn unique_keys() {
+    let store = MemoryPayloadStore::new();
+    let replica = PayloadRef { key: String::from("payload-1"), size: 7 };
+    store.put_at(&replica, "replica").unwrap();
+
+    // the keys of the stored payloads are skipped
+    let first = store.put("first").unwrap();
+    let second = store.put("second").unwrap();
+    assert_eq!((first.key.as_str(), second.key.as_str()), ("payload-0", "payload-2"));
+    assert_eq!(store.get(&replica).unwrap(), "replica");
+    assert_eq!(store.get(&first).unwrap(), "first");
+    assert_eq!(store.get(&second).unwrap(), "second");
+}
+
+#[test]
+pub fn replication() {
+    let primary = FlakyStore::default();
+    let down = primary.down.clone();
+    let store = ReplicatedPayloadStore::new(primary, MemoryPayloadStore::new());
+
+    let mut report = Payload::Inline("z".repeat(300));
+    report.offload(&store).unwrap();
+    store.flush();
+    assert_eq!(store.pending(), 0);
+    assert_eq!(store.failures(), 0);
+
+    // the reads fall back to the replica when the primary store is down
+    down.store(true, Ordering::SeqCst);
+    assert_eq!(report.load(&store).unwrap(), "z".repeat(300));
+    let missing = PayloadRef { key: String::from("missing"), size: 0 };
+    assert_eq!(store.get(&missing).unwrap_err().to_string(), "the store is down");
+
+    // a secondary store which cannot hold replicas
+    let store = ReplicatedPayloadStore::new(MemoryPayloadStore::new(), FlakyStore::default());
+    store.put("payload").unwrap();
+    store.flush();
+    assert_eq!(store.failures(), 1);
+}
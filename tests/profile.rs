@@ -0,0 +1,55 @@
+use std::error::Error;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State}, data::DeserializeStateData, backoff::BackoffConfig, error::StateMachineError};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  counter: i16,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn flaky(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.counter += 1;
+    if data.counter < 2 {
+        return Err(Box::new(StateMachineError { message: String::from("Flaky") }));
+    }
+    Ok(())
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = SharedData { counter: 0 };
+    let mut state_machine = StateMachine::new("MachineProfile".to_string(), &mut shared_data, 3);
+    state_machine.set_backoff_config(BackoffConfig { initial_delay: Duration::from_millis(200), ..Default::default() });
+    state_machine.enable_snapshots();
+    state_machine.step("Flaky", State::Task, flaky, None, None, Some(vec!["Flaky"]), None);
+    state_machine.step("Wait", State::Sleep(1), StateMachine::okay, None, None, None, None);
+    state_machine.step("Done", State::Pass, StateMachine::okay, None, None, None, None);
+    state_machine.execute().unwrap();
+
+    let profile = state_machine.history().profile();
+    assert_eq!(profile.nodes.len(), 3);
+    let (flaky, wait) = (&profile.nodes[0], &profile.nodes[1]);
+    assert_eq!(flaky.retry, Duration::from_millis(200));
+    assert!(flaky.wall >= flaky.retry);
+    assert!(wait.wait >= Duration::from_secs(1));
+    assert!(flaky.serialization > Duration::ZERO);
+    assert_eq!(profile.slowest(1)[0].node, "Wait");
+    let percent: f64 = profile.nodes.iter().map(|node| node.percent).sum();
+    assert!((percent - 100.0).abs() < 1e-6);
+
+    let folded = profile.to_flamegraph_folded();
+    assert!(folded.lines().any(|line| line.starts_with("MachineProfile;Flaky;retry 200")));
+    assert!(folded.lines().any(|line| line.starts_with("MachineProfile;Wait;wait 1")));
+    assert!(folded.lines().all(|line| line.rsplit_once(' ').unwrap().1.parse::<u128>().is_ok()));
+}
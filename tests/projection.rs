@@ -0,0 +1,67 @@
+use std::error::Error;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State, ExecutionOptions}, data::DeserializeStateData, projection::InputSample};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  counter: i16,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn increment(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.counter += 1;
+    Ok(())
+}
+
+fn premium() -> bool {
+    true
+}
+
+fn ids(nodes: &[String]) -> Vec<&str> {
+    nodes.iter().map(String::as_str).collect()
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = SharedData { counter: 0 };
+    let mut machine = StateMachine::new("MachineProjection".to_string(), &mut shared_data, 3);
+    machine.step("Validate", State::Task, increment, None, None, None, None);
+    machine.step("RouteA", State::Route(0, 50), increment, None, None, None, None);
+    machine.step("RouteB", State::Route(50, 100), increment, None, None, None, None);
+    machine.step("Upsell", State::Choice(premium), increment, None, None, None, None);
+    machine.step("Notify", State::Task, increment, None, None, None, None);
+    machine.set_feature_flag("Notify", "notifications", None).unwrap();
+    machine.step("Done", State::Succeed, StateMachine::okay, None, None, None, Some(true));
+    machine.step("Orphan", State::Task, increment, None, None, None, None);
+    let definition = machine.definition();
+
+    // the routing bucket of a sample is the one taken by the executions with its routing key
+    let bucket = machine.routing_bucket("customer-1");
+    machine.execute_with(ExecutionOptions::default().routing_key("customer-1")).unwrap();
+    assert_eq!(machine.history().routing_bucket, bucket);
+
+    // without samples, only the steps after the end are pruned
+    let projection = definition.project(&[]);
+    assert_eq!(projection.pruned, vec![String::from("Orphan")]);
+
+    let sample = InputSample::default().routing_bucket(10).flag("notifications", false).choice("Upsell", false);
+    let projection = definition.project(std::slice::from_ref(&sample));
+    let kept: Vec<&str> = projection.definition.nodes.iter().map(|node| node.id.as_str()).collect();
+    assert_eq!(kept, vec!["Validate", "RouteA", "Done"]);
+    assert_eq!(ids(&projection.pruned), vec!["RouteB", "Upsell", "Notify", "Orphan"]);
+    assert!(projection.to_string().contains("- RouteB"));
+
+    // a step is kept when any of the samples reaches it
+    let other = InputSample::default().routing_bucket(75).flag("notifications", true);
+    let projection = definition.project(&[sample, other]);
+    assert_eq!(ids(&projection.pruned), vec!["Orphan"]);
+}
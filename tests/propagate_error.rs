@@ -2,7 +2,7 @@
 use std::error::Error;
 use serde::{Deserialize, Serialize};
 use sfn_machine::machine::
-    {state::{StateMachine, State, ErrorBlock}, data::DeserializeStateData};
+    {state::{StateMachine, State, ErrorBlock}, data::DeserializeStateData, error::ExecutionError};
 
 // Define the struct representing the shared data
 #[derive(Debug, Serialize, Deserialize)]
@@ -75,4 +75,30 @@ pub fn main() {
     }
   
     assert_eq!(shared_data.counter, 5);
-  }
\ No newline at end of file
+  }
+
+#[test]
+pub fn kinds() {
+    fn state_function_a(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+      data.counter += 1;
+      Ok(())
+    }
+
+    // an uncaught error of a step
+    let mut shared_data = SharedData { counter: 0, id: String::from("some-id") };
+    let mut state_machine = StateMachine::new("MachineKinds".to_string(), &mut shared_data, 3);
+    state_machine.step("NodeA", State::Task, state_function_a, None, None, None, None);
+    state_machine.step("NodeB", State::Task, StateMachine::error, None, None, None, None);
+    let err = state_machine.execute().unwrap_err();
+    assert_eq!(err, ExecutionError::NodeFailed { node: String::from("NodeB"), error: String::from("STATE.FAILED") });
+    assert_eq!(err.node(), Some("NodeB"));
+
+    // a failing catch block
+    let mut shared_data = SharedData { counter: 0, id: String::from("some-id") };
+    let mut state_machine = StateMachine::new("MachineKinds".to_string(), &mut shared_data, 3);
+    let catch = vec![ErrorBlock { error_equals: vec![String::from("STATE.FAILED")], next: StateMachine::error }];
+    state_machine.step("NodeA", State::Task, StateMachine::error, None, Some(catch), None, None);
+    let err = state_machine.execute().unwrap_err();
+    assert_eq!(err, ExecutionError::CatchFailed { node: String::from("NodeA"), error: String::from("STATE.FAILED") });
+    assert_eq!(state_machine.history().error.as_deref(), Some("STATE.FAILED"));
+}
@@ -0,0 +1,133 @@
+use std::error::Error;
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State, ErrorBlock}, data::DeserializeStateData, history::EventOutcome, error::{ErrorCode, StateMachineError}};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  counter: i16,
+  status: String,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn add(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.counter += 10;
+    Ok(())
+}
+
+// a new version of add, with a regression
+fn add_twice(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.counter += 20;
+    Ok(())
+}
+
+fn fail(_data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    Err(Box::new(StateMachineError { message: String::from("Failed") }))
+}
+
+fn recover(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.status = String::from("recovered");
+    Ok(())
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = SharedData { counter: 1, status: String::from("new") };
+    let mut state_machine = StateMachine::new("MachineReplay".to_string(), &mut shared_data, 3);
+    state_machine.enable_snapshots();
+    let catch = vec![ErrorBlock { error_equals: vec![String::from("Failed")], next: recover }];
+    state_machine.step("NodeA", State::Task, add, None, None, None, None);
+    state_machine.step("NodeB", State::Task, fail, None, Some(catch), None, None);
+    state_machine.step("NodeC", State::Task, add, None, None, None, None);
+    state_machine.execute().unwrap();
+    let recorded = state_machine.history().clone();
+
+    // the same handlers reproduce the recorded execution
+    let report = state_machine.replay_with_live_handlers(&recorded).unwrap();
+    assert!(report.is_identical(), "{}", report);
+    assert_eq!(report.steps.len(), 3);
+    assert_eq!(report.steps[1].recorded, EventOutcome::Caught { block: 0, error: String::from("Failed") });
+
+    // new handlers for NodeA and NodeB
+    let mut shared_data = SharedData { counter: 1, status: String::from("new") };
+    let mut state_machine = StateMachine::new("MachineReplay".to_string(), &mut shared_data, 3);
+    let catch = vec![ErrorBlock { error_equals: vec![String::from("Failed")], next: recover }];
+    state_machine.step("NodeA", State::Task, add_twice, None, None, None, None);
+    state_machine.step("NodeB", State::Task, add, None, Some(catch), None, None);
+    state_machine.step("NodeC", State::Task, add, None, None, None, None);
+
+    let report = state_machine.replay_with_live_handlers(&recorded).unwrap();
+    let regressions = report.regressions();
+    assert_eq!(regressions.len(), 2);
+    assert_eq!(regressions[0].node, "NodeA");
+    assert_eq!(regressions[0].differences[0].path, "$.counter");
+    assert_eq!(regressions[0].differences[0].recorded.as_deref(), Some("11"));
+    assert_eq!(regressions[0].differences[0].live.as_deref(), Some("21"));
+    assert_eq!(regressions[1].live, Some(EventOutcome::Succeeded));
+    assert_eq!(report.to_string(), "replay of MachineReplay: 2 regressions\n\
+        \x20 NodeA $.counter: 11 -> 21\n\
+        \x20 NodeB: outcome Caught { block: 0, error: \"Failed\" } -> Succeeded\n\
+        \x20 NodeB $.counter: 11 -> 21\n\
+        \x20 NodeB $.status: \"recovered\" -> \"new\"\n");
+}
+
+#[test]
+pub fn without_snapshots() {
+    let mut shared_data = SharedData { counter: 1, status: String::from("new") };
+    let mut state_machine = StateMachine::new("MachineReplay".to_string(), &mut shared_data, 3);
+    state_machine.step("NodeA", State::Task, add, None, None, None, None);
+    state_machine.execute().unwrap();
+
+    let err = state_machine.replay_with_live_handlers(&state_machine.history().clone()).unwrap_err();
+    assert_eq!(err.message, "no snapshot recorded for step NodeA");
+}
+
+// An error identified by its code rather than its message
+#[derive(Debug)]
+struct Declined;
+
+impl fmt::Display for Declined {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the card was declined")
+    }
+}
+
+impl Error for Declined {}
+
+impl ErrorCode for Declined {
+    fn code(&self) -> String {
+        String::from("Payment.Declined")
+    }
+}
+
+fn decline(_data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    Err(Box::new(Declined))
+}
+
+#[test]
+pub fn error_codes() {
+    let mut shared_data = SharedData { counter: 1, status: String::from("new") };
+    let mut state_machine = StateMachine::new("MachineReplay".to_string(), &mut shared_data, 3);
+    state_machine.enable_snapshots();
+    state_machine.error_codes_of::<Declined>();
+    let catch = vec![ErrorBlock { error_equals: vec![String::from("Payment.Declined")], next: recover }];
+    state_machine.step("Charge", State::Task, decline, None, Some(catch), None, None);
+    state_machine.step("Refund", State::Task, decline, None, None, None, None);
+    assert!(state_machine.execute().is_err());
+    let recorded = state_machine.history().clone();
+
+    // the replayed errors are caught and reported by their code, like the recorded ones
+    let report = state_machine.replay_with_live_handlers(&recorded).unwrap();
+    assert!(report.is_identical(), "{}", report);
+    assert_eq!(report.steps[0].live, Some(EventOutcome::Caught { block: 0, error: String::from("Payment.Declined") }));
+    assert_eq!(report.steps[1].live, Some(EventOutcome::Failed(String::from("Payment.Declined"))));
+}
@@ -0,0 +1,76 @@
+
+use std::error::Error;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State, RetryBlock}, data::DeserializeStateData, backoff::BackoffConfig, error::{StateMachineError, ExecutionError}};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  // errors returned by the successive calls, the call succeeds once they are consumed
+  errors: Vec<String>,
+  calls: i16,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn call(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.calls += 1;
+    if data.errors.is_empty() {
+        return Ok(());
+    }
+    Err(Box::new(StateMachineError { message: data.errors.remove(0) }))
+}
+
+fn retry_blocks() -> Vec<RetryBlock> {
+    let backoff = BackoffConfig { max_retries: None, initial_delay: Duration::ZERO, ..Default::default() };
+    vec![
+        RetryBlock { error_equals: vec![String::from("Throttled")], max_retries: 10, backoff },
+        RetryBlock { error_equals: vec![String::from("Timeout")], max_retries: 3, backoff },
+        RetryBlock { error_equals: vec![String::from("Validation")], max_retries: 0, backoff },
+    ]
+}
+
+fn run(errors: &[&str]) -> (Result<(), ExecutionError>, i16) {
+    let mut shared_data = SharedData { errors: errors.iter().map(|e| e.to_string()).collect(), calls: 0 };
+    let mut state_machine = StateMachine::new("MachineRetry".to_string(), &mut shared_data, 1);
+    state_machine.step("NodeA", State::Task, call, None, None, None, None);
+    state_machine.set_retry_blocks("NodeA", retry_blocks()).unwrap();
+    let result = state_machine.execute();
+    (result, shared_data.calls)
+}
+
+#[test]
+pub fn main() {
+    // throttling is retried up to ten times
+    let (result, calls) = run(&["Throttled"; 10]);
+    assert!(result.is_ok());
+    assert_eq!(calls, 11);
+
+    // timeouts only three times
+    let (result, calls) = run(&["Timeout"; 4]);
+    let err = result.unwrap_err();
+    assert_eq!(err.to_string(), "Timeout");
+    assert_eq!(err, ExecutionError::RetryExhausted { node: String::from("NodeA"), error: String::from("Timeout"), attempts: 4 });
+    assert_eq!(calls, 4);
+
+    // validation errors are never retried
+    let (result, calls) = run(&["Validation"]);
+    assert_eq!(result.unwrap_err(), ExecutionError::NodeFailed { node: String::from("NodeA"), error: String::from("Validation") });
+    assert_eq!(calls, 1);
+}
+
+#[test]
+pub fn unknown_node() {
+    let mut shared_data = SharedData { errors: Vec::new(), calls: 0 };
+    let mut state_machine = StateMachine::new("MachineRetry".to_string(), &mut shared_data, 1);
+    let err = state_machine.set_retry_blocks("NodeA", retry_blocks()).unwrap_err();
+    assert_eq!(err.to_string(), "Node ID not found: NodeA");
+}
@@ -0,0 +1,97 @@
+
+use std::error::Error;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State, ErrorBlock}, data::DeserializeStateData, backoff::BackoffConfig, error::StateMachineError};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  attempts_a: i16,
+  attempts_b: i16,
+  caught: bool,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn throttled() -> Box<dyn Error> {
+    Box::new(StateMachineError { message: String::from("Throttled") })
+}
+
+// fails once, then succeeds
+fn flaky(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.attempts_a += 1;
+    if data.attempts_a < 2 { Err(throttled()) } else { Ok(()) }
+}
+
+fn broken(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.attempts_b += 1;
+    Err(throttled())
+}
+
+fn recover(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.caught = true;
+    Ok(())
+}
+
+fn no_delay() -> BackoffConfig {
+    BackoffConfig { initial_delay: Duration::ZERO, ..Default::default() }
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = SharedData { attempts_a: 0, attempts_b: 0, caught: false };
+    let mut state_machine = StateMachine::new("MachineBudget".to_string(), &mut shared_data, 5);
+    state_machine.set_backoff_config(no_delay());
+    state_machine.set_retry_budget(3);
+
+    let catch = vec![ErrorBlock {
+        error_equals: vec![String::from("States.RetryBudgetExhausted")], next: recover
+    }];
+    state_machine.step("NodeA", State::Task, flaky, None, None, Some(vec!["Throttled"]), None);
+    state_machine.step("NodeB", State::Task, broken, None, Some(catch), Some(vec!["Throttled"]), None);
+
+    state_machine.execute().expect("the exhausted budget is caught");
+
+    // NodeA used one retry, NodeB the two left
+    assert_eq!(shared_data.attempts_a, 2);
+    assert_eq!(shared_data.attempts_b, 3);
+    assert!(shared_data.caught);
+}
+
+#[test]
+pub fn uncaught() {
+    let mut shared_data = SharedData { attempts_a: 0, attempts_b: 0, caught: false };
+    let mut state_machine = StateMachine::new("MachineBudget".to_string(), &mut shared_data, 5);
+    state_machine.set_backoff_config(no_delay());
+    state_machine.set_retry_budget(0);
+
+    state_machine.step("NodeB", State::Task, broken, None, None, Some(vec!["Throttled"]), None);
+
+    let err = state_machine.execute().unwrap_err();
+    assert_eq!(err.to_string(), "States.RetryBudgetExhausted");
+    assert_eq!(err.node(), Some("NodeB"));
+    assert_eq!(shared_data.attempts_b, 1);
+}
+
+#[test]
+pub fn within_budget() {
+    let mut shared_data = SharedData { attempts_a: 0, attempts_b: 0, caught: false };
+    let mut state_machine = StateMachine::new("MachineBudget".to_string(), &mut shared_data, 2);
+    state_machine.set_backoff_config(no_delay());
+    state_machine.set_retry_budget(10);
+
+    state_machine.step("NodeB", State::Task, broken, None, None, Some(vec!["Throttled"]), None);
+
+    // the step ran out of its own retries before the budget ran out
+    let err = state_machine.execute().unwrap_err();
+    assert_eq!(err.to_string(), "Throttled");
+    assert_eq!(shared_data.attempts_b, 3);
+}
@@ -0,0 +1,98 @@
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State}, data::DeserializeStateData, backoff::BackoffConfig, error::{ExecutionError, Retryable}};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  // errors returned by the successive calls, the call succeeds once they are consumed
+  errors: Vec<u16>,
+  calls: i16,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+// An http error, throttling responses carry a retry-after delay
+#[derive(Debug)]
+struct HttpError {
+    status: u16,
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Http.{}", self.status)
+    }
+}
+
+impl Error for HttpError {}
+
+impl Retryable for HttpError {
+    fn is_retryable(&self) -> bool {
+        self.status == 429 || self.status >= 500
+    }
+
+    fn retry_after(&self) -> Option<Duration> {
+        (self.status == 429).then_some(Duration::from_millis(5))
+    }
+}
+
+fn call(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.calls += 1;
+    if data.errors.is_empty() {
+        return Ok(());
+    }
+    Err(Box::new(HttpError { status: data.errors.remove(0) }))
+}
+
+fn run(errors: &[u16]) -> (Result<(), ExecutionError>, i16, Duration) {
+    let mut shared_data = SharedData { errors: errors.to_vec(), calls: 0 };
+    let mut state_machine = StateMachine::new("MachineRetryable".to_string(), &mut shared_data, 3);
+    // a long backoff, only the retry-after delays keep the test fast
+    state_machine.set_backoff_config(BackoffConfig { initial_delay: Duration::from_secs(60), ..Default::default() });
+    state_machine.retry_errors_of::<HttpError>();
+    state_machine.step("Call", State::Task, call, None, None, None, None);
+    let result = state_machine.execute();
+    let delay = state_machine.history().events[0].retry_delay;
+    (result, shared_data.calls, delay)
+}
+
+#[test]
+pub fn main() {
+    // throttling is retried after the delay given by the error
+    let (result, calls, delay) = run(&[429, 429]);
+    assert!(result.is_ok());
+    assert_eq!(calls, 3);
+    assert_eq!(delay, Duration::from_millis(10));
+
+    // client errors are not retried
+    let (result, calls, _) = run(&[404]);
+    assert_eq!(result.unwrap_err(), ExecutionError::NodeFailed { node: String::from("Call"), error: String::from("Http.404") });
+    assert_eq!(calls, 1);
+
+    // the retries stop at the first error which is not retryable
+    let (result, calls, _) = run(&[429, 400]);
+    assert_eq!(result.unwrap_err().to_string(), "Http.400");
+    assert_eq!(calls, 2);
+}
+
+#[test]
+pub fn saturating_delays() {
+    let mut shared_data = SharedData { errors: vec![429; 4], calls: 0 };
+    let mut state_machine = StateMachine::new("MachineRetryable".to_string(), &mut shared_data, 5);
+    // the backoff delay overflows at the third retry, the retry-after delays are waited instead
+    state_machine.set_backoff_config(BackoffConfig { initial_delay: Duration::from_secs(60), multiplier: u32::MAX, ..Default::default() });
+    state_machine.retry_errors_of::<HttpError>();
+    state_machine.step("Call", State::Task, call, None, None, None, None);
+    assert!(state_machine.execute().is_ok());
+    assert_eq!(state_machine.history().events[0].retry_delay, Duration::from_millis(20));
+    assert_eq!(shared_data.calls, 5);
+}
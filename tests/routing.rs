@@ -0,0 +1,125 @@
+use std::error::Error;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State, ExecutionOptions}, data::DeserializeStateData, definition::StateKind, error::{ExecutionError, StateMachineError}};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  canary: u32,
+  baseline: u32,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn canary(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.canary += 1;
+    Ok(())
+}
+
+fn baseline(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.baseline += 1;
+    Ok(())
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = SharedData { canary: 0, baseline: 0 };
+    let mut state_machine = StateMachine::new("MachineRouting".to_string(), &mut shared_data, 3);
+    state_machine.step("Canary", State::Route(0, 10), canary, None, None, None, None);
+    state_machine.step("Baseline", State::Route(10, 100), baseline, None, None, None, None);
+    assert_eq!(state_machine.definition().nodes[0].state, StateKind::Route(0, 10));
+
+    for execution in 0..1000 {
+        let key = format!("execution-{}", execution);
+        state_machine.execute_with(ExecutionOptions::default().routing_key(&key)).unwrap();
+        let taken = state_machine.history().path().len();
+        assert_eq!(taken, 2);
+    }
+    // every execution took exactly one of the routes, about 10% of them the canary
+    let data = state_machine.data();
+    assert_eq!(data.canary + data.baseline, 1000);
+    assert!((50..150).contains(&data.canary), "{} canary executions", data.canary);
+}
+
+#[test]
+pub fn sticky() {
+    let mut shared_data = SharedData { canary: 0, baseline: 0 };
+    let mut state_machine = StateMachine::new("MachineRouting".to_string(), &mut shared_data, 3);
+    state_machine.step("Canary", State::Route(0, 50), canary, None, None, None, None);
+
+    state_machine.execute_with(ExecutionOptions::default().routing_key("customer-1")).unwrap();
+    let bucket = state_machine.history().routing_bucket;
+    for _ in 0..5 {
+        state_machine.execute_with(ExecutionOptions::default().routing_key("customer-1")).unwrap();
+        assert_eq!(state_machine.history().routing_bucket, bucket);
+    }
+    assert!(state_machine.data().canary == 0 || state_machine.data().canary == 6);
+
+    // another seed reshuffles the buckets
+    let buckets: Vec<u8> = (0..3).map(|seed| {
+        state_machine.set_routing_seed(seed);
+        state_machine.execute_with(ExecutionOptions::default().routing_key("customer-1")).unwrap();
+        state_machine.history().routing_bucket
+    }).collect();
+    assert!(buckets.iter().any(|b| *b != buckets[0]), "{:?}", buckets);
+}
+
+#[test]
+pub fn reproducible() {
+    let mut shared_data = SharedData { canary: 0, baseline: 0 };
+    let mut state_machine = StateMachine::new("MachineRouting".to_string(), &mut shared_data, 3);
+    state_machine.step("Canary", State::Route(0, 50), canary, None, None, None, None);
+    state_machine.step("Baseline", State::Route(50, 100), baseline, None, None, None, None);
+
+    // executions without a routing key are spread by their seed
+    let histories: Vec<_> = (0..20).map(|_| {
+        state_machine.execute().unwrap();
+        state_machine.history().clone()
+    }).collect();
+    assert!(histories.iter().any(|history| history.routing_bucket != histories[0].routing_bucket));
+
+    for recorded in &histories {
+        state_machine.execute_with(ExecutionOptions::reproducing(recorded)).unwrap();
+        let history = state_machine.history();
+        assert_eq!(history.seed, recorded.seed);
+        assert_eq!(history.routing_bucket, recorded.routing_bucket);
+        assert_eq!(history.path(), recorded.path());
+    }
+
+    state_machine.execute_with(ExecutionOptions::default().seed(42)).unwrap();
+    let bucket = state_machine.history().routing_bucket;
+    state_machine.execute_with(ExecutionOptions::default().seed(42)).unwrap();
+    assert_eq!(state_machine.history().routing_bucket, bucket);
+}
+
+fn first_run(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.baseline += 1;
+    match data.baseline {
+        1 => Err(Box::new(StateMachineError { message: String::from("NextFailed") })),
+        _ => Ok(()),
+    }
+}
+
+#[test]
+pub fn rerun() {
+    let mut shared_data = SharedData { canary: 0, baseline: 0 };
+    let mut state_machine = StateMachine::new("MachineRouting".to_string(), &mut shared_data, 3);
+    state_machine.step("Canary", State::Task, canary, Some(first_run), None, None, None);
+    state_machine.step("Baseline", State::Task, canary, None, None, None, None);
+
+    let err = state_machine.execute().unwrap_err();
+    assert!(matches!(err, ExecutionError::NodeFailed { ref node, ref error } if node == "Canary" && error == "NextFailed"));
+
+    // the error of the previous execution does not leak into the next one
+    state_machine.execute().unwrap();
+    assert_eq!(state_machine.history().path(), vec!["Canary", "Baseline"]);
+    assert!(state_machine.history().succeeded());
+    assert_eq!(state_machine.data().canary, 2);
+}
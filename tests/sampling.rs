@@ -0,0 +1,92 @@
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State, ExecutionOptions}, data::DeserializeStateData, sampling::{RetainedExecution, RetentionReason, SamplingPolicy}};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  counter: i16,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+static FAIL: AtomicBool = AtomicBool::new(false);
+
+fn increment(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.counter += 1;
+    if FAIL.load(Ordering::SeqCst) {
+        return Err("Boom".into());
+    }
+    Ok(())
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = SharedData { counter: 0 };
+    let mut state_machine = StateMachine::new("MachineSampling".to_string(), &mut shared_data, 1);
+    state_machine.step("Increment", State::Task, increment, None, None, None, None);
+    state_machine.enable_snapshots();
+
+    // every execution is kept without a policy
+    state_machine.execute().unwrap();
+    assert!(matches!(state_machine.retained_execution(), RetainedExecution::Full { reason: RetentionReason::Sampled, .. }));
+
+    state_machine.set_sampling_policy(SamplingPolicy { slow: None, success_rate: 0.0 });
+    state_machine.execute_with(ExecutionOptions::default().tag("customer", "acme")).unwrap();
+    assert!(state_machine.history().events[0].data_before.is_none());
+    match state_machine.retained_execution() {
+        RetainedExecution::Summary(summary) => {
+            assert_eq!(summary.machine_id, "MachineSampling");
+            assert_eq!(summary.tags.get("customer").map(String::as_str), Some("acme"));
+            assert_eq!(summary.steps, 1);
+            assert_eq!(summary.seed, state_machine.history().seed);
+        },
+        retained => panic!("expected a summary, got {:?}", retained),
+    }
+
+    // failed executions keep their snapshots
+    FAIL.store(true, Ordering::SeqCst);
+    assert!(state_machine.execute().is_err());
+    FAIL.store(false, Ordering::SeqCst);
+    assert!(state_machine.history().events[0].data_before.is_some());
+    match state_machine.retained_execution() {
+        RetainedExecution::Full { reason, history } => {
+            assert_eq!(reason, RetentionReason::Failed);
+            assert_eq!(history.error.as_deref(), Some("Boom"));
+        },
+        retained => panic!("expected the full history, got {:?}", retained),
+    }
+
+    // slow executions are kept
+    state_machine.set_sampling_policy(SamplingPolicy { slow: Some(Duration::ZERO), success_rate: 0.0 });
+    state_machine.execute().unwrap();
+    assert!(matches!(state_machine.retained_execution(), RetainedExecution::Full { reason: RetentionReason::Slow, .. }));
+}
+
+#[test]
+pub fn sample_of_successes() {
+    let mut shared_data = SharedData { counter: 0 };
+    let mut state_machine = StateMachine::new("MachineSample".to_string(), &mut shared_data, 1);
+    state_machine.step("Increment", State::Task, increment, None, None, None, None);
+    let policy = SamplingPolicy { slow: None, success_rate: 0.25 };
+
+    let mut sampled = 0;
+    for seed in 0..1000 {
+        state_machine.execute_with(ExecutionOptions { seed: Some(seed), ..Default::default() }).unwrap();
+        if policy.reason(state_machine.history()).is_some() {
+            sampled += 1;
+            // the same execution is always sampled
+            assert_eq!(policy.reason(state_machine.history()), Some(RetentionReason::Sampled));
+        }
+    }
+    assert!((200..300).contains(&sampled), "sampled {} executions out of 1000", sampled);
+}
@@ -0,0 +1,91 @@
+use std::error::Error;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State, ExecutionOptions}, data::DeserializeStateData, history::EventOutcome};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  counter: i16,
+  charged: bool,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn increment(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.counter += 1;
+    Ok(())
+}
+
+fn double(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.counter *= 2;
+    Ok(())
+}
+
+fn charge(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.charged = true;
+    Ok(())
+}
+
+#[test]
+pub fn main() {
+    let mut live_data = SharedData { counter: 3, charged: false };
+    let mut live = StateMachine::new("MachineLive".to_string(), &mut live_data, 1);
+    live.step("Count", State::Task, increment, None, None, None, None);
+    live.step("Charge", State::Task, charge, None, None, None, None);
+
+    let mut shadow_data = SharedData { counter: 0, charged: false };
+    let mut shadow = StateMachine::new("MachineShadow".to_string(), &mut shadow_data, 1);
+    shadow.step("Count", State::Task, increment, None, None, None, None);
+    // the stub does not charge anything
+    shadow.step("Charge", State::Task, StateMachine::okay, None, None, None, None);
+
+    let (result, report) = live.execute_with_shadow(ExecutionOptions::default(), &mut shadow);
+    assert!(result.is_ok());
+    assert_eq!(report.divergence(), None);
+    assert_eq!(report.differences.len(), 1);
+    assert_eq!(report.differences[0].path, "$.charged");
+
+    // a new version diverging from the live one
+    let mut shadow = StateMachine::new("MachineShadowV2".to_string(), &mut shadow_data, 1);
+    shadow.step("Count", State::Task, double, None, None, None, None);
+    shadow.step("Validate", State::Task, StateMachine::error, None, None, None, None);
+    let (result, report) = live.execute_with_shadow(ExecutionOptions::default(), &mut shadow);
+    assert!(result.is_ok());
+    assert!(!report.matches());
+    assert_eq!(report.divergence(), Some(1));
+    assert_eq!(report.shadow_path[1], (String::from("Validate"), EventOutcome::Failed(String::from("STATE.FAILED"))));
+    assert_eq!(report.live_error, None);
+    assert!(report.shadow_error.is_some());
+    assert!(report.to_string().contains("paths diverge at step 1"));
+
+    // the live execution is not affected by its shadow
+    assert_eq!(live_data.counter, 5);
+    assert!(live_data.charged);
+    assert_eq!(shadow_data.counter, 8);
+}
+
+#[test]
+pub fn routes() {
+    let mut live_data = SharedData { counter: 0, charged: false };
+    let mut live = StateMachine::new("MachineLive".to_string(), &mut live_data, 1);
+    live.step("Count", State::Route(0, 50), increment, None, None, None, None);
+    live.step("Double", State::Route(50, 100), double, None, None, None, None);
+
+    // neither a routing key nor a seed, the shadow takes the route drawn for the live execution
+    for _ in 0..20 {
+        let mut shadow_data = SharedData { counter: 0, charged: false };
+        let mut shadow = StateMachine::new("MachineShadow".to_string(), &mut shadow_data, 1);
+        shadow.step("Count", State::Route(0, 50), increment, None, None, None, None);
+        shadow.step("Double", State::Route(50, 100), double, None, None, None, None);
+        let (result, report) = live.execute_with_shadow(ExecutionOptions::default(), &mut shadow);
+        assert!(result.is_ok());
+        assert!(report.matches(), "{}", report);
+    }
+}
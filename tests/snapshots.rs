@@ -0,0 +1,71 @@
+
+use std::error::Error;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State, ErrorBlock}, data::DeserializeStateData, history::Snapshot, error::StateMachineError};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  counter: i16,
+  status: String,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn add(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.counter += 10;
+    Ok(())
+}
+
+fn corrupt(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.status = String::from("wrong");
+    Err(Box::new(StateMachineError { message: String::from("Failed") }))
+}
+
+fn recover(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.counter *= 2;
+    Ok(())
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = SharedData { counter: 1, status: String::from("new") };
+    let mut state_machine = StateMachine::new("MachineSnapshots".to_string(), &mut shared_data, 3);
+    state_machine.enable_snapshots();
+
+    let catch = vec![ErrorBlock { error_equals: vec![String::from("Failed")], next: recover }];
+    state_machine.step("NodeA", State::Task, add, None, None, None, None);
+    state_machine.step("NodeB", State::Task, corrupt, None, Some(catch), None, None);
+    state_machine.step("NodeC", State::Task, add, None, None, None, None);
+    state_machine.execute().unwrap();
+
+    let history = state_machine.history();
+    let before: SharedData = history.data_at("NodeB", Snapshot::Before).unwrap();
+    assert_eq!((before.counter, before.status.as_str()), (11, "new"));
+    // the data after a step includes the changes of the catch block
+    let after: SharedData = history.data_at("NodeB", Snapshot::After).unwrap();
+    assert_eq!((after.counter, after.status.as_str()), (22, "wrong"));
+    let after: SharedData = history.data_at("NodeC", Snapshot::After).unwrap();
+    assert_eq!(after.counter, 32);
+
+    let err = history.data_at::<SharedData>("NodeD", Snapshot::Before).unwrap_err();
+    assert_eq!(err.to_string(), "no snapshot recorded for step NodeD");
+}
+
+#[test]
+pub fn disabled() {
+    let mut shared_data = SharedData { counter: 1, status: String::from("new") };
+    let mut state_machine = StateMachine::new("MachineSnapshots".to_string(), &mut shared_data, 3);
+    state_machine.step("NodeA", State::Task, add, None, None, None, None);
+    state_machine.execute().unwrap();
+
+    assert!(state_machine.history().data_at::<SharedData>("NodeA", Snapshot::Before).is_err());
+    assert_eq!(state_machine.history().events[0].data_before, None);
+}
@@ -0,0 +1,85 @@
+use std::error::Error;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{thread, time::{Duration, Instant}};
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State}, data::DeserializeStateData, watchdog::StallEvent};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  counter: i16,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+static STALLS: AtomicUsize = AtomicUsize::new(0);
+
+fn on_stall(event: &StallEvent) {
+    assert_eq!(event.node, "Slow");
+    STALLS.fetch_add(1, Ordering::SeqCst);
+}
+
+fn slow(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    thread::sleep(Duration::from_millis(400));
+    data.counter += 1;
+    Ok(())
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = SharedData { counter: 0 };
+    let mut state_machine = StateMachine::new("MachineStall".to_string(), &mut shared_data, 3);
+    state_machine.set_stall_timeout(Duration::from_millis(100), Some(on_stall));
+    state_machine.step("Fast", State::Task, StateMachine::okay, None, None, None, None);
+    state_machine.step("Slow", State::Task, slow, None, None, None, None);
+    state_machine.step("Wait", State::Sleep(1), StateMachine::okay, None, None, None, None);
+    state_machine.execute().unwrap();
+
+    // a single report for the slow step, the sleep is an expected wait
+    assert_eq!(STALLS.load(Ordering::SeqCst), 1);
+    let stalls = &state_machine.history().stalls;
+    assert_eq!(stalls.len(), 1);
+    assert_eq!((stalls[0].machine_id.as_str(), stalls[0].node.as_str()), ("MachineStall", "Slow"));
+    assert!(stalls[0].stalled_for >= Duration::from_millis(100));
+}
+
+static STARTED: Mutex<Option<Instant>> = Mutex::new(None);
+
+// A hook slower than the rest of the execution
+fn slow_hook(_: &StallEvent) {
+    thread::sleep(Duration::from_millis(600));
+}
+
+fn stuck(_: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    thread::sleep(Duration::from_millis(200));
+    Ok(())
+}
+
+fn next(_: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    *STARTED.lock().unwrap() = Some(Instant::now());
+    Ok(())
+}
+
+#[test]
+pub fn slow_hook_does_not_block() {
+    let mut shared_data = SharedData { counter: 0 };
+    let mut state_machine = StateMachine::new("MachineStallHook".to_string(), &mut shared_data, 3);
+    state_machine.set_stall_timeout(Duration::from_millis(50), Some(slow_hook));
+    state_machine.step("Stuck", State::Task, stuck, None, None, None, None);
+    state_machine.step("Next", State::Task, next, None, None, None, None);
+
+    let started = Instant::now();
+    state_machine.execute().unwrap();
+    // the next step starts while the hook is still running
+    let next_started = STARTED.lock().unwrap().unwrap();
+    assert!(next_started.duration_since(started) < Duration::from_millis(500));
+    assert_eq!(state_machine.history().stalls.len(), 1);
+}
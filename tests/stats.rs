@@ -0,0 +1,70 @@
+use std::error::Error;
+use std::sync::{Arc, OnceLock};
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State}, data::DeserializeStateData, batch::{run_batch, BatchOptions}, stats::{ExecutorStats, StatsSnapshot}};
+
+// Define the struct representing the shared data
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SharedData {
+  // the gauges observed from inside the step
+  #[serde(skip)]
+  seen: Option<StatsSnapshot>,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+static MACHINE_STATS: OnceLock<Arc<ExecutorStats>> = OnceLock::new();
+static BATCH_STATS: OnceLock<Arc<ExecutorStats>> = OnceLock::new();
+
+fn observe_machine(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.seen = MACHINE_STATS.get().map(|stats| stats.stats());
+    Ok(())
+}
+
+fn observe_batch(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.seen = BATCH_STATS.get().map(|stats| stats.stats());
+    Ok(())
+}
+
+#[test]
+pub fn main() {
+    let stats = MACHINE_STATS.get_or_init(ExecutorStats::shared);
+    let mut shared_data = SharedData::default();
+    let mut state_machine = StateMachine::new("MachineStats".to_string(), &mut shared_data, 1);
+    state_machine.set_executor_stats(stats.clone());
+    state_machine.step("Observe", State::Task, observe_machine, None, None, None, None);
+    state_machine.execute().unwrap();
+
+    let seen = shared_data.seen.unwrap();
+    assert_eq!(seen.running, 1);
+    assert_eq!(seen.running_by_machine.get("MachineStats"), Some(&1));
+    assert_eq!(seen.in_flight.get("MachineStats/Observe"), Some(&1));
+    // the gauges are back to zero once the execution ended
+    assert_eq!(stats.stats(), StatsSnapshot::default());
+}
+
+#[test]
+pub fn batch() {
+    let stats = BATCH_STATS.get_or_init(ExecutorStats::shared);
+    let inputs: Vec<SharedData> = (0..4).map(|_| SharedData::default()).collect();
+    let options = BatchOptions { stats: Some(stats.clone()), ..Default::default() };
+    let report = run_batch(inputs, &options, |data| {
+        let mut state_machine = StateMachine::new("MachineStatsBatch".to_string(), data, 1);
+        state_machine.step("Observe", State::Task, observe_batch, None, None, None, None);
+        state_machine
+    }).unwrap();
+
+    // a single worker executes the inputs one after the other
+    let seen: Vec<StatsSnapshot> = report.items.into_iter().map(|item| item.data.seen.unwrap()).collect();
+    assert_eq!(seen.iter().map(|gauges| gauges.queued).collect::<Vec<_>>(), vec![3, 2, 1, 0]);
+    assert!(seen.iter().all(|gauges| gauges.workers == 1 && gauges.utilization() == 1.0));
+    assert!(seen.iter().all(|gauges| gauges.in_flight.get("MachineStatsBatch/Observe") == Some(&1)));
+    assert_eq!(stats.stats(), StatsSnapshot::default());
+}
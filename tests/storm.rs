@@ -0,0 +1,76 @@
+use std::error::Error;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::thread;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State}, data::DeserializeStateData, backoff::BackoffConfig, storm::{RetryStormGuard, StormAlert, StormConfig}};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  calls: i16,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+static ALERTS: AtomicU32 = AtomicU32::new(0);
+
+fn alert(alert: &StormAlert) {
+    assert_eq!(alert.node, "Call");
+    assert!(alert.failure_rate >= 0.5);
+    ALERTS.fetch_add(1, Ordering::SeqCst);
+}
+
+fn call(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.calls += 1;
+    Err("Http.503".into())
+}
+
+fn attempts(guard: &std::sync::Arc<RetryStormGuard>) -> u32 {
+    execute(guard).0
+}
+
+// The attempts of the step and the alerts recorded in the history
+fn execute(guard: &std::sync::Arc<RetryStormGuard>) -> (u32, Vec<StormAlert>) {
+    let mut shared_data = SharedData { calls: 0 };
+    let mut state_machine = StateMachine::new("MachineStorm".to_string(), &mut shared_data, 10);
+    state_machine.set_backoff_config(BackoffConfig { initial_delay: Duration::from_millis(1), multiplier: 1, ..Default::default() });
+    state_machine.set_retry_storm_guard(guard.clone());
+    state_machine.step("Call", State::Task, call, None, None, Some(vec!["Http.503"]), None);
+    assert!(state_machine.execute().is_err());
+    let history = state_machine.history();
+    (history.events[0].attempts, history.storm_alerts.clone())
+}
+
+#[test]
+pub fn main() {
+    let guard = RetryStormGuard::shared(StormConfig {
+        min_attempts: 4,
+        cool_down: Duration::from_millis(200),
+        on_alert: Some(alert),
+        ..Default::default()
+    });
+
+    // the retries stop once the failure rate crosses the threshold, the alert is recorded
+    let (attempts_before_pause, alerts) = execute(&guard);
+    assert_eq!(attempts_before_pause, 4);
+    assert_eq!(alerts.len(), 1);
+    assert_eq!((alerts[0].machine_id.as_str(), alerts[0].node.as_str(), alerts[0].attempts), ("MachineStorm", "Call", 4));
+    assert!(guard.is_paused("MachineStorm", "Call"));
+    // the other executions do not retry during the cool-down
+    assert_eq!(attempts(&guard), 1);
+    assert_eq!(ALERTS.load(Ordering::SeqCst), 1);
+
+    thread::sleep(Duration::from_millis(250));
+    assert!(!guard.is_paused("MachineStorm", "Call"));
+    // the failure during the cool-down counts in the next window
+    assert_eq!(attempts(&guard), 3);
+    assert_eq!(ALERTS.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,46 @@
+use std::error::Error;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State, ExecutionOptions}, data::DeserializeStateData, history::ExecutionHistory};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  counter: i16,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn add(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.counter += 1;
+    Ok(())
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = SharedData { counter: 0 };
+    let mut state_machine = StateMachine::new("MachineTags".to_string(), &mut shared_data, 3);
+    state_machine.step("NodeA", State::Task, add, None, None, None, None);
+
+    let options = ExecutionOptions::default().tag("customer", "acme").tag("region", "eu-west-1");
+    state_machine.execute_with(options).unwrap();
+    let history = state_machine.history();
+    assert_eq!(history.tag("customer"), Some("acme"));
+    assert_eq!(history.tag("region"), Some("eu-west-1"));
+    assert_eq!(history.tag("trigger"), None);
+
+    // tags survive the serialization of the history
+    let json = serde_json::to_string(history).unwrap();
+    let restored: ExecutionHistory = serde_json::from_str(&json).unwrap();
+    assert_eq!(&restored, history);
+
+    // tags are attached to a single execution
+    state_machine.execute().unwrap();
+    assert!(state_machine.history().tags.is_empty());
+}
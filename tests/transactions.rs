@@ -0,0 +1,281 @@
+use std::error::Error;
+use serde::{Deserialize, Serialize};
+use sfn_machine::machine::
+    {state::{StateMachine, State}, data::DeserializeStateData, error::{ExecutionError, StateMachineError}, coverage::Coverage,
+    definition::TransactionDefinition, analysis::{ExecutionPath, PathOutcome, PathStep}};
+
+// Define the struct representing the shared data
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedData {
+  from: i32,
+  to: i32,
+  reject: bool,
+  outbox: Vec<String>,
+  dispatched: Vec<String>,
+  rolled_back: bool,
+  notified: bool,
+}
+
+// Implement the deserialization trait for SharedData
+impl DeserializeStateData for SharedData {
+  fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+    let data: Self = serde_json::from_str(json)?;
+    Ok(data)
+  }
+}
+
+fn accounts(reject: bool) -> SharedData {
+    SharedData { from: 100, to: 0, reject, outbox: Vec::new(), dispatched: Vec::new(), rolled_back: false, notified: false }
+}
+
+fn debit(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.from -= 10;
+    data.outbox.push(String::from("debited"));
+    Ok(())
+}
+
+fn credit(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.to += 10;
+    data.outbox.push(String::from("credited"));
+    if data.reject {
+        return Err(Box::new(StateMachineError { message: String::from("Rejected") }));
+    }
+    Ok(())
+}
+
+fn dispatch(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.dispatched.append(&mut data.outbox);
+    Ok(())
+}
+
+fn compensate(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.rolled_back = true;
+    Ok(())
+}
+
+fn notify(data: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    data.notified = true;
+    Ok(())
+}
+
+fn transfer<'a>(data: &'a mut SharedData) -> StateMachine<'a, SharedData> {
+    let mut state_machine = StateMachine::new("MachineTransaction".to_string(), data, 1);
+    state_machine.step("Debit", State::Task, debit, None, None, None, None);
+    state_machine.step("Credit", State::Task, credit, None, None, None, None);
+    state_machine.step("Notify", State::Task, notify, None, None, None, None);
+    state_machine.transaction("Transfer", "Debit", "Credit", compensate, Some(dispatch)).unwrap();
+    state_machine
+}
+
+#[test]
+pub fn main() {
+    let mut shared_data = accounts(false);
+    let mut state_machine = transfer(&mut shared_data);
+    state_machine.execute().unwrap();
+    assert_eq!(state_machine.history().path(), vec!["Debit", "Credit", "Transfer.Commit", "Notify"]);
+
+    let data = state_machine.data();
+    assert_eq!((data.from, data.to), (90, 10));
+    assert_eq!(data.dispatched, vec!["debited", "credited"]);
+    assert!(data.outbox.is_empty());
+    assert!(!data.rolled_back);
+    assert!(data.notified);
+}
+
+#[test]
+pub fn rollback() {
+    let mut shared_data = accounts(true);
+    let mut state_machine = transfer(&mut shared_data);
+    state_machine.execute().unwrap();
+    assert_eq!(state_machine.history().path(), vec!["Debit", "Credit", "Transfer.Rollback", "Notify"]);
+    assert_eq!(state_machine.history().events[2].causes, vec!["Rejected"]);
+
+    // the data is restored, and the side effects of the group are never dispatched
+    let data = state_machine.data();
+    assert_eq!((data.from, data.to), (100, 0));
+    assert!(data.outbox.is_empty());
+    assert!(data.dispatched.is_empty());
+    assert!(data.rolled_back);
+    assert!(data.notified);
+}
+
+fn fail(_: &mut SharedData) -> Result<(), Box<dyn Error>> {
+    Err(Box::new(StateMachineError { message: String::from("Compensation failed") }))
+}
+
+fn solvent(data: &SharedData) -> Result<(), String> {
+    match data.to > 5 {
+        true => Err(format!("{} credited", data.to)),
+        false => Ok(()),
+    }
+}
+
+#[test]
+pub fn invariant_violated() {
+    let mut shared_data = accounts(false);
+    let mut state_machine = transfer(&mut shared_data);
+    state_machine.add_invariant("solvent", solvent);
+
+    let err = state_machine.execute().unwrap_err();
+    assert!(matches!(err, ExecutionError::InvariantViolated { ref node, .. } if node == "Credit"));
+    assert_eq!(state_machine.history().path(), vec!["Debit", "Credit"]);
+    let data = state_machine.data();
+    assert_eq!((data.from, data.to), (90, 10));
+    assert!(!data.rolled_back);
+}
+
+#[test]
+pub fn invalid_groups() {
+    let mut shared_data = accounts(true);
+    let mut state_machine = transfer(&mut shared_data);
+    let err = state_machine.transaction("Again", "Credit", "Notify", compensate, None).unwrap_err();
+    assert_eq!(err.message, "Transaction Again overlaps transaction Transfer");
+    let err = state_machine.transaction("Missing", "Notify", "Audit", compensate, None).unwrap_err();
+    assert_eq!(err.message, "Node ID not found: Audit");
+    let err = state_machine.transaction("Backwards", "Notify", "Debit", compensate, None).unwrap_err();
+    assert_eq!(err.message, "Transaction Backwards: step Notify comes after step Debit");
+
+    // the groups reaching the end step would never commit
+    let mut shared_data = accounts(true);
+    let mut state_machine = StateMachine::new("MachineTransaction".to_string(), &mut shared_data, 1);
+    state_machine.step("Debit", State::Task, debit, None, None, None, None);
+    state_machine.step("Stop", State::Task, notify, None, None, None, Some(true));
+    state_machine.step("Credit", State::Task, credit, None, None, None, None);
+    let err = state_machine.transaction("Transfer", "Debit", "Stop", compensate, None).unwrap_err();
+    assert_eq!(err.message, "Transaction Transfer reaches the end step Stop");
+    let err = state_machine.transaction("Transfer", "Debit", "Credit", compensate, None).unwrap_err();
+    assert_eq!(err.message, "Transaction Transfer reaches the end step Stop");
+    let err = state_machine.transaction("Transfer", "Credit", "Credit", compensate, None).unwrap_err();
+    assert_eq!(err.message, "Transaction Transfer reaches the end step Stop");
+
+    let mut shared_data = accounts(true);
+    let mut state_machine = StateMachine::new("MachineTransaction".to_string(), &mut shared_data, 1);
+    state_machine.step("Credit", State::Task, credit, None, None, None, None);
+    state_machine.transaction("Transfer", "Credit", "Credit", fail, None).unwrap();
+    let err = state_machine.execute().unwrap_err();
+    assert_eq!(err, ExecutionError::CatchFailed { node: String::from("Transfer"), error: String::from("Compensation failed") });
+}
+
+// Shared data whose snapshots cannot be read back
+#[derive(Debug, Serialize, Deserialize)]
+struct Unrestorable {
+  counter: i32,
+}
+
+impl DeserializeStateData for Unrestorable {
+  fn from_json(_: &str) -> Result<Self, Box<dyn Error>> {
+    Err("unsupported snapshot".into())
+  }
+}
+
+fn increment(data: &mut Unrestorable) -> Result<(), Box<dyn Error>> {
+    data.counter += 1;
+    Ok(())
+}
+
+fn refuse(_: &mut Unrestorable) -> Result<(), Box<dyn Error>> {
+    Err("Rejected".into())
+}
+
+fn restore(data: &mut Unrestorable) -> Result<(), Box<dyn Error>> {
+    data.counter = -1;
+    Ok(())
+}
+
+#[test]
+pub fn rollback_failure() {
+    let mut shared_data = Unrestorable { counter: 0 };
+    let mut state_machine = StateMachine::new("MachineTransaction".to_string(), &mut shared_data, 1);
+    state_machine.step("Increment", State::Task, increment, None, None, None, None);
+    state_machine.step("Refuse", State::Task, refuse, None, None, None, None);
+    state_machine.transaction("Transfer", "Increment", "Refuse", restore, None).unwrap();
+
+    let err = state_machine.execute().unwrap_err();
+    assert_eq!(err, ExecutionError::NodeFailed {
+        node: String::from("Transfer"),
+        error: String::from("Transaction Transfer cannot be rolled back: unsupported snapshot"),
+    });
+    let rollback = &state_machine.history().events[2];
+    assert_eq!(rollback.node, "Transfer.Rollback");
+    assert_eq!(rollback.causes, vec!["Rejected", "unsupported snapshot"]);
+    // the data is left as the failing step left it, without running the failure handler
+    assert_eq!(state_machine.data().counter, 1);
+}
+
+#[test]
+pub fn history_consumers() {
+    let mut shared_data = accounts(false);
+    let mut committed = transfer(&mut shared_data);
+    committed.enable_snapshots();
+    committed.execute().unwrap();
+    let history = committed.history().clone();
+    assert_eq!(history.events[2].transaction.as_deref(), Some("Transfer"));
+
+    // the commit is not a step, the transition to the next step is covered
+    let mut coverage = Coverage::new(committed.definition());
+    coverage.record(&history);
+    assert!(coverage.report().uncovered().is_empty(), "{:?}", coverage.report().uncovered());
+
+    // the execution is replayed without the commit
+    let report = committed.replay_with_live_handlers(&history).unwrap();
+    assert!(report.is_identical(), "{}", report);
+    assert_eq!(report.steps.len(), 3);
+
+    let mut shared_data = accounts(true);
+    let mut rolled_back = transfer(&mut shared_data);
+    rolled_back.enable_snapshots();
+    rolled_back.execute().unwrap();
+    let failed = rolled_back.history().clone();
+
+    // the executions differ by their input and the outcome of the group, not by the commit or rollback
+    let diff = history.compare(&failed);
+    let labels: Vec<&str> = diff.steps.iter().map(|step| step.label.as_str()).collect();
+    assert_eq!(labels, vec!["Debit", "Credit", "Notify"]);
+    assert_eq!(diff.divergence, Some(1));
+
+    // the rollback restores the data written by the group
+    let lineage = failed.lineage();
+    assert_eq!(lineage.writers("from"), ["Debit", "Transfer.Rollback"]);
+    assert_eq!(lineage.writers("outbox"), ["Debit", "Credit", "Transfer.Rollback"]);
+}
+
+#[test]
+pub fn definition() {
+    let mut shared_data = accounts(false);
+    let state_machine = transfer(&mut shared_data);
+    let definition = state_machine.definition();
+    assert_eq!(definition.transactions, vec![TransactionDefinition {
+        name: String::from("Transfer"),
+        steps: vec![String::from("Debit"), String::from("Credit")],
+        on_commit: true,
+    }]);
+    let groups: Vec<Option<&str>> = definition.nodes.iter().map(|node| node.transaction.as_deref()).collect();
+    assert_eq!(groups, vec![Some("Transfer"), Some("Transfer"), None]);
+
+    // the group shows up in the diff with the same steps outside of a transaction
+    let mut other_data = accounts(false);
+    let mut plain = StateMachine::new("MachineTransaction".to_string(), &mut other_data, 1);
+    plain.step("Debit", State::Task, debit, None, None, None, None);
+    plain.step("Credit", State::Task, credit, None, None, None, None);
+    plain.step("Notify", State::Task, notify, None, None, None, None);
+    let diff = plain.definition().diff(&definition);
+    assert_eq!(diff.machine_changes[0].field, "transactions");
+    let changed: Vec<&str> = diff.changed_nodes.iter().map(|node| node.id.as_str()).collect();
+    assert_eq!(changed, vec!["Debit", "Credit"]);
+
+    // the failures of the group resume the execution after it
+    let exploration = definition.explore(100);
+    let executed = |ids: &[&str]| ids.iter().map(|id| PathStep::Executed(id.to_string())).collect::<Vec<_>>();
+    let rolled_back = ExecutionPath {
+        steps: [executed(&["Debit"]), vec![PathStep::RolledBack(String::from("Transfer"))], executed(&["Notify"])].concat(),
+        outcome: PathOutcome::Succeeded,
+    };
+    assert!(exploration.paths.contains(&rolled_back));
+    assert!(exploration.paths.iter().all(|path| !matches!(&path.outcome, PathOutcome::Failed(node) if node == "Debit" || node == "Credit")));
+    assert!(!plain.definition().equivalent(&definition, 100).is_equivalent());
+
+    // the rollback has no equivalent in the exported machine
+    let asl = definition.to_asl(|state| format!("arn:{}", state)).unwrap();
+    assert_eq!(asl["States"]["Credit"]["Comment"], "part of the transaction Transfer, which is not rolled back");
+    assert!(asl["States"]["Notify"].get("Comment").is_none());
+}